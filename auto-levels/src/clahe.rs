@@ -0,0 +1,127 @@
+//! Contrast-limited adaptive histogram equalization on luma.
+//!
+//! The capture is split into a grid of tiles and each tile's luma histogram
+//! equalized on its own, with every bin first capped at `clip_limit` times
+//! the tile's mean bin count (the excess spread evenly over all bins) so flat
+//! areas don't turn into amplified noise. Each pixel's new luma is the
+//! bilinear blend of the mappings of the four tiles whose centres surround
+//! it, so there are no seams at tile edges. The change in luma is then added
+//! to r, g and b alike.
+
+use capscr_plugin_sdk::config::Config;
+
+use capscr_plugin_sdk::px::luma;
+
+const DEFAULT_TILES: u32 = 8;
+const MAX_TILES: u32 = 64;
+const DEFAULT_CLIP_LIMIT: f32 = 2.0;
+const MAX_CLIP_LIMIT: f32 = 64.0;
+// tiles narrower than this have too few pixels for a useful histogram
+const MIN_TILE: usize = 16;
+
+pub struct Settings {
+    tiles: u32,
+    clip_limit: f32,
+}
+
+impl Settings {
+    pub fn read(config: &dyn Config) -> Settings {
+        Settings {
+            tiles: config.u32("tiles", DEFAULT_TILES, 1..=MAX_TILES),
+            clip_limit: config.f32("clip_limit", DEFAULT_CLIP_LIMIT, 1.0..=MAX_CLIP_LIMIT),
+        }
+    }
+}
+
+/// where tile `i` of `n` starts along a `len`-pixel axis.
+fn start(i: usize, n: usize, len: usize) -> usize {
+    i * len / n
+}
+
+/// per pixel along an axis of `len` split into `n` tiles: the tiles whose
+/// centres either side of it, and the second one's weight in 1/256ths.
+fn weights(len: usize, n: usize) -> Vec<(u16, u16, u32)> {
+    let centre = |i: usize| (start(i, n, len) + start(i + 1, n, len)) as f32 / 2.0;
+    (0..len)
+        .map(|p| {
+            let p = p as f32 + 0.5;
+            // the last tile whose centre is at or before p
+            let i = (0..n).rev().find(|&i| centre(i) <= p).unwrap_or(0);
+            if i + 1 >= n || centre(i) > p {
+                return (i as u16, i as u16, 0);
+            }
+            let t = (p - centre(i)) / (centre(i + 1) - centre(i));
+            (i as u16, i as u16 + 1, (t * 256.0 + 0.5) as u32)
+        })
+        .collect()
+}
+
+/// equalize `rgba` (`width` pixels a row) in place.
+pub fn equalize(rgba: &mut [u8], width: usize, settings: &Settings) {
+    let height = rgba.len() / 4 / width;
+    let tiles = settings.tiles as usize;
+    let (nx, ny) = (
+        tiles.min(width / MIN_TILE).max(1),
+        tiles.min(height / MIN_TILE).max(1),
+    );
+
+    // every tile's mapping, row-major
+    let mut maps = vec![[0u8; 256]; nx * ny];
+    for ty in 0..ny {
+        for tx in 0..nx {
+            let (x0, x1) = (start(tx, nx, width), start(tx + 1, nx, width));
+            let (y0, y1) = (start(ty, ny, height), start(ty + 1, ny, height));
+            let mut hist = [0u32; 256];
+            for y in y0..y1 {
+                for px in rgba[(y * width + x0) * 4..(y * width + x1) * 4].chunks_exact(4) {
+                    hist[luma(px) as usize] += 1;
+                }
+            }
+            maps[ty * nx + tx] = mapping(&mut hist, settings.clip_limit);
+        }
+    }
+
+    let across = weights(width, nx);
+    let down = weights(height, ny);
+    for (y, row) in rgba.chunks_exact_mut(width * 4).enumerate() {
+        let (t0, t1, wy) = down[y];
+        let (above, below) = (t0 as usize * nx, t1 as usize * nx);
+        for (px, &(i0, i1, wx)) in row.chunks_exact_mut(4).zip(&across) {
+            let v = luma(px) as usize;
+            let (i0, i1) = (i0 as usize, i1 as usize);
+            let lerp = |a: u8, b: u8, w: u32| a as u32 * (256 - w) + b as u32 * w;
+            let top = lerp(maps[above + i0][v], maps[above + i1][v], wx);
+            let bottom = lerp(maps[below + i0][v], maps[below + i1][v], wx);
+            // top, bottom in 1/256ths; the blend of them in 1/65536ths
+            let new = (top * (256 - wy) + bottom * wy + (1 << 15)) >> 16;
+            let delta = new as i32 - v as i32;
+            for c in &mut px[..3] {
+                *c = (*c as i32 + delta).clamp(0, 255) as u8;
+            }
+        }
+    }
+}
+
+/// the equalizing map of a tile's histogram: bins capped at `clip_limit` ×
+/// the mean, the excess shared out evenly, then the scaled cumulative count.
+fn mapping(hist: &mut [u32; 256], clip_limit: f32) -> [u8; 256] {
+    let n: u32 = hist.iter().sum();
+    if n == 0 {
+        return core::array::from_fn(|v| v as u8);
+    }
+    let cap = ((clip_limit * n as f32 / 256.0) as u32).max(1);
+    let mut excess = 0;
+    for h in hist.iter_mut() {
+        excess += h.saturating_sub(cap);
+        *h = (*h).min(cap);
+    }
+    let (each, extra) = (excess / 256, excess % 256);
+    for (v, h) in hist.iter_mut().enumerate() {
+        *h += each + (v < extra as usize) as u32;
+    }
+    let mut seen = 0u64;
+    core::array::from_fn(|v| {
+        seen += hist[v] as u64;
+        ((seen * 255 + n as u64 / 2) / n as u64) as u8
+    })
+}
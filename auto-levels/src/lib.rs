@@ -0,0 +1,143 @@
+//! capscr plugin: stretch captures with crushed contrast back to full range.
+//!
+//! Captures of VMs and remote desktops often never reach true black or white.
+//! `method = "levels"` measures the darkest and brightest levels — of luma, or
+//! of each channel on its own, which also takes out a colour cast — ignoring a
+//! configurable percentile at each end, and stretches them to 0 and 255 with
+//! one lookup table per channel. `method = "clahe"` equalizes luma tile by
+//! tile instead (see [`clahe`]), for captures whose dim and bright parts need
+//! different treatment.
+//!
+//! Either way a capture whose extremes already reach (close to) black and
+//! white is left untouched first, so ordinary UI captures pass through. Both
+//! need the whole histogram before the first pixel changes: no tiled variant.
+//! The rewrite happens in place (capscr 0.6+).
+
+pub mod clahe;
+
+use capscr_plugin_sdk::config::Config;
+use capscr_plugin_sdk::hist::{self, luma_histogram, range};
+use capscr_plugin_sdk::image::Header;
+use capscr_plugin_sdk::inplace::InPlaceFilter;
+use capscr_plugin_sdk::log;
+
+const DEFAULT_CLIP: f32 = 0.5; // percent, each end
+const MAX_CLIP: f32 = 10.0;
+const DEFAULT_MARGIN: u32 = 8;
+const MAX_MARGIN: u32 = 64;
+// the guard's own clip: enough to ignore a stray pixel or two, far too little
+// to ignore a UI's text
+const GUARD_CLIP: f32 = 0.0001;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Levels,
+    Clahe,
+}
+
+const METHODS: &[(&str, Method)] = &[("levels", Method::Levels), ("clahe", Method::Clahe)];
+
+/// which histograms `levels` measures.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Histogram {
+    /// one stretch from BT.601 luma, the same for every channel: hues keep
+    Luminance,
+    /// each channel stretched by its own: also neutralises a colour cast
+    PerChannel,
+}
+
+const HISTOGRAMS: &[(&str, Histogram)] = &[
+    ("luminance", Histogram::Luminance),
+    ("per-channel", Histogram::PerChannel),
+];
+
+capscr_plugin_sdk::export_in_place_filter!(AutoLevels);
+
+pub struct AutoLevels {
+    width: usize,
+    method: Method,
+    histogram: Histogram,
+    // fractions of the pixels clipped at the dark and bright ends
+    clip: (f32, f32),
+    margin: u32,
+    clahe: clahe::Settings,
+}
+
+impl InPlaceFilter for AutoLevels {
+    fn begin(header: &Header, config: &dyn Config) -> Option<Self> {
+        if let Some(modes) = config.modes("only_modes") {
+            if !modes.contains(&header.mode) {
+                return None;
+            }
+        }
+        Some(AutoLevels {
+            width: header.width as usize,
+            method: config.choice("method", Method::Levels, METHODS),
+            histogram: config.choice("histogram", Histogram::Luminance, HISTOGRAMS),
+            clip: (
+                config.f32("clip_low", DEFAULT_CLIP, 0.0..=MAX_CLIP) / 100.0,
+                config.f32("clip_high", DEFAULT_CLIP, 0.0..=MAX_CLIP) / 100.0,
+            ),
+            margin: config.u32("full_range_margin", DEFAULT_MARGIN, 0..=MAX_MARGIN),
+            clahe: clahe::Settings::read(config),
+        })
+    }
+
+    fn rows(&mut self, _y: u32, rgba: &mut [u8]) {
+        let hists = match (self.method, self.histogram) {
+            (Method::Levels, Histogram::PerChannel) => channel_histograms(rgba),
+            _ => vec![luma_histogram(rgba)],
+        };
+        let n = (rgba.len() / 4) as u32;
+        let full = hists.iter().all(|h| {
+            let (black, white) = range(h, n, (GUARD_CLIP, GUARD_CLIP));
+            black <= self.margin && white >= 255 - self.margin
+        });
+        if full {
+            log::info("auto-levels: already full range, left untouched");
+            return;
+        }
+        match self.method {
+            Method::Levels => {
+                let ranges: Vec<_> = hists.iter().map(|h| range(h, n, self.clip)).collect();
+                if ranges
+                    .iter()
+                    .any(|&(black, white)| white < black + hist::MIN_RANGE)
+                {
+                    log::info("auto-levels: too flat to stretch, left untouched");
+                    return;
+                }
+                log::info(&format!("auto-levels: stretching {ranges:?} to 0..=255"));
+                let luts: Vec<[u8; 256]> = ranges.iter().map(|&(b, w)| stretch(b, w)).collect();
+                // one table for all three channels, or one each
+                let lut = |c: usize| &luts[c.min(luts.len() - 1)];
+                for px in rgba.chunks_exact_mut(4) {
+                    for (c, v) in px[..3].iter_mut().enumerate() {
+                        *v = lut(c)[*v as usize];
+                    }
+                }
+            }
+            Method::Clahe => clahe::equalize(rgba, self.width, &self.clahe),
+        }
+    }
+}
+
+fn channel_histograms(rgba: &[u8]) -> Vec<[u32; 256]> {
+    let mut hists = vec![[0; 256]; 3];
+    for px in rgba.chunks_exact(4) {
+        for (hist, &v) in hists.iter_mut().zip(px) {
+            hist[v as usize] += 1;
+        }
+    }
+    hists
+}
+
+/// `black..=white` stretched linearly to `0..=255`, rounded; levels outside
+/// are clipped.
+fn stretch(black: u32, white: u32) -> [u8; 256] {
+    let span = white - black;
+    core::array::from_fn(|v| {
+        let v = (v as u32).clamp(black, white) - black;
+        ((v * 255 + span / 2) / span) as u8
+    })
+}
@@ -0,0 +1,261 @@
+//! capscr plugin: place each capture on a padded gradient or solid backdrop.
+//!
+//! The "pretty screenshot" step docs and marketing otherwise do in a web tool:
+//! the canvas grows by `padding` (and further, if an `aspect` is set, along
+//! the short axis), gets a linear, radial or solid background, and the capture
+//! is centred on it with optionally rounded corners and a soft shadow. The
+//! corner mask is the SDK's `px::Corners` and the shadow its `shadow::Shadow`,
+//! the same ones `rounded-corners` and `drop-shadow` use.
+
+use capscr_plugin_sdk::blur;
+use capscr_plugin_sdk::config::Config;
+use capscr_plugin_sdk::filter::Filter;
+use capscr_plugin_sdk::image::Header;
+use capscr_plugin_sdk::px::{self, over, Corners};
+use capscr_plugin_sdk::shadow::{self, Shadow};
+
+const DEFAULT_FROM: [u8; 4] = [99, 102, 241, 255]; // indigo
+const DEFAULT_TO: [u8; 4] = [236, 72, 153, 255]; // pink
+const DEFAULT_ANGLE: f32 = 135.0; // CSS degrees: top-left to bottom-right
+const DEFAULT_PADDING: u32 = 64;
+const DEFAULT_RADIUS: u32 = 12;
+const DEFAULT_SHADOW_BLUR: u32 = 32;
+const DEFAULT_SHADOW_OFFSET: (i32, i32) = (0, 12);
+const DEFAULT_SHADOW_OPACITY: f32 = 0.35;
+const DEFAULT_SHADOW_COLOR: [u8; 4] = [0, 0, 0, 255];
+const MAX_PADDING: u32 = 4096;
+const MAX_RADIUS: u32 = 4096;
+const MAX_BLUR: u32 = 256;
+const MAX_OFFSET: i32 = 1024;
+const MAX_DIM: u32 = 16384; // host rejects larger replacements; bail to match
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Background {
+    Solid,
+    /// along `angle`, CSS `linear-gradient` style
+    Linear,
+    /// from the centre out to the corners
+    Radial,
+}
+
+const BACKGROUNDS: &[(&str, Background)] = &[
+    ("solid", Background::Solid),
+    ("linear", Background::Linear),
+    ("radial", Background::Radial),
+];
+
+capscr_plugin_sdk::export_filter!(Beautify);
+
+pub struct Beautify {
+    width: u32,
+    height: u32,
+    // where the capture's top-left lands on the canvas
+    left: u32,
+    top: u32,
+    background: Background,
+    from: [u8; 4],
+    to: [u8; 4],
+    // unit gradient direction, y down
+    direction: (f32, f32),
+    corners: Corners,
+    shadow: Option<ShadowStyle>,
+    out_width: u32,
+    out_height: u32,
+}
+
+struct ShadowStyle {
+    offset: (i32, i32),
+    kernel: Vec<f32>,
+    color: [u8; 4],
+    // peak shadow alpha, 0..=1: opacity × the colour's own alpha
+    strength: f32,
+}
+
+impl Filter for Beautify {
+    fn begin(header: &Header, config: &dyn Config) -> Option<Self> {
+        let (w, h) = (header.width, header.height);
+        if let Some(modes) = config.modes("only_modes") {
+            if !modes.contains(&header.mode) {
+                return None;
+            }
+        }
+        let background = config.choice("background", Background::Linear, BACKGROUNDS);
+        let from = config.color("color", DEFAULT_FROM);
+        let to = config.color("color_to", DEFAULT_TO);
+        let angle = config.f32("angle", DEFAULT_ANGLE, 0.0..=360.0);
+        let padding = config.u32("padding", DEFAULT_PADDING, 0..=MAX_PADDING);
+        let radius = config.u32("corner_radius", DEFAULT_RADIUS, 0..=MAX_RADIUS);
+        let aspect = config.aspect("aspect", None);
+        let shadow = config.bool("shadow", true).then(|| {
+            let blur = config.u32("shadow_blur", DEFAULT_SHADOW_BLUR, 0..=MAX_BLUR);
+            let (dx, dy) = DEFAULT_SHADOW_OFFSET;
+            let ox = config.i32("shadow_offset_x", dx, -MAX_OFFSET..=MAX_OFFSET);
+            let oy = config.i32("shadow_offset_y", dy, -MAX_OFFSET..=MAX_OFFSET);
+            let opacity = config.f32("shadow_opacity", DEFAULT_SHADOW_OPACITY, 0.0..=1.0);
+            let color = config.color("shadow_color", DEFAULT_SHADOW_COLOR);
+            ShadowStyle {
+                offset: (ox, oy),
+                kernel: blur::kernel(blur::sigma_for_radius(blur as f32)),
+                color,
+                strength: opacity * color[3] as f32 / 255.0,
+            }
+        });
+
+        let mut nw = w as u64 + 2 * padding as u64;
+        let mut nh = h as u64 + 2 * padding as u64;
+        if let Some((aw, ah)) = aspect {
+            let (aw, ah) = (aw as u64, ah as u64);
+            // widen or heighten the canvas, never crop it
+            if nw * ah < nh * aw {
+                nw = (nh * aw).div_ceil(ah);
+            } else {
+                nh = (nw * ah).div_ceil(aw);
+            }
+        }
+        if nw > MAX_DIM as u64 || nh > MAX_DIM as u64 {
+            return None; // the host would reject an oversized replacement anyway
+        }
+        let (nw, nh) = (nw as u32, nh as u32);
+        let rad = angle.to_radians();
+        Some(Beautify {
+            width: w,
+            height: h,
+            left: (nw - w) / 2,
+            top: (nh - h) / 2,
+            background,
+            from,
+            to,
+            direction: (rad.sin(), -rad.cos()),
+            corners: Corners::new(w, h, radius),
+            shadow,
+            out_width: nw,
+            out_height: nh,
+        })
+    }
+
+    fn output_size(&self) -> (u32, u32) {
+        (self.out_width, self.out_height)
+    }
+
+    fn render(&mut self, rgba: &[u8], out: &mut Vec<u8>) {
+        let (w, nw) = (self.width as usize, self.out_width as usize);
+        let row_bytes = w * 4;
+        let (left, top) = (self.left as usize, self.top as usize);
+        let corners = &self.corners;
+        let opaque: Vec<bool> = shadow::opaque_rows(rgba, self.width)
+            .into_iter()
+            .zip(0..)
+            .map(|(o, y)| o && !corners.rounds_row(y))
+            .collect();
+        let shadow = self.shadow.as_ref().map(|s| {
+            let alpha = |x: usize, y: usize| {
+                px::scale_alpha(
+                    rgba[(y * w + x) * 4 + 3],
+                    corners.coverage(x as u32, y as u32),
+                )
+            };
+            let at = (
+                left as i64 + s.offset.0 as i64,
+                top as i64 + s.offset.1 as i64,
+            );
+            let map = Shadow::new(
+                (self.width, self.height),
+                alpha,
+                &opaque,
+                at,
+                (self.out_width, self.out_height),
+                &s.kernel,
+            );
+            (s, map)
+        });
+
+        let mut coverage = vec![0.0; nw];
+        let mut masked = Vec::with_capacity(row_bytes);
+        for y in 0..self.out_height as usize {
+            if let Some((_, map)) = &shadow {
+                map.row(y, &mut coverage);
+            }
+            let shade = shadow.as_ref().map(|(style, _)| (*style, &coverage[..]));
+            let start = out.len();
+            out.resize(start + nw * 4, 0);
+            let dst = &mut out[start..];
+            let cy = y.wrapping_sub(top);
+            if cy >= self.height as usize {
+                self.backdrop(y, 0, dst, shade);
+                continue;
+            }
+            let (before, rest) = dst.split_at_mut(left * 4);
+            let (under, after) = rest.split_at_mut(row_bytes);
+            self.backdrop(y, 0, before, shade);
+            self.backdrop(y, left + w, after, shade);
+            let mut src = &rgba[cy * row_bytes..][..row_bytes];
+            if opaque[cy] {
+                // nothing shows through, so no backdrop to draw
+                under.copy_from_slice(src);
+                continue;
+            }
+            self.backdrop(y, left, under, shade);
+            if corners.rounds_row(cy as u32) {
+                masked.clear();
+                masked.extend_from_slice(src);
+                corners.apply(cy as u32, &mut masked);
+                src = &masked;
+            }
+            for (d, s) in under.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+                let blended = over([s[0], s[1], s[2], s[3]], [d[0], d[1], d[2], d[3]]);
+                d.copy_from_slice(&blended);
+            }
+        }
+    }
+}
+
+impl Beautify {
+    /// fill `dst` with canvas row `y` of the background from column `x0` on,
+    /// with the shadow (its style and this row's coverage) composited on top.
+    fn backdrop(
+        &self,
+        y: usize,
+        x0: usize,
+        dst: &mut [u8],
+        shadow: Option<(&ShadowStyle, &[f32])>,
+    ) {
+        let n = dst.len() / 4;
+        let (cx, cy) = (self.out_width as f32 / 2.0, self.out_height as f32 / 2.0);
+        let fy = y as f32 + 0.5 - cy;
+        let (dx, dy) = self.direction;
+        // CSS gradient line: long enough that the far corners hit 0 and 1
+        let half = (cx * dx).abs() + (cy * dy).abs();
+        let corner = (cx * cx + cy * cy).sqrt();
+        for (i, d) in dst.chunks_exact_mut(4).enumerate() {
+            let fx = (x0 + i) as f32 + 0.5 - cx;
+            let t = match self.background {
+                Background::Solid => 0.0,
+                Background::Linear => 0.5 + (fx * dx + fy * dy) / (2.0 * half),
+                Background::Radial => (fx * fx + fy * fy).sqrt() / corner,
+            };
+            d.copy_from_slice(&lerp(self.from, self.to, t.clamp(0.0, 1.0)));
+        }
+        let Some((style, coverage)) = shadow else {
+            return;
+        };
+        let [r, g, b, _] = style.color;
+        let scale = style.strength * 255.0;
+        for (d, &c) in dst.chunks_exact_mut(4).zip(&coverage[x0..x0 + n]) {
+            let a = (c * scale + 0.5) as u8;
+            if a > 0 {
+                let blended = over([r, g, b, a], [d[0], d[1], d[2], d[3]]);
+                d.copy_from_slice(&blended);
+            }
+        }
+    }
+}
+
+fn lerp(a: [u8; 4], b: [u8; 4], t: f32) -> [u8; 4] {
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t + 0.5) as u8;
+    [
+        mix(a[0], b[0]),
+        mix(a[1], b[1]),
+        mix(a[2], b[2]),
+        mix(a[3], b[3]),
+    ]
+}
@@ -0,0 +1,86 @@
+//! The built plugin.wasm under wasmtime, the way capscr runs it:
+//!
+//! - `on_capture/<res>`: one whole hook call, copies included
+//! - `guest/<res>`: only the time inside the hook
+//! - `copy/<res>`: only the host↔guest copies (blob in, reply out)
+//!
+//! Before timing, prints each plugin's peak linear memory per resolution.
+//!
+//!     cargo bench -p capscr-plugin-bench --bench hooks
+
+use std::time::{Duration, Instant};
+
+use capscr_plugin_bench::{capture, load, peak_memory, PLUGINS, RESOLUTIONS};
+use capscr_plugin_harness::{Image, Plugin};
+use capscr_plugin_sdk::image::Mode;
+use criterion::{criterion_group, criterion_main, Criterion, SamplingMode, Throughput};
+
+/// which slice of an on_capture call a group times.
+#[derive(Clone, Copy)]
+enum Part {
+    Total,
+    Guest,
+    Copy,
+}
+
+fn timed(plugin: &mut Plugin, img: &Image, iters: u64, part: Part) -> Duration {
+    let mut sum = Duration::ZERO;
+    for _ in 0..iters {
+        let guest = plugin.guest_time();
+        let start = Instant::now();
+        plugin.on_capture(img, Mode::FullScreen);
+        let total = start.elapsed();
+        let guest = plugin.guest_time() - guest;
+        sum += match part {
+            Part::Total => total,
+            Part::Guest => guest,
+            Part::Copy => total.saturating_sub(guest),
+        };
+    }
+    sum
+}
+
+fn report_peak_memory() {
+    println!("peak linear memory after one on_capture (MiB):");
+    for &(label, id, features) in PLUGINS {
+        let row: Vec<String> = RESOLUTIONS
+            .iter()
+            .map(|&(res, w, h)| {
+                let mib = peak_memory(id, features, &capture(w, h)) as f64 / (1024.0 * 1024.0);
+                format!("{res} {mib:7.1}")
+            })
+            .collect();
+        println!("  {label:<16} {}", row.join("   "));
+    }
+}
+
+fn hooks(c: &mut Criterion) {
+    report_peak_memory();
+    let parts = [
+        ("on_capture", Part::Total),
+        ("guest", Part::Guest),
+        ("copy", Part::Copy),
+    ];
+    for &(res, w, h) in RESOLUTIONS {
+        let img = capture(w, h);
+        for (name, part) in parts {
+            let mut group = c.benchmark_group(format!("{name}/{res}"));
+            group.sample_size(10).sampling_mode(SamplingMode::Flat);
+            group.throughput(Throughput::Elements(w as u64 * h as u64));
+            for &(label, id, features) in PLUGINS {
+                // the copies don't depend on how the kernel was built
+                if matches!(part, Part::Copy) && !features.is_empty() {
+                    continue;
+                }
+                let mut plugin = load(id, features);
+                group.bench_function(label, |b| {
+                    b.iter_custom(|iters| timed(&mut plugin, &img, iters, part))
+                });
+            }
+            group.finish();
+        }
+    }
+}
+
+criterion_group!(benches, hooks);
+criterion_main!(benches);
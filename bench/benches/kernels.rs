@@ -0,0 +1,50 @@
+//! Native throughput of the pixel kernels, without a wasm engine in the way.
+//!
+//!     cargo bench -p capscr-plugin-bench --bench kernels
+
+use capscr_borders::Borders;
+use capscr_downscale::Downscale;
+use capscr_plugin_bench::{capture, CONFIG, RESOLUTIONS};
+use capscr_plugin_sdk::band;
+use capscr_plugin_sdk::image::{Capture, Mode};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+fn kernels(c: &mut Criterion) {
+    for &(label, w, h) in RESOLUTIONS {
+        let img = capture(w, h);
+        let mut blob = Vec::with_capacity(12 + img.rgba.len());
+        for v in [w, h, Mode::FullScreen.to_u32()] {
+            blob.extend_from_slice(&v.to_le_bytes());
+        }
+        blob.extend_from_slice(&img.rgba);
+        let cap = Capture::parse(&blob).unwrap();
+
+        let mut group = c.benchmark_group(format!("kernel/{label}"));
+        group.sample_size(10);
+        group.throughput(Throughput::Elements(w as u64 * h as u64));
+
+        let mut rgba = img.rgba.clone();
+        group.bench_function("grayscale", |b| {
+            b.iter(|| capscr_grayscale::luma_in_place(black_box(&mut rgba)))
+        });
+
+        let mut out = Vec::new();
+        group.bench_function("downscale", |b| {
+            b.iter(|| {
+                out.clear();
+                band::apply::<Downscale>(black_box(&cap), &CONFIG, &mut out)
+            })
+        });
+
+        group.bench_function("borders", |b| {
+            b.iter(|| {
+                out.clear();
+                band::apply::<Borders>(black_box(&cap), &CONFIG, &mut out)
+            })
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, kernels);
+criterion_main!(benches);
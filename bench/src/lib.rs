@@ -0,0 +1,54 @@
+//! Shared fixtures for the criterion benchmarks in `benches/`.
+//!
+//! `kernels` times the pixel kernels natively; `hooks` runs the built
+//! plugin.wasm through the harness, splitting each call into guest time and
+//! the host↔guest copies, and prints every plugin's peak linear memory.
+
+use capscr_plugin_harness::{Image, Plugin};
+use capscr_plugin_sdk::image::Mode;
+
+/// capture sizes every benchmark runs at.
+pub const RESOLUTIONS: &[(&str, u32, u32)] = &[
+    ("1080p", 1920, 1080),
+    ("4K", 3840, 2160),
+    ("8K", 7680, 4320),
+];
+
+/// plugin variants the wasm benchmarks load: (label, id, cargo features).
+pub const PLUGINS: &[(&str, &str, &[&str])] = &[
+    ("grayscale", "grayscale", &[]),
+    ("grayscale+simd", "grayscale", &["simd"]),
+    ("downscale", "downscale", &[]),
+    ("downscale+simd", "downscale", &["simd"]),
+    ("borders", "borders", &[]),
+    ("borders+simd", "borders", &["simd"]),
+    ("drop-shadow", "drop-shadow", &[]),
+    ("beautify", "beautify", &[]),
+];
+
+/// config every run uses, so a downscale bench always has work to do even
+/// at 1080p.
+pub const CONFIG: [(&str, &str); 1] = [("max_dimension", "1280")];
+
+/// instantiate `capscr-<id>` built with `features`, configured with [`CONFIG`].
+pub fn load(id: &str, features: &[&str]) -> Plugin {
+    CONFIG
+        .iter()
+        .fold(Plugin::load_with(id, features), |p, (k, v)| p.config(k, v))
+}
+
+/// the rgba capture a benchmark at `w`×`h` feeds in: the synthetic pattern,
+/// made opaque like a real screen capture.
+pub fn capture(w: u32, h: u32) -> Image {
+    let mut img = Image::synthetic(w, h);
+    img.rgba.chunks_exact_mut(4).for_each(|px| px[3] = 255);
+    img
+}
+
+/// peak linear memory after one on_capture of a `w`×`h` capture, from a
+/// fresh instance (wasm memory never shrinks, so the final size is the peak).
+pub fn peak_memory(id: &str, features: &[&str], img: &Image) -> usize {
+    let mut plugin = load(id, features);
+    plugin.on_capture(img, Mode::FullScreen);
+    plugin.memory_size()
+}
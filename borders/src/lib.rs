@@ -1,83 +1,330 @@
-#![cfg(target_arch = "wasm32")] // wasm-only plugin; empty lib on other targets
-//! capscr plugin: add a solid border around each capture.
+//! capscr plugin: add a border around each capture.
 //!
-//! Streamlined WASM port of the original native borders plugin (solid style),
-//! driven by the v0.5 image-blob `on_capture` API. The sandbox has no
-//! filesystem, so the styling is a built-in default (the native version loaded
-//! it from TOML). Pure byte math, no dependencies — the original's extra styles
-//! (drop shadow, rounded corners, double/dashed/3-D) port the same way once a
-//! wasm toolchain is wired up to compile against the `image` crate.
-
-const BORDER: u32 = 8; // thickness in px on every side
-const COLOR: [u8; 4] = [40, 40, 40, 255]; // opaque dark gray
+//! Streamlined WASM port of the original native borders plugin, driven by the
+//! v0.5 image-blob `on_capture` API. `style`, `size`, `color`, `padding` and
+//! `corner_radius` come from `config.toml` through `config_get`; unset or
+//! invalid values fall back to the old built-in look (8 px solid dark gray,
+//! square). Pure byte math, no dependencies.
+//!
+//! Every output row depends only on its own input row, so it's a
+//! [`BandFilter`] and also exports the tiled hook. Build with `--features simd`
+//! for the simd128 canvas fill (bit-identical output).
+
+use capscr_plugin_sdk::band::BandFilter;
+use capscr_plugin_sdk::config::Config;
+use capscr_plugin_sdk::image::Header;
+use capscr_plugin_sdk::px;
+
+const DEFAULT_SIZE: u32 = 8; // thickness in px on every side
+const DEFAULT_COLOR: [u8; 4] = [40, 40, 40, 255]; // opaque dark gray
+const TRANSPARENT: [u8; 4] = [0; 4];
 const MAX_DIM: u32 = 16384; // host rejects larger replacements; bail to match
 
-// host writes the input [w][h][mode][rgba] blob here; OUTPUT holds the bordered
-// replacement. capscr serialises hook calls per plugin, so reusing buffers is
-// safe and leak-free.
-static mut SCRATCH: Vec<u8> = Vec::new();
-static mut OUTPUT: Vec<u8> = Vec::new();
-
-#[no_mangle]
-pub extern "C" fn capscr_alloc(size: i32) -> i32 {
-    let size = size.max(0) as usize;
-    // SAFETY: single-threaded wasm; calls are serialised by the host store lock
-    unsafe {
-        let buf = &mut *core::ptr::addr_of_mut!(SCRATCH);
-        buf.clear();
-        buf.reserve(size);
-        buf.as_mut_ptr() as i32
+/// the CSS `border-style` set the native plugin offered.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Solid,
+    Double,
+    Dashed,
+    Dotted,
+    Groove,
+    Ridge,
+    Inset,
+    Outset,
+}
+
+const STYLES: &[(&str, Style)] = &[
+    ("solid", Style::Solid),
+    ("double", Style::Double),
+    ("dashed", Style::Dashed),
+    ("dotted", Style::Dotted),
+    ("groove", Style::Groove),
+    ("ridge", Style::Ridge),
+    ("inset", Style::Inset),
+    ("outset", Style::Outset),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+capscr_plugin_sdk::export_band_filter!(Borders);
+
+pub struct Borders {
+    width: u32,
+    height: u32,
+    style: Style,
+    // border thickness, then the transparent gap between it and the capture
+    size: u32,
+    padding: u32,
+    color: [u8; 4],
+    // outer corner radius, already clamped to half the shorter output side
+    radius: u32,
+    // with no padding, the arc the border's inner edge follows (radius - size,
+    // capped to the capture) where it's still rounded; 0 otherwise
+    inner_radius: u32,
+    inner: Vec<u8>,
+    out_width: u32,
+    out_height: u32,
+    // next output row to emit
+    y: u32,
+}
+
+impl BandFilter for Borders {
+    fn begin(header: &Header, config: &dyn Config) -> Option<Self> {
+        let (w, h) = (header.width, header.height);
+        let style = config.choice("style", Style::Solid, STYLES);
+        let size = config.u32("size", DEFAULT_SIZE, 0..=MAX_DIM / 2);
+        let padding = config.u32("padding", 0, 0..=MAX_DIM / 2);
+        let color = config.color("color", DEFAULT_COLOR);
+        let radius = config.u32("corner_radius", 0, 0..=MAX_DIM / 2);
+
+        let frame = size + padding;
+        let nw = w.checked_add(frame * 2)?;
+        let nh = h.checked_add(frame * 2)?;
+        if nw > MAX_DIM || nh > MAX_DIM {
+            return None; // the host would reject an oversized replacement anyway
+        }
+        if frame == 0 && radius == 0 {
+            return None; // nothing to draw
+        }
+        let radius = radius.min(nw.min(nh) / 2);
+        let inner_radius = match radius.checked_sub(size) {
+            Some(r) if padding == 0 => r.min(w.min(h) / 2),
+            _ => 0,
+        };
+        Some(Borders {
+            width: w,
+            height: h,
+            style,
+            size,
+            padding,
+            color,
+            radius,
+            inner_radius,
+            inner: px::corner_mask(inner_radius),
+            out_width: nw,
+            out_height: nh,
+            y: 0,
+        })
+    }
+
+    fn output_size(&self) -> (u32, u32) {
+        (self.out_width, self.out_height)
+    }
+
+    fn band(&mut self, rows: &[u8], out: &mut Vec<u8>) {
+        let frame = self.size + self.padding;
+        while self.y < frame {
+            self.frame_row(out);
+        }
+        for row in rows.chunks_exact(self.width as usize * 4) {
+            let start = out.len();
+            let (size, padding) = (self.size as usize, self.padding as usize);
+            px::fill_extend(out, self.color, size);
+            px::fill_extend(out, TRANSPARENT, padding);
+            out.extend_from_slice(row);
+            px::fill_extend(out, TRANSPARENT, padding);
+            px::fill_extend(out, self.color, size);
+            self.fill_inner_corners(&mut out[start..]);
+            self.paint(&mut out[start..]);
+            self.round_corners(&mut out[start..]);
+            self.y += 1;
+        }
+    }
+
+    fn finish(&mut self, out: &mut Vec<u8>) {
+        // the frame rows below the capture
+        while self.y < self.out_height {
+            self.frame_row(out);
+        }
     }
 }
 
-/// on_capture: 0 = continue unchanged, >0 = packed (ptr<<32)|len of a
-/// replacement [w:u32][h:u32][rgba] blob.
-#[no_mangle]
-pub extern "C" fn capscr_on_capture(ptr: i32, len: i32) -> i64 {
-    if ptr < 0 || len < 12 {
-        return 0;
+impl Borders {
+    /// emit output row `self.y`, which lies above or below the capture: solid
+    /// border, or border at the sides with transparent padding between.
+    fn frame_row(&mut self, out: &mut Vec<u8>) {
+        let start = out.len();
+        let (y, nw, size) = (self.y, self.out_width as usize, self.size);
+        if y < size || y >= self.out_height - size {
+            px::fill_extend(out, self.color, nw);
+        } else {
+            px::fill_extend(out, self.color, size as usize);
+            px::fill_extend(out, TRANSPARENT, nw - 2 * size as usize);
+            px::fill_extend(out, self.color, size as usize);
+        }
+        self.paint(&mut out[start..]);
+        self.round_corners(&mut out[start..]);
+        self.y += 1;
     }
-    let input = unsafe { core::slice::from_raw_parts(ptr as *const u8, len as usize) };
-    let w = u32::from_le_bytes([input[0], input[1], input[2], input[3]]);
-    let h = u32::from_le_bytes([input[4], input[5], input[6], input[7]]);
-    // input[8..12] is the capture mode — unused here
-    let rgba = &input[12..];
-    let expected = (w as usize)
-        .saturating_mul(h as usize)
-        .saturating_mul(4);
-    if rgba.len() != expected || expected == 0 {
-        return 0;
+
+    /// redraw the border pixels of output row `self.y` (laid down solid) in
+    /// the configured style.
+    fn paint(&self, row: &mut [u8]) {
+        if self.style == Style::Solid {
+            return;
+        }
+        let (y, nw, size) = (self.y, self.out_width, self.size);
+        let mut put = |x: u32| row[x as usize * 4..][..4].copy_from_slice(&self.style_px(x, y));
+        if y < size || y >= self.out_height - size {
+            (0..nw).for_each(&mut put);
+        } else {
+            (0..size).chain(nw - size..nw).for_each(&mut put);
+        }
     }
 
-    let nw = w + BORDER * 2;
-    let nh = h + BORDER * 2;
-    if nw > MAX_DIM || nh > MAX_DIM {
-        return 0; // the host would reject an oversized replacement anyway
+    /// the colour of border pixel (x, y). Each pixel belongs to the side whose
+    /// outer edge is nearest (top/bottom win ties), which splits the corner
+    /// squares along their diagonals the way CSS does.
+    fn style_px(&self, x: u32, y: u32) -> [u8; 4] {
+        let (s, c) = (self.size, self.color);
+        let (dt, db) = (y, self.out_height - 1 - y);
+        let (dl, dr) = (x, self.out_width - 1 - x);
+        let (side, across) = [
+            (Side::Top, dt),
+            (Side::Bottom, db),
+            (Side::Left, dl),
+            (Side::Right, dr),
+        ]
+        .into_iter()
+        .min_by_key(|&(_, d)| d)
+        .unwrap();
+        let corner = dt.min(db) < s && dl.min(dr) < s;
+        let along = if matches!(side, Side::Top | Side::Bottom) {
+            x
+        } else {
+            y
+        };
+        // top/left are the "lit from below-right" sides of an inset border
+        let sunk = matches!(side, Side::Top | Side::Left);
+        match self.style {
+            Style::Solid => c,
+            Style::Double => {
+                // two lines a third of the size each, the gap between see-through
+                let line = (s + 1) / 3;
+                if s < 3 || across < line || across >= s - line {
+                    c
+                } else {
+                    TRANSPARENT
+                }
+            }
+            // dashes 2×size long with size gaps; the corner squares stay solid
+            Style::Dashed => {
+                if corner || (along / s) % 3 != 2 {
+                    c
+                } else {
+                    TRANSPARENT
+                }
+            }
+            // size-wide round dots every 2×size; one centred in each corner square
+            Style::Dotted => {
+                let (u, v) = if corner {
+                    (dl.min(dr), dt.min(db))
+                } else {
+                    (along % (2 * s), across)
+                };
+                let (du, dv) = (
+                    u as f32 + 0.5 - s as f32 / 2.0,
+                    v as f32 + 0.5 - s as f32 / 2.0,
+                );
+                if u < s && du * du + dv * dv <= (s * s) as f32 / 4.0 {
+                    c
+                } else {
+                    TRANSPARENT
+                }
+            }
+            Style::Inset => shade(c, sunk),
+            Style::Outset => shade(c, !sunk),
+            // outer half inset, inner half outset — and the reverse for ridge
+            Style::Groove => shade(c, sunk == (across < s.div_ceil(2))),
+            Style::Ridge => shade(c, sunk != (across < s.div_ceil(2))),
+        }
     }
-    let row_bytes = w as usize * 4;
-    let nrow_bytes = nw as usize * 4;
-    let out_len = 8 + nrow_bytes * nh as usize;
-
-    // SAFETY: serialised calls; the host copies the bytes out before the next
-    // call clears this buffer
-    unsafe {
-        let out = &mut *core::ptr::addr_of_mut!(OUTPUT);
-        out.clear();
-        out.reserve(out_len);
-        out.extend_from_slice(&nw.to_le_bytes());
-        out.extend_from_slice(&nh.to_le_bytes());
-        // fill the whole canvas with the border colour
-        for _ in 0..(nw as usize * nh as usize) {
-            out.extend_from_slice(&COLOR);
+
+    /// scale alpha by how much of each pixel lies inside the rounded outer
+    /// edge (a one-pixel linear ramp, so the curve isn't jagged).
+    fn round_corners(&self, row: &mut [u8]) {
+        let r = self.radius;
+        let (y, nh) = (self.y, self.out_height);
+        if r == 0 || (y >= r && y < nh - r) {
+            return;
         }
-        // blit the original into the centre, row by row
-        let body = &mut out[8..];
-        for row in 0..h as usize {
-            let src = &rgba[row * row_bytes..row * row_bytes + row_bytes];
-            let dst = (row + BORDER as usize) * nrow_bytes + BORDER as usize * 4;
-            body[dst..dst + row_bytes].copy_from_slice(src);
+        let rf = r as f32;
+        let dy = if y < r {
+            rf - (y as f32 + 0.5)
+        } else {
+            (y - (nh - r)) as f32 + 0.5
+        };
+        let nw = self.out_width;
+        for i in 0..r {
+            let dx = rf - (i as f32 + 0.5);
+            let coverage = (rf - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0);
+            if coverage >= 1.0 {
+                continue;
+            }
+            for x in [i, nw - 1 - i] {
+                let a = &mut row[x as usize * 4 + 3];
+                *a = (*a as f32 * coverage + 0.5) as u8;
+            }
         }
-        let p = out.as_ptr() as i64;
-        (p << 32) | (out_len as i64 & 0xffff_ffff)
     }
+
+    /// a capture with rounded corners of its own (`rounded-corners`) sits
+    /// inside a rounded border: back the capture's corner pixels with the
+    /// border colour outside the inner arc, so no transparent wedge shows
+    /// between the two curves.
+    fn fill_inner_corners(&self, row: &mut [u8]) {
+        let (ri, cy) = (self.inner_radius, self.y - self.size);
+        let (w, h) = (self.width, self.height);
+        let dy = if cy < ri {
+            cy
+        } else if cy >= h - ri {
+            h - 1 - cy
+        } else {
+            return;
+        };
+        let mask = &self.inner[(dy * ri) as usize..][..ri as usize];
+        for (i, &coverage) in (0..ri).zip(mask) {
+            if coverage == 255 {
+                continue;
+            }
+            for x in [i, w - 1 - i] {
+                let p = &mut row[(self.size + x) as usize * 4..][..4];
+                let blended = beside([p[0], p[1], p[2], p[3]], self.color, 255 - coverage);
+                p.copy_from_slice(&blended);
+            }
+        }
+    }
+}
+
+/// `a` plus `b` scaled to `coverage`, for two shapes that share a pixel
+/// without overlapping (the capture inside an arc, the border outside it): the
+/// coverages add (up to opaque), where "over" would leave a seam along the
+/// curve.
+fn beside(a: [u8; 4], b: [u8; 4], coverage: u8) -> [u8; 4] {
+    let aa = a[3] as u32;
+    let ba = (px::scale_alpha(b[3], coverage) as u32).min(255 - aa);
+    let oa = aa + ba;
+    if oa == 0 {
+        return [0; 4];
+    }
+    let mix = |x: u8, y: u8| ((x as u32 * aa + y as u32 * ba + oa / 2) / oa) as u8;
+    [
+        mix(a[0], b[0]),
+        mix(a[1], b[1]),
+        mix(a[2], b[2]),
+        oa.min(255) as u8,
+    ]
+}
+
+/// the darker (half brightness) or lighter (half way to white) tone of `c`
+/// for the 3-D styles; alpha is kept.
+fn shade(c: [u8; 4], dark: bool) -> [u8; 4] {
+    let f = |v: u8| if dark { v / 2 } else { v + (255 - v) / 2 };
+    [f(c[0]), f(c[1]), f(c[2]), c[3]]
 }
@@ -0,0 +1,53 @@
+//! capscr plugin: log a one-line summary of every finished capture — size,
+//! mode, mean color, distinct colors, translucent share.
+//!
+//! The smallest showcase of the read-only `on_capture_inspect` hook (capscr
+//! 0.6+): it only looks at pixels, so it runs after the modifying chain with
+//! just `image = ["read"]`, and reads the blob where the host wrote it.
+
+use capscr_plugin_sdk::config::Config;
+use capscr_plugin_sdk::image::Capture;
+use capscr_plugin_sdk::inspect::Inspector;
+use capscr_plugin_sdk::log;
+
+capscr_plugin_sdk::export_inspector!(CaptureStats);
+
+pub struct CaptureStats;
+
+impl Inspector for CaptureStats {
+    fn inspect(cap: &Capture, _config: &dyn Config) {
+        log::info(&summary(cap));
+    }
+}
+
+/// e.g. `1920x1080 region: mean rgba(31, 33, 38, 255), 5821 colors, 0.0% translucent`
+pub fn summary(cap: &Capture) -> String {
+    let mut sums = [0u64; 4];
+    let mut translucent = 0u64;
+    // one bit per 24-bit rgb value (2 MiB), alpha ignored
+    let mut seen = vec![0u64; 1 << 18];
+    let mut colors = 0u32;
+    for px in cap.rgba.chunks_exact(4) {
+        for (s, &c) in sums.iter_mut().zip(px) {
+            *s += c as u64;
+        }
+        if px[3] < 255 {
+            translucent += 1;
+        }
+        let rgb = (px[0] as usize) << 16 | (px[1] as usize) << 8 | px[2] as usize;
+        let (word, bit) = (rgb >> 6, 1u64 << (rgb & 63));
+        if seen[word] & bit == 0 {
+            seen[word] |= bit;
+            colors += 1;
+        }
+    }
+    let n = (cap.rgba.len() / 4) as u64;
+    let [r, g, b, a] = sums.map(|s| s / n);
+    format!(
+        "{}x{} {}: mean rgba({r}, {g}, {b}, {a}), {colors} colors, {:.1}% translucent",
+        cap.header.width,
+        cap.header.height,
+        cap.header.mode.name(),
+        translucent as f64 * 100.0 / n as f64,
+    )
+}
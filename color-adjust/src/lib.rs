@@ -0,0 +1,168 @@
+//! capscr plugin: brightness, contrast, saturation, hue, gamma and levels.
+//!
+//! Everything that acts on one channel at a time — levels, gamma, brightness
+//! and contrast — folds into a single 256-entry table, so a pixel costs three
+//! lookups however many of them are set. Saturation and hue rotation mix the
+//! channels; together they're one 3×3 matrix in 12-bit fixed point, applied
+//! after the table only when either is set. Alpha is never touched.
+//!
+//! `auto_levels` picks the black and white points from the capture's own
+//! luma histogram, stretching a dim or washed-out remote-desktop capture back
+//! to full range, so the whole image has to be seen first: no tiled variant.
+//! The rewrite happens in place (capscr 0.6+).
+
+use capscr_plugin_sdk::config::Config;
+use capscr_plugin_sdk::hist;
+use capscr_plugin_sdk::image::Header;
+use capscr_plugin_sdk::inplace::InPlaceFilter;
+use capscr_plugin_sdk::log;
+
+const DEFAULT_AUTO_CLIP: f32 = 0.5; // percent
+const MAX_AUTO_CLIP: f32 = 10.0;
+// the matrix's fixed-point scale
+const ONE: i32 = 1 << 12;
+
+capscr_plugin_sdk::export_in_place_filter!(ColorAdjust);
+
+pub struct ColorAdjust {
+    /// black and white points, or None to measure them per capture
+    levels: Option<(u8, u8)>,
+    auto_clip: f32,
+    gamma: f32,
+    brightness: f32,
+    contrast: f32,
+    /// saturation and hue rotation, None when neither is set
+    mix: Option<[[i32; 3]; 3]>,
+}
+
+impl InPlaceFilter for ColorAdjust {
+    fn begin(header: &Header, config: &dyn Config) -> Option<Self> {
+        if let Some(modes) = config.modes("only_modes") {
+            if !modes.contains(&header.mode) {
+                return None;
+            }
+        }
+        let auto = config.bool("auto_levels", false);
+        let black = config.u32("black_point", 0, 0..=254);
+        let white = config.u32("white_point", 255, black + 1..=255);
+        let adjust = ColorAdjust {
+            levels: (!auto).then_some((black as u8, white as u8)),
+            auto_clip: config.f32("auto_clip", DEFAULT_AUTO_CLIP, 0.0..=MAX_AUTO_CLIP) / 100.0,
+            gamma: config.f32("gamma", 1.0, 0.1..=10.0),
+            brightness: config.f32("brightness", 0.0, -1.0..=1.0),
+            contrast: config.f32("contrast", 1.0, 0.0..=4.0),
+            mix: mix(
+                config.f32("saturation", 1.0, 0.0..=4.0),
+                config.f32("hue", 0.0, -180.0..=180.0),
+            ),
+        };
+        // nothing set: don't even rewrite the capture with itself
+        let identity = adjust.levels == Some((0, 255))
+            && adjust.gamma == 1.0
+            && adjust.brightness == 0.0
+            && adjust.contrast == 1.0
+            && adjust.mix.is_none();
+        (!identity).then_some(adjust)
+    }
+
+    fn rows(&mut self, _y: u32, rgba: &mut [u8]) {
+        let (black, white) = match self.levels {
+            Some(levels) => levels,
+            None => {
+                let levels = auto_levels(rgba, self.auto_clip);
+                log::info(&match levels {
+                    (0, 255) => "auto_levels: already full range".to_owned(),
+                    (b, w) => format!("auto_levels: stretching {b}..={w} to 0..=255"),
+                });
+                levels
+            }
+        };
+        let lut = self.curve(black, white);
+        match &self.mix {
+            None => {
+                for px in rgba.chunks_exact_mut(4) {
+                    px[0] = lut[px[0] as usize];
+                    px[1] = lut[px[1] as usize];
+                    px[2] = lut[px[2] as usize];
+                }
+            }
+            Some(m) => {
+                for px in rgba.chunks_exact_mut(4) {
+                    let v = [0, 1, 2].map(|c| lut[px[c] as usize] as i32);
+                    for (c, row) in m.iter().enumerate() {
+                        let mixed = row[0] * v[0] + row[1] * v[1] + row[2] * v[2];
+                        px[c] = ((mixed + ONE / 2) >> 12).clamp(0, 255) as u8;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl ColorAdjust {
+    /// the per-channel table: levels, then gamma, brightness and contrast,
+    /// each on the 0..=1 encoded value.
+    pub fn curve(&self, black: u8, white: u8) -> [u8; 256] {
+        let (black, span) = (black as f32, (white - black) as f32);
+        core::array::from_fn(|v| {
+            let t = ((v as f32 - black) / span).clamp(0.0, 1.0);
+            let t = t.powf(1.0 / self.gamma) + self.brightness;
+            let t = (t - 0.5) * self.contrast + 0.5;
+            (t.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+        })
+    }
+}
+
+/// the black and white points of `rgba`'s BT.601 luma with `clip` (a
+/// fraction) of the pixels let past each end, or 0..=255 if that range is
+/// full already or too narrow to stretch.
+pub fn auto_levels(rgba: &[u8], clip: f32) -> (u8, u8) {
+    let n = (rgba.len() / 4) as u32;
+    let (black, white) = hist::range(&hist::luma_histogram(rgba), n, (clip, clip));
+    if white < black + hist::MIN_RANGE {
+        return (0, 255);
+    }
+    (black as u8, white as u8)
+}
+
+/// saturation `s` (0 = grey, 1 = unchanged) then a hue rotation of `degrees`,
+/// as one fixed-point matrix — the feColorMatrix `saturate` and `hueRotate`
+/// forms, which keep (BT.709-weighted) luma. None when both are neutral.
+pub fn mix(s: f32, degrees: f32) -> Option<[[i32; 3]; 3]> {
+    if s == 1.0 && degrees == 0.0 {
+        return None;
+    }
+    const W: [f32; 3] = [0.213, 0.715, 0.072];
+    let sat: [[f32; 3]; 3] = core::array::from_fn(|r| {
+        core::array::from_fn(|c| W[c] * (1.0 - s) + if r == c { s } else { 0.0 })
+    });
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let hue = [
+        [
+            0.213 + cos * 0.787 - sin * 0.213,
+            0.715 - cos * 0.715 - sin * 0.715,
+            0.072 - cos * 0.072 + sin * 0.928,
+        ],
+        [
+            0.213 - cos * 0.213 + sin * 0.143,
+            0.715 + cos * 0.285 + sin * 0.140,
+            0.072 - cos * 0.072 - sin * 0.283,
+        ],
+        [
+            0.213 - cos * 0.213 - sin * 0.787,
+            0.715 - cos * 0.715 + sin * 0.715,
+            0.072 + cos * 0.928 + sin * 0.072,
+        ],
+    ];
+    let mut m: [[i32; 3]; 3] = core::array::from_fn(|r| {
+        core::array::from_fn(|c| {
+            let v: f32 = (0..3).map(|k| hue[r][k] * sat[k][c]).sum();
+            (v * ONE as f32).round() as i32
+        })
+    });
+    // every row sums to one exactly, so greys come back unchanged
+    for (r, row) in m.iter_mut().enumerate() {
+        row[r] += ONE - row.iter().sum::<i32>();
+    }
+    Some(m)
+}
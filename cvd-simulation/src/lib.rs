@@ -0,0 +1,115 @@
+//! capscr plugin: show a capture as a viewer with a colour-vision deficiency
+//! would see it.
+//!
+//! Each pixel goes to linear light, through the Machado, Oliveira & Fernandes
+//! (2009) simulation matrix for the chosen deficiency and severity (see
+//! [`machado`]), and back to sRGB; alpha is never touched. Achromatopsia is
+//! a blend toward relative luminance. With `side_by_side`, the output is
+//! twice as wide: the original on the left, the simulation on the right.
+//!
+//! Every output row depends only on its own input row, so it's a
+//! [`BandFilter`] and also exports the tiled hook.
+
+pub mod machado;
+
+use capscr_plugin_sdk::band::BandFilter;
+use capscr_plugin_sdk::config::Config;
+use capscr_plugin_sdk::image::Header;
+use capscr_plugin_sdk::log;
+use capscr_plugin_sdk::srgb::Lut;
+
+use machado::Matrix;
+
+const MAX_DIM: u32 = 16384; // host rejects larger replacements
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Deficiency {
+    /// no working L (red) cones
+    Protanopia,
+    /// no working M (green) cones
+    Deuteranopia,
+    /// no working S (blue) cones
+    Tritanopia,
+    /// no working cones at all: luminance only
+    Achromatopsia,
+}
+
+const DEFICIENCIES: &[(&str, Deficiency)] = &[
+    ("protanopia", Deficiency::Protanopia),
+    ("deuteranopia", Deficiency::Deuteranopia),
+    ("tritanopia", Deficiency::Tritanopia),
+    ("achromatopsia", Deficiency::Achromatopsia),
+];
+
+capscr_plugin_sdk::export_band_filter!(CvdSimulation);
+
+pub struct CvdSimulation {
+    width: u32,
+    height: u32,
+    side_by_side: bool,
+    matrix: Matrix,
+    lut: Box<Lut>,
+    // the previous pixel's r,g,b and result: UI is mostly runs of one colour
+    last: ([u8; 3], [u8; 3]),
+}
+
+impl BandFilter for CvdSimulation {
+    fn begin(header: &Header, config: &dyn Config) -> Option<Self> {
+        if let Some(modes) = config.modes("only_modes") {
+            if !modes.contains(&header.mode) {
+                return None;
+            }
+        }
+        let deficiency = config.choice("deficiency", Deficiency::Deuteranopia, DEFICIENCIES);
+        let severity = config.f32("severity", 1.0, 0.0..=1.0);
+        let mut side_by_side = config.bool("side_by_side", false);
+        if side_by_side && header.width > MAX_DIM / 2 {
+            log::warn("side_by_side: capture too wide to double; emitting the simulation alone");
+            side_by_side = false;
+        }
+        let matrix = match deficiency {
+            Deficiency::Protanopia => machado::at(&machado::PROTAN, severity),
+            Deficiency::Deuteranopia => machado::at(&machado::DEUTAN, severity),
+            Deficiency::Tritanopia => machado::at(&machado::TRITAN, severity),
+            Deficiency::Achromatopsia => machado::achromat(severity),
+        };
+        let lut = Box::new(Lut::new());
+        let last = ([0; 3], simulate(&lut, &matrix, [0; 3]));
+        Some(CvdSimulation {
+            width: header.width,
+            height: header.height,
+            side_by_side,
+            matrix,
+            lut,
+            last,
+        })
+    }
+
+    fn output_size(&self) -> (u32, u32) {
+        let copies = if self.side_by_side { 2 } else { 1 };
+        (self.width * copies, self.height)
+    }
+
+    fn band(&mut self, rows: &[u8], out: &mut Vec<u8>) {
+        for row in rows.chunks_exact(self.width as usize * 4) {
+            if self.side_by_side {
+                out.extend_from_slice(row);
+            }
+            for px in row.chunks_exact(4) {
+                let rgb = [px[0], px[1], px[2]];
+                if rgb != self.last.0 {
+                    self.last = (rgb, simulate(&self.lut, &self.matrix, rgb));
+                }
+                out.extend_from_slice(&self.last.1);
+                out.push(px[3]);
+            }
+        }
+    }
+}
+
+/// `rgb` as seen through `m`, applied in linear light; out-of-gamut results
+/// are clamped.
+pub fn simulate(lut: &Lut, m: &Matrix, rgb: [u8; 3]) -> [u8; 3] {
+    let l = rgb.map(|c| lut.linear(c));
+    m.map(|row| lut.encode(row[0] * l[0] + row[1] * l[1] + row[2] * l[2]))
+}
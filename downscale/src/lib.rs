@@ -1,124 +1,277 @@
-#![cfg(target_arch = "wasm32")] // wasm-only plugin; empty lib on other targets
 //! capscr plugin: downscale captures whose longest side exceeds a configured
 //! limit, so saved/uploaded images stay small. The limit is read at runtime from
-//! `config.toml` (`max_dimension`, default 1920). Box-average downscale by an
-//! integer factor — dependency-free. Demonstrates `config_get` + the v0.5
-//! image-blob `on_capture` API together. See docs/plugin-runtime.md for the ABI.
-
-const DEFAULT_MAX_DIM: u32 = 1920;
-const CONFIG_KEY: &str = "max_dimension";
-
-// the host writes the input blob ([w][h][mode][rgba]) here via capscr_alloc, and
-// reuses it for config_get's value too — so on_capture copies the input out
-// first. OUTPUT holds the downscaled replacement ([w][h][rgba]).
-static mut SCRATCH: Vec<u8> = Vec::new();
-static mut OUTPUT: Vec<u8> = Vec::new();
-
-#[no_mangle]
-pub extern "C" fn capscr_alloc(size: i32) -> i32 {
-    let size = size.max(0) as usize;
-    // SAFETY: single-threaded wasm; calls are serialised by the host store lock
-    unsafe {
-        let buf = &mut *core::ptr::addr_of_mut!(SCRATCH);
-        buf.clear();
-        buf.reserve(size);
-        buf.as_mut_ptr() as i32
-    }
+//! `config.toml` (`max_dimension`, default 1920; or width, height and
+//! megapixel caps, per capture mode if need be — see [`limits`]). Box-average
+//! downscale by an integer factor — dependency-free. Demonstrates
+//! `config_get` + the v0.5 image-blob `on_capture` API together. See
+//! docs/plugin-runtime.md for the ABI.
+//!
+//! The box average runs one input row at a time into a row of per-block sums,
+//! so it's a [`BandFilter`]: with the tiled hook the guest only ever holds one
+//! band plus one output row, however tall the capture. Build with
+//! `--features simd` for the simd128 row kernel (bit-identical output).
+//!
+//! Setting `filter` swaps the integer factor for an exact fit: the image comes
+//! out exactly as large as the tightest limit allows, resampled with the
+//! chosen kernel (see [`resample`]). Still row-streamed, so still tiled.
+//!
+//! `linear_light = true` averages in linear light with premultiplied alpha
+//! (the SDK's [`Lut`]) on either path, so thin text keeps its weight
+//! and transparent edges don't pick up dark fringes.
+
+pub mod limits;
+pub mod resample;
+
+use capscr_plugin_sdk::band::BandFilter;
+use capscr_plugin_sdk::config::Config;
+use capscr_plugin_sdk::image::Header;
+use capscr_plugin_sdk::srgb::Lut;
+
+use limits::Limits;
+use resample::{Kernel, Resampler};
+
+// unset: the integer box path
+const FILTERS: &[(&str, Option<Kernel>)] = &[
+    ("box", Some(Kernel::Box)),
+    ("bilinear", Some(Kernel::Bilinear)),
+    ("mitchell", Some(Kernel::Mitchell)),
+    ("lanczos3", Some(Kernel::Lanczos3)),
+];
+
+capscr_plugin_sdk::export_band_filter!(Downscale);
+
+pub struct Downscale {
+    out_width: u32,
+    out_height: u32,
+    scaler: Scaler,
 }
 
-#[link(wasm_import_module = "capscr")]
-extern "C" {
-    fn config_get(key_ptr: i32, key_len: i32) -> i64;
+enum Scaler {
+    Boxes(Boxes),
+    Exact(Resampler),
 }
 
-/// read `max_dimension` from config, falling back to the default. only valid
-/// after the caller has copied the input out of SCRATCH (config_get reuses it).
-fn read_max_dim() -> u32 {
-    let packed =
-        unsafe { config_get(CONFIG_KEY.as_ptr() as i32, CONFIG_KEY.len() as i32) };
-    if packed == 0 {
-        return DEFAULT_MAX_DIM;
-    }
-    let ptr = ((packed as u64) >> 32) as usize;
-    let len = (packed as u64 & 0xffff_ffff) as usize;
-    let s = unsafe { core::slice::from_raw_parts(ptr as *const u8, len) };
-    core::str::from_utf8(s)
-        .ok()
-        .and_then(|t| t.trim().parse::<u32>().ok())
-        .filter(|&d| d > 0)
-        .unwrap_or(DEFAULT_MAX_DIM)
+/// the integer box average: every f×f block (clamped at the edges) becomes
+/// one output pixel.
+struct Boxes {
+    width: u32,
+    height: u32,
+    factor: u32,
+    // next input row to arrive
+    y: u32,
+    // per-block sums for the output row being accumulated
+    sums: Sums,
+    // input rows summed into `sums` so far
+    rows_in: u32,
+}
+
+enum Sums {
+    /// r,g,b,a bytes as they are
+    Srgb(Vec<u32>),
+    /// premultiplied linear r,g,b,a; f64 so huge blocks don't lose precision
+    Linear(Box<Lut>, Vec<f64>),
 }
 
-/// on_capture: 0 = continue unchanged, >0 = packed (ptr<<32)|len of a
-/// replacement [w:u32][h:u32][rgba] blob. (never cancels)
-#[no_mangle]
-pub extern "C" fn capscr_on_capture(ptr: i32, len: i32) -> i64 {
-    if ptr < 0 || len < 12 {
-        return 0;
+impl BandFilter for Downscale {
+    fn begin(header: &Header, config: &dyn Config) -> Option<Self> {
+        let (w, h) = (header.width, header.height);
+        let limits = Limits::read(config, header.mode);
+        let filter = config.choice("filter", None, FILTERS);
+        let linear = config
+            .bool("linear_light", false)
+            .then(|| Box::new(Lut::new()));
+        if limits.fit(w, h) {
+            return None; // already within the limits — leave it untouched
+        }
+        let scale = limits.scale(w, h);
+        if let Some(kernel) = filter {
+            // the binding limit lands exactly, the other side in proportion —
+            // rounded, unless that overshoots a limit (megapixels, usually)
+            let size = |round: fn(f64) -> f64| {
+                let side = |v: u32| (round(v as f64 * scale) as u32).max(1);
+                (side(w), side(h))
+            };
+            let (mut nw, mut nh) = size(f64::round);
+            if !limits.fit(nw, nh) {
+                (nw, nh) = size(f64::floor);
+            }
+            return Some(Downscale {
+                out_width: nw,
+                out_height: nh,
+                scaler: Scaler::Exact(Resampler::new(kernel, (w, h), (nw, nh), linear)),
+            });
+        }
+        // integer box-average downscale: the smallest factor that fits, which
+        // for max_dimension alone is ceil(longest / max_dimension)
+        let mut f = ((1.0 / scale) as u32).max(2);
+        while !limits.fit(w.div_ceil(f), h.div_ceil(f)) && f < w.max(h) {
+            f += 1;
+        }
+        let nw = w.div_ceil(f);
+        Some(Downscale {
+            out_width: nw,
+            out_height: h.div_ceil(f),
+            scaler: Scaler::Boxes(Boxes {
+                width: w,
+                height: h,
+                factor: f,
+                y: 0,
+                sums: match linear {
+                    None => Sums::Srgb(vec![0; nw as usize * 4]),
+                    Some(lut) => Sums::Linear(lut, vec![0.0; nw as usize * 4]),
+                },
+                rows_in: 0,
+            }),
+        })
     }
-    // copy the input out of SCRATCH before config_get reuses that buffer
-    let input = unsafe { core::slice::from_raw_parts(ptr as *const u8, len as usize) }.to_vec();
-    let w = u32::from_le_bytes([input[0], input[1], input[2], input[3]]);
-    let h = u32::from_le_bytes([input[4], input[5], input[6], input[7]]);
-    let rgba = &input[12..];
-    let expected = (w as usize).saturating_mul(h as usize).saturating_mul(4);
-    if rgba.len() != expected || expected == 0 {
-        return 0;
+
+    fn output_size(&self) -> (u32, u32) {
+        (self.out_width, self.out_height)
     }
 
-    let max_dim = read_max_dim();
-    let longest = w.max(h);
-    if longest <= max_dim {
-        return 0; // already within the limit — leave it untouched
+    fn band(&mut self, rows: &[u8], out: &mut Vec<u8>) {
+        match &mut self.scaler {
+            Scaler::Boxes(boxes) => boxes.band(rows, out),
+            Scaler::Exact(resampler) => resampler.band(rows, out),
+        }
     }
+}
 
-    // integer box-average downscale: factor = ceil(longest / max_dim)
-    let f = longest.div_ceil(max_dim).max(2);
-    let nw = w.div_ceil(f);
-    let nh = h.div_ceil(f);
-    let row = w as usize * 4;
-    let out_len = 8 + (nw as usize) * (nh as usize) * 4;
+impl Boxes {
+    fn band(&mut self, rows: &[u8], out: &mut Vec<u8>) {
+        let f = self.factor as usize;
+        for row in rows.chunks_exact(self.width as usize * 4) {
+            match &mut self.sums {
+                Sums::Srgb(sums) => sum_blocks(row, f, sums),
+                Sums::Linear(lut, sums) => sum_blocks_linear(row, f, lut, sums),
+            }
+            self.y += 1;
+            self.rows_in += 1;
+            if self.rows_in == self.factor || self.y == self.height {
+                self.emit_row(out);
+            }
+        }
+    }
 
-    // SAFETY: serialised calls; the host copies the bytes out before the next
-    // call clears this buffer
-    unsafe {
-        let out = &mut *core::ptr::addr_of_mut!(OUTPUT);
-        out.clear();
-        out.reserve(out_len);
-        out.extend_from_slice(&nw.to_le_bytes());
-        out.extend_from_slice(&nh.to_le_bytes());
-        for oy in 0..nh {
-            for ox in 0..nw {
-                // average the f×f input block (clamped at the edges)
-                let (mut r, mut g, mut b, mut a, mut n) = (0u32, 0u32, 0u32, 0u32, 0u32);
-                let x0 = ox * f;
-                let y0 = oy * f;
-                for dy in 0..f {
-                    let y = y0 + dy;
-                    if y >= h {
-                        break;
-                    }
-                    for dx in 0..f {
-                        let x = x0 + dx;
-                        if x >= w {
-                            break;
-                        }
-                        let i = y as usize * row + x as usize * 4;
-                        r += rgba[i] as u32;
-                        g += rgba[i + 1] as u32;
-                        b += rgba[i + 2] as u32;
-                        a += rgba[i + 3] as u32;
-                        n += 1;
+    /// average the accumulated f×f blocks (clamped at the edges) into one
+    /// output row and reset the sums.
+    fn emit_row(&mut self, out: &mut Vec<u8>) {
+        let (f, width, rows_in) = (self.factor, self.width, self.rows_in);
+        let count = |ox: usize| (f.min(width - ox as u32 * f) * rows_in).max(1);
+        match &mut self.sums {
+            Sums::Srgb(sums) => {
+                for (ox, sum) in sums.chunks_exact_mut(4).enumerate() {
+                    let n = count(ox);
+                    for c in sum.iter_mut() {
+                        out.push((*c / n) as u8);
+                        *c = 0;
                     }
                 }
-                let n = n.max(1);
-                out.push((r / n) as u8);
-                out.push((g / n) as u8);
-                out.push((b / n) as u8);
-                out.push((a / n) as u8);
             }
+            Sums::Linear(lut, sums) => {
+                for (ox, sum) in sums.chunks_exact_mut(4).enumerate() {
+                    let n = count(ox) as f64;
+                    let mean = [0, 1, 2, 3].map(|c| (sum[c] / n) as f32);
+                    out.extend_from_slice(&lut.unpremultiplied(mean));
+                    sum.fill(0.0);
+                }
+            }
+        }
+        self.rows_in = 0;
+    }
+}
+
+/// add each f-pixel-wide block of `row` (the last one clamped at the edge)
+/// into its r,g,b,a slot of `sums`.
+pub fn sum_blocks(row: &[u8], f: usize, sums: &mut [u32]) {
+    #[cfg(all(feature = "simd", target_arch = "wasm32"))]
+    // SAFETY: simd128 is a module-level feature on wasm; an engine without it
+    // rejects the module at load time rather than trapping here
+    unsafe {
+        simd::sum_blocks(row, f, sums)
+    }
+    #[cfg(not(all(feature = "simd", target_arch = "wasm32")))]
+    sum_blocks_scalar(row, f, sums)
+}
+
+pub fn sum_blocks_scalar(row: &[u8], f: usize, sums: &mut [u32]) {
+    for (block, sum) in row.chunks(f * 4).zip(sums.chunks_exact_mut(4)) {
+        for px in block.chunks_exact(4) {
+            sum[0] += px[0] as u32;
+            sum[1] += px[1] as u32;
+            sum[2] += px[2] as u32;
+            sum[3] += px[3] as u32;
+        }
+    }
+}
+
+/// [`sum_blocks`] in premultiplied linear light.
+pub fn sum_blocks_linear(row: &[u8], f: usize, lut: &Lut, sums: &mut [f64]) {
+    for (block, sum) in row.chunks(f * 4).zip(sums.chunks_exact_mut(4)) {
+        for px in block.chunks_exact(4) {
+            let v = lut.premultiplied([px[0], px[1], px[2], px[3]]);
+            for (s, c) in sum.iter_mut().zip(v) {
+                *s += c as f64;
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "wasm32"))]
+mod simd {
+    use core::arch::wasm32::*;
+
+    /// one u32x4 accumulator per block; four pixels per load are folded to
+    /// one u32x4 (pairs in u16 first — 2 × 255 can't overflow). Integer sums,
+    /// so the result matches the scalar path exactly.
+    #[target_feature(enable = "simd128")]
+    pub unsafe fn sum_blocks(row: &[u8], f: usize, sums: &mut [u32]) {
+        if f == 2 {
+            return sum_pairs(row, sums);
+        }
+        for (block, sum) in row.chunks(f * 4).zip(sums.chunks_exact_mut(4)) {
+            let slot = sum.as_mut_ptr() as *mut v128;
+            let mut acc = v128_load(slot);
+            let mut quads = block.chunks_exact(16);
+            for quad in &mut quads {
+                let v = v128_load(quad.as_ptr() as *const v128);
+                let pairs = u16x8_add(u16x8_extend_low_u8x16(v), u16x8_extend_high_u8x16(v));
+                acc = u32x4_add(
+                    acc,
+                    u32x4_add(
+                        u32x4_extend_low_u16x8(pairs),
+                        u32x4_extend_high_u16x8(pairs),
+                    ),
+                );
+            }
+            for px in quads.remainder().chunks_exact(4) {
+                let v = u32x4_splat(u32::from_le_bytes([px[0], px[1], px[2], px[3]]));
+                acc = u32x4_add(acc, u32x4_extend_low_u16x8(u16x8_extend_low_u8x16(v)));
+            }
+            v128_store(slot, acc);
+        }
+    }
+
+    /// f = 2 (the common 4K → 1080p case): one load covers two whole blocks,
+    /// so regroup the u16 pixels into block pairs and update two slots at once.
+    #[target_feature(enable = "simd128")]
+    unsafe fn sum_pairs(row: &[u8], sums: &mut [u32]) {
+        let mut quads = row.chunks_exact(16);
+        for (quad, slot) in (&mut quads).zip(sums.chunks_exact_mut(8)) {
+            let v = v128_load(quad.as_ptr() as *const v128);
+            let (lo, hi) = (u16x8_extend_low_u8x16(v), u16x8_extend_high_u8x16(v));
+            let firsts = i16x8_shuffle::<0, 1, 2, 3, 8, 9, 10, 11>(lo, hi);
+            let seconds = i16x8_shuffle::<4, 5, 6, 7, 12, 13, 14, 15>(lo, hi);
+            let blocks = u16x8_add(firsts, seconds);
+            let p = slot.as_mut_ptr() as *mut v128;
+            v128_store(p, u32x4_add(v128_load(p), u32x4_extend_low_u16x8(blocks)));
+            let p = p.add(1);
+            v128_store(p, u32x4_add(v128_load(p), u32x4_extend_high_u16x8(blocks)));
         }
-        let p = out.as_ptr() as i64;
-        (p << 32) | (out_len as i64 & 0xffff_ffff)
+        // the last one to three pixels: a block and a half at most. Their
+        // slots aren't necessarily the chunks' remainder — three pixels fill
+        // two blocks, an even number of slots
+        let done = row.len() / 16 * 8;
+        super::sum_blocks_scalar(quads.remainder(), 2, &mut sums[done..]);
     }
 }
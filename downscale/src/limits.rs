@@ -0,0 +1,77 @@
+//! The size limits a capture must fit, from config.
+//!
+//! Every limit is optional and they combine — the tightest one wins. Each can
+//! be overridden per capture mode by prefixing its key with the mode's name
+//! (`gif_max_dimension`, `window_max_megapixels`, …). An override of 0 lifts
+//! that one limit for the mode and leaves the others in force; to leave a
+//! mode's captures alone entirely, set `<mode>_enabled = false`.
+
+use capscr_plugin_sdk::config::Config;
+use capscr_plugin_sdk::image::Mode;
+
+const DEFAULT_MAX_DIM: u32 = 1920;
+// longest side, width, height, total pixels (in millions)
+const KEYS: [&str; 4] = ["max_dimension", "max_width", "max_height", "max_megapixels"];
+// well past any capture the host accepts; 0 is "no limit"
+const MAX_MEGAPIXELS: f32 = 1_000_000.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Limits {
+    pub dimension: Option<u32>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub pixels: Option<f64>,
+}
+
+impl Limits {
+    /// the limits for a `mode` capture: none at all if the mode is switched
+    /// off. With none of the global keys set, `max_dimension` defaults to
+    /// 1920.
+    pub fn read(config: &dyn Config, mode: Mode) -> Limits {
+        if !config.bool(&format!("{}_enabled", mode.name()), true) {
+            return Limits::default();
+        }
+        let any = KEYS.iter().any(|k| config.get(k).is_some());
+        let default_dim = if any { 0 } else { DEFAULT_MAX_DIM };
+        let side = |key: &str, default: u32| {
+            let global = config.u32(key, default, 0..=u32::MAX);
+            let v = config.u32(&format!("{}_{key}", mode.name()), global, 0..=u32::MAX);
+            (v > 0).then_some(v)
+        };
+        let global = config.f32(KEYS[3], 0.0, 0.0..=MAX_MEGAPIXELS);
+        let mp = config.f32(
+            &format!("{}_{}", mode.name(), KEYS[3]),
+            global,
+            0.0..=MAX_MEGAPIXELS,
+        );
+        Limits {
+            dimension: side(KEYS[0], default_dim),
+            width: side(KEYS[1], 0),
+            height: side(KEYS[2], 0),
+            pixels: (mp > 0.0).then(|| (mp as f64 * 1e6).floor()),
+        }
+    }
+
+    /// whether a `w`×`h` image is within every limit.
+    pub fn fit(&self, w: u32, h: u32) -> bool {
+        let within = |limit: Option<u32>, v: u32| limit.is_none_or(|l| v <= l);
+        within(self.dimension, w.max(h))
+            && within(self.width, w)
+            && within(self.height, h)
+            && self.pixels.is_none_or(|p| w as f64 * h as f64 <= p)
+    }
+
+    /// the largest scale factor (≤ 1 when the image doesn't fit) that brings
+    /// `w`×`h` within every limit.
+    pub fn scale(&self, w: u32, h: u32) -> f64 {
+        let ratio =
+            |limit: Option<u32>, v: u32| limit.map_or(f64::INFINITY, |l| l as f64 / v as f64);
+        ratio(self.dimension, w.max(h))
+            .min(ratio(self.width, w))
+            .min(ratio(self.height, h))
+            .min(
+                self.pixels
+                    .map_or(f64::INFINITY, |p| (p / (w as f64 * h as f64)).sqrt()),
+            )
+    }
+}
@@ -0,0 +1,260 @@
+//! Exact-fit separable resampling: any output size, with a choice of kernel.
+//!
+//! Each output pixel is a weighted sum over the input pixels under the
+//! kernel, stretched by the scale factor so it covers every input pixel on a
+//! downscale. Horizontal then vertical, so the cost is per-axis taps rather
+//! than their product. Rows are resampled horizontally as they arrive and kept
+//! in a window only as tall as one output row's vertical taps, which keeps the
+//! tiled hook's memory flat.
+//!
+//! With a [`Lut`] the pixels are resampled as premultiplied linear light
+//! (0..=1) instead of sRGB bytes (0..=255).
+
+use std::collections::VecDeque;
+
+use capscr_plugin_sdk::srgb::Lut;
+
+/// the reconstruction kernel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kernel {
+    /// area average; the exact-fit version of the integer box path
+    Box,
+    /// triangle, a.k.a. tent or linear
+    Bilinear,
+    /// Mitchell–Netravali cubic, B = C = 1/3: sharp with little ringing
+    Mitchell,
+    /// windowed sinc over 3 lobes: sharpest, may ring on hard edges
+    Lanczos3,
+}
+
+impl Kernel {
+    /// how far from its centre the kernel is non-zero, in input px at 1:1.
+    fn support(self) -> f32 {
+        match self {
+            Kernel::Box => 0.5,
+            Kernel::Bilinear => 1.0,
+            Kernel::Mitchell => 2.0,
+            Kernel::Lanczos3 => 3.0,
+        }
+    }
+
+    fn weight(self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            // area-weighted in `taps` instead
+            Kernel::Box => (x < 0.5) as u8 as f32,
+            Kernel::Bilinear => (1.0 - x).max(0.0),
+            Kernel::Mitchell => {
+                const B: f32 = 1.0 / 3.0;
+                const C: f32 = 1.0 / 3.0;
+                let (x2, x3) = (x * x, x * x * x);
+                let w = if x < 1.0 {
+                    (12.0 - 9.0 * B - 6.0 * C) * x3
+                        + (-18.0 + 12.0 * B + 6.0 * C) * x2
+                        + (6.0 - 2.0 * B)
+                } else if x < 2.0 {
+                    (-B - 6.0 * C) * x3
+                        + (6.0 * B + 30.0 * C) * x2
+                        + (-12.0 * B - 48.0 * C) * x
+                        + (8.0 * B + 24.0 * C)
+                } else {
+                    0.0
+                };
+                w / 6.0
+            }
+            Kernel::Lanczos3 => {
+                if x < 3.0 {
+                    sinc(x) * sinc(x / 3.0)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        return 1.0;
+    }
+    let px = core::f32::consts::PI * x;
+    px.sin() / px
+}
+
+/// the weights behind every output pixel along one axis: output `i` reads
+/// input `start[i]..start[i] + n` with `weights[i * n..][..n]`. A fixed `n`
+/// (zero-padded) keeps the inner loops branch-free.
+struct Taps {
+    n: usize,
+    start: Vec<usize>,
+    weights: Vec<f32>,
+}
+
+impl Taps {
+    /// taps for resampling `len` input px to `out`.
+    fn new(kernel: Kernel, len: u32, out: u32) -> Taps {
+        let scale = len as f32 / out as f32;
+        // stretch the kernel on a downscale so it covers every input px
+        let stretch = scale.max(1.0);
+        let support = kernel.support() * stretch;
+        let len = len as usize;
+        // every input px the kernel touches, even partly
+        let n = ((2.0 * support).ceil() as usize + 1).min(len);
+        let mut start = Vec::with_capacity(out as usize);
+        let mut weights = Vec::with_capacity(out as usize * n);
+        for i in 0..out {
+            let centre = (i as f32 + 0.5) * scale;
+            let lo = ((centre - support).floor().max(0.0) as usize).min(len - n);
+            let at = weights.len();
+            weights.extend((lo..lo + n).map(|x| {
+                let x = x as f32;
+                match kernel {
+                    // each input px weighted by how much of it the output px
+                    // covers, not just whether its centre is inside
+                    Kernel::Box => {
+                        ((x + 1.0).min(centre + support) - x.max(centre - support)).max(0.0)
+                    }
+                    _ => kernel.weight((x + 0.5 - centre) / stretch),
+                }
+            }));
+            let sum: f32 = weights[at..].iter().sum();
+            if sum != 0.0 {
+                weights[at..].iter_mut().for_each(|w| *w /= sum);
+            }
+            start.push(lo);
+        }
+        Taps { n, start, weights }
+    }
+
+    /// one past the last input px output `i` reads.
+    fn end(&self, i: usize) -> usize {
+        self.start[i] + self.n
+    }
+}
+
+/// resample rows of `width` px streamed in top to bottom into `out_width` ×
+/// `out_height`.
+pub struct Resampler {
+    width: u32,
+    columns: Taps,
+    rows: Taps,
+    // next input row to arrive
+    y: usize,
+    // next output row to emit
+    out_y: usize,
+    // horizontally resampled input rows (r,g,b,a f32, `out_width` px each),
+    // the first being input row `window_start`
+    window: VecDeque<Vec<f32>>,
+    window_start: usize,
+    // a dropped window row, reused for the next arrival
+    spare: Vec<f32>,
+    // the arriving row as f32, converted once rather than per tap
+    input: Vec<f32>,
+    acc: Vec<f32>,
+    linear: Option<Box<Lut>>,
+}
+
+impl Resampler {
+    pub fn new(
+        kernel: Kernel,
+        (w, h): (u32, u32),
+        (nw, nh): (u32, u32),
+        linear: Option<Box<Lut>>,
+    ) -> Resampler {
+        Resampler {
+            width: w,
+            columns: Taps::new(kernel, w, nw),
+            rows: Taps::new(kernel, h, nh),
+            y: 0,
+            out_y: 0,
+            window: VecDeque::new(),
+            window_start: 0,
+            spare: Vec::new(),
+            input: vec![0.0; w as usize * 4],
+            acc: vec![0.0; nw as usize * 4],
+            linear,
+        }
+    }
+
+    /// take the next input rows, appending every output row they complete.
+    pub fn band(&mut self, rows: &[u8], out: &mut Vec<u8>) {
+        for row in rows.chunks_exact(self.width as usize * 4) {
+            self.push(row);
+            while self.out_y < self.rows.start.len() && self.rows.end(self.out_y) <= self.y {
+                self.emit(out);
+            }
+        }
+    }
+
+    /// resample input row `self.y` horizontally into the window, first
+    /// dropping rows no later output row reads.
+    fn push(&mut self, row: &[u8]) {
+        let keep_from = self.rows.start.get(self.out_y).copied().unwrap_or(self.y);
+        while self.window_start < keep_from && !self.window.is_empty() {
+            self.spare = self.window.pop_front().unwrap();
+            self.window_start += 1;
+        }
+        if self.window.is_empty() {
+            self.window_start = self.y;
+        }
+        match &self.linear {
+            None => {
+                for (f, &c) in self.input.iter_mut().zip(row) {
+                    *f = c as f32;
+                }
+            }
+            Some(lut) => {
+                for (f, px) in self.input.chunks_exact_mut(4).zip(row.chunks_exact(4)) {
+                    f.copy_from_slice(&lut.premultiplied([px[0], px[1], px[2], px[3]]));
+                }
+            }
+        }
+        let mut line = core::mem::take(&mut self.spare);
+        line.resize(self.acc.len(), 0.0);
+        let cols = &self.columns;
+        let taps = cols.start.iter().zip(cols.weights.chunks_exact(cols.n));
+        for ((&start, weights), o) in taps.zip(line.chunks_exact_mut(4)) {
+            let src = &self.input[start * 4..][..cols.n * 4];
+            let mut sum = [0.0f32; 4];
+            for (&w, p) in weights.iter().zip(src.chunks_exact(4)) {
+                sum[0] += w * p[0];
+                sum[1] += w * p[1];
+                sum[2] += w * p[2];
+                sum[3] += w * p[3];
+            }
+            o.copy_from_slice(&sum);
+        }
+        self.window.push_back(line);
+        self.y += 1;
+    }
+
+    /// blend the window rows under output row `self.out_y` into bytes.
+    fn emit(&mut self, out: &mut Vec<u8>) {
+        let rows = &self.rows;
+        let first = rows.start[self.out_y] - self.window_start;
+        let weights = &rows.weights[self.out_y * rows.n..][..rows.n];
+        self.acc.fill(0.0);
+        for (line, &w) in self.window.range(first..).zip(weights) {
+            for (a, &s) in self.acc.iter_mut().zip(line) {
+                *a += w * s;
+            }
+        }
+        // sharpening kernels overshoot; clamp back into range
+        let start = out.len();
+        out.resize(start + self.acc.len(), 0);
+        match &self.linear {
+            None => {
+                for (o, &v) in out[start..].iter_mut().zip(&self.acc) {
+                    *o = (v + 0.5).clamp(0.0, 255.0) as u8;
+                }
+            }
+            Some(lut) => {
+                let pixels = out[start..].chunks_exact_mut(4);
+                for (o, v) in pixels.zip(self.acc.chunks_exact(4)) {
+                    o.copy_from_slice(&lut.unpremultiplied([v[0], v[1], v[2], v[3]]));
+                }
+            }
+        }
+        self.out_y += 1;
+    }
+}
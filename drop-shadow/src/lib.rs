@@ -0,0 +1,134 @@
+//! capscr plugin: a soft drop shadow under each capture.
+//!
+//! The canvas grows by transparent padding just big enough to hold the shadow
+//! (plus an optional extra margin), the capture's alpha is offset and Gaussian
+//! blurred into the shadow, and the capture is composited over it. Pure byte
+//! math on top of the SDK, no dependencies.
+//!
+//! The shadow itself is the SDK's [`Shadow`]: for an opaque capture only
+//! `width + height` samples are convolved, however big the capture.
+
+use capscr_plugin_sdk::blur;
+use capscr_plugin_sdk::config::Config;
+use capscr_plugin_sdk::filter::Filter;
+use capscr_plugin_sdk::image::Header;
+use capscr_plugin_sdk::px::over;
+use capscr_plugin_sdk::shadow::{self, Shadow};
+
+const DEFAULT_BLUR: u32 = 16; // CSS-style blur length, px (σ = blur / 2)
+const DEFAULT_OFFSET: (i32, i32) = (0, 8);
+const DEFAULT_OPACITY: f32 = 0.4;
+const DEFAULT_COLOR: [u8; 4] = [0, 0, 0, 255];
+const MAX_BLUR: u32 = 256;
+const MAX_OFFSET: i32 = 1024;
+const MAX_PADDING: u32 = 1024;
+const MAX_DIM: u32 = 16384; // host rejects larger replacements; bail to match
+
+capscr_plugin_sdk::export_filter!(DropShadow);
+
+pub struct DropShadow {
+    width: u32,
+    height: u32,
+    // where the capture's top-left lands on the canvas
+    left: u32,
+    top: u32,
+    offset: (i32, i32),
+    kernel: Vec<f32>,
+    color: [u8; 4],
+    // peak shadow alpha, 0..=1: opacity × the colour's own alpha
+    strength: f32,
+    out_width: u32,
+    out_height: u32,
+}
+
+impl Filter for DropShadow {
+    fn begin(header: &Header, config: &dyn Config) -> Option<Self> {
+        let (w, h) = (header.width, header.height);
+        let blur = config.u32("blur", DEFAULT_BLUR, 0..=MAX_BLUR);
+        let ox = config.i32("offset_x", DEFAULT_OFFSET.0, -MAX_OFFSET..=MAX_OFFSET);
+        let oy = config.i32("offset_y", DEFAULT_OFFSET.1, -MAX_OFFSET..=MAX_OFFSET);
+        let opacity = config.f32("opacity", DEFAULT_OPACITY, 0.0..=1.0);
+        let color = config.color("color", DEFAULT_COLOR);
+        let padding = config.u32("padding", 0, 0..=MAX_PADDING) as i64;
+
+        let kernel = blur::kernel(blur::sigma_for_radius(blur as f32));
+        let reach = (kernel.len() / 2) as i64;
+        // the shadow spans [offset - reach, offset + size + reach) around the
+        // capture; pad each side out to whichever of the two reaches further
+        let before = |o: i32| (reach - o as i64).max(0) + padding;
+        let after = |o: i32| (reach + o as i64).max(0) + padding;
+        let nw = w as i64 + before(ox) + after(ox);
+        let nh = h as i64 + before(oy) + after(oy);
+        if nw > MAX_DIM as i64 || nh > MAX_DIM as i64 {
+            return None; // the host would reject an oversized replacement anyway
+        }
+        Some(DropShadow {
+            width: w,
+            height: h,
+            left: before(ox) as u32,
+            top: before(oy) as u32,
+            offset: (ox, oy),
+            kernel,
+            color,
+            strength: opacity * color[3] as f32 / 255.0,
+            out_width: nw as u32,
+            out_height: nh as u32,
+        })
+    }
+
+    fn output_size(&self) -> (u32, u32) {
+        (self.out_width, self.out_height)
+    }
+
+    fn render(&mut self, rgba: &[u8], out: &mut Vec<u8>) {
+        let nw = self.out_width as usize;
+        let (w, row_bytes) = (self.width as usize, self.width as usize * 4);
+        let (left, top) = (self.left as usize, self.top as usize);
+        let opaque = shadow::opaque_rows(rgba, self.width);
+        let shadow = Shadow::new(
+            (self.width, self.height),
+            |x, y| rgba[(y * w + x) * 4 + 3],
+            &opaque,
+            (
+                self.left as i64 + self.offset.0 as i64,
+                self.top as i64 + self.offset.1 as i64,
+            ),
+            (self.out_width, self.out_height),
+            &self.kernel,
+        );
+        let [r, g, b, _] = self.color;
+        let paint = |dst: &mut [u8], alpha: &[f32]| {
+            let scale = self.strength * 255.0;
+            for (d, &a) in dst.chunks_exact_mut(4).zip(alpha) {
+                d.copy_from_slice(&[r, g, b, (a * scale + 0.5) as u8]);
+            }
+        };
+
+        let mut row = vec![0.0; nw];
+        for y in 0..self.out_height as usize {
+            shadow.row(y, &mut row);
+            let start = out.len();
+            out.resize(start + nw * 4, 0);
+            let dst = &mut out[start..];
+            let cy = y.wrapping_sub(top);
+            if cy >= self.height as usize {
+                paint(dst, &row);
+                continue;
+            }
+            let src = &rgba[cy * row_bytes..][..row_bytes];
+            let (before, rest) = dst.split_at_mut(left * 4);
+            let (under, after) = rest.split_at_mut(row_bytes);
+            paint(before, &row[..left]);
+            paint(after, &row[left + w..]);
+            if opaque[cy] {
+                under.copy_from_slice(src);
+                continue;
+            }
+            paint(under, &row[left..]);
+            for (d, s) in under.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+                let over = over([s[0], s[1], s[2], s[3]], [d[0], d[1], d[2], d[3]]);
+                d.copy_from_slice(&over);
+            }
+        }
+    }
+}
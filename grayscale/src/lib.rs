@@ -1,65 +1,179 @@
-#![cfg(target_arch = "wasm32")] // wasm-only plugin; empty lib on other targets
-//! capscr plugin: convert each capture to grayscale (BT.601 luma).
+//! capscr plugin: convert each capture to grayscale (BT.601 luma by default).
 //!
-//! A minimal showcase of the v0.5 image-blob `on_capture` API — it rewrites the
-//! captured pixels and returns a replacement image. Pure byte math, no deps.
+//! A minimal showcase of the image-blob `on_capture` API — it rewrites the
+//! captured pixels in place and tells the host so (capscr 0.6+), so there's
+//! never a second image-sized buffer in guest memory. Pure byte math, no deps.
+//! Every row is independent, so it also exports the tiled hook variant.
+//! Build with `--features simd` for the simd128 BT.601 kernel (bit-identical
+//! output).
 //! See docs/plugin-runtime.md for the on_capture wire format.
+//!
+//! `mode` picks the conversion: BT.601 or BT.709 luma, CIE lightness, a plain
+//! channel average, sepia, or a two-colour duotone. Alpha is never touched.
+
+use capscr_plugin_sdk::config::Config;
+use capscr_plugin_sdk::image::Header;
+use capscr_plugin_sdk::inplace::InPlaceFilter;
+use capscr_plugin_sdk::px::luma;
+use capscr_plugin_sdk::srgb::Lut;
+
+/// the `mode` key's values.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Conversion {
+    Bt601,
+    Bt709,
+    Lightness,
+    Average,
+    Sepia,
+    Duotone,
+}
+
+const CONVERSIONS: &[(&str, Conversion)] = &[
+    ("bt601", Conversion::Bt601),
+    ("bt709", Conversion::Bt709),
+    ("lightness", Conversion::Lightness),
+    ("average", Conversion::Average),
+    ("sepia", Conversion::Sepia),
+    ("duotone", Conversion::Duotone),
+];
+
+// deep indigo shadows, warm sand highlights
+const DUOTONE_DARK: [u8; 4] = [36, 30, 78, 255];
+const DUOTONE_LIGHT: [u8; 4] = [250, 214, 150, 255];
+
+capscr_plugin_sdk::export_in_place_filter!(Grayscale, tiled);
 
-// host writes the input blob ([w][h][mode][rgba]) here via capscr_alloc
-static mut SCRATCH: Vec<u8> = Vec::new();
-// our replacement blob ([w][h][rgba]); the host reads it after the hook returns,
-// before the next (serialised) call reuses it — so reusing one buffer is safe
-static mut OUTPUT: Vec<u8> = Vec::new();
-
-#[no_mangle]
-pub extern "C" fn capscr_alloc(size: i32) -> i32 {
-    let size = size.max(0) as usize;
-    // SAFETY: single-threaded wasm; calls are serialised by the host store lock
-    unsafe {
-        let buf = &mut *core::ptr::addr_of_mut!(SCRATCH);
-        buf.clear();
-        buf.reserve(size);
-        buf.as_mut_ptr() as i32
+pub enum Grayscale {
+    Bt601,
+    Bt709,
+    Lightness(Box<Lut>),
+    Average,
+    Sepia,
+    Duotone { dark: [u8; 4], light: [u8; 4] },
+}
+
+impl InPlaceFilter for Grayscale {
+    fn begin(_header: &Header, config: &dyn Config) -> Option<Self> {
+        // input[8..12] is the capture mode — unused here
+        Some(
+            match config.choice("mode", Conversion::Bt601, CONVERSIONS) {
+                Conversion::Bt601 => Grayscale::Bt601,
+                Conversion::Bt709 => Grayscale::Bt709,
+                Conversion::Lightness => Grayscale::Lightness(Box::new(Lut::new())),
+                Conversion::Average => Grayscale::Average,
+                Conversion::Sepia => Grayscale::Sepia,
+                Conversion::Duotone => Grayscale::Duotone {
+                    dark: config.color("duotone_dark", DUOTONE_DARK),
+                    light: config.color("duotone_light", DUOTONE_LIGHT),
+                },
+            },
+        )
+    }
+
+    fn rows(&mut self, _y: u32, rows: &mut [u8]) {
+        match self {
+            Grayscale::Bt601 => luma_in_place(rows),
+            Grayscale::Bt709 => map_grey(rows, |[r, g, b]| {
+                ((r as u32 * 2126 + g as u32 * 7152 + b as u32 * 722) / 10000) as u8
+            }),
+            Grayscale::Lightness(lut) => map_grey(rows, |[r, g, b]| lightness(lut, r, g, b)),
+            Grayscale::Average => map_grey(rows, |[r, g, b]| {
+                ((r as u32 + g as u32 + b as u32) / 3) as u8
+            }),
+            Grayscale::Sepia => sepia_in_place(rows),
+            Grayscale::Duotone { dark, light } => duotone_in_place(rows, *dark, *light),
+        }
     }
 }
 
-/// on_capture: 0 = continue unchanged, >0 = packed (ptr<<32)|len of a
-/// replacement [w:u32][h:u32][rgba] blob. (We never cancel.)
-#[no_mangle]
-pub extern "C" fn capscr_on_capture(ptr: i32, len: i32) -> i64 {
-    if ptr < 0 || len < 12 {
-        return 0;
+/// set r,g,b of every pixel to `grey(r, g, b)`; alpha is preserved.
+fn map_grey(rgba: &mut [u8], grey: impl Fn([u8; 3]) -> u8) {
+    for px in rgba.chunks_exact_mut(4) {
+        let g = grey([px[0], px[1], px[2]]);
+        px[0] = g;
+        px[1] = g;
+        px[2] = g;
     }
-    let input = unsafe { core::slice::from_raw_parts(ptr as *const u8, len as usize) };
-    let w = u32::from_le_bytes([input[0], input[1], input[2], input[3]]);
-    let h = u32::from_le_bytes([input[4], input[5], input[6], input[7]]);
-    // input[8..12] is the capture mode — unused here
-    let rgba = &input[12..];
-    let expected = (w as usize)
-        .saturating_mul(h as usize)
-        .saturating_mul(4);
-    if rgba.len() != expected || expected == 0 {
-        return 0;
+}
+
+/// the sRGB grey with the same CIE lightness (L*) as r,g,b: their relative
+/// luminance, from linear light with the BT.709 / sRGB primaries' weights.
+/// Unlike luma (weights on the gamma-encoded bytes), saturated colours keep
+/// their apparent brightness.
+pub fn lightness(lut: &Lut, r: u8, g: u8, b: u8) -> u8 {
+    let y = 0.2126 * lut.linear(r) + 0.7152 * lut.linear(g) + 0.0722 * lut.linear(b);
+    lut.encode(y)
+}
+
+/// replace r,g,b of every pixel with its BT.601 luma; alpha is preserved.
+pub fn luma_in_place(rgba: &mut [u8]) {
+    #[cfg(all(feature = "simd", target_arch = "wasm32"))]
+    // SAFETY: simd128 is a module-level feature on wasm; an engine without it
+    // rejects the module at load time rather than trapping here
+    let rgba = unsafe { simd::luma_in_place(rgba) };
+    luma_in_place_scalar(rgba);
+}
+
+pub fn luma_in_place_scalar(rgba: &mut [u8]) {
+    for px in rgba.chunks_exact_mut(4) {
+        let g = luma(px);
+        px[0] = g;
+        px[1] = g;
+        px[2] = g; // alpha (px[3]) preserved
+    }
+}
+
+/// the classic sepia matrix; channels that overflow are clamped.
+pub fn sepia_in_place(rgba: &mut [u8]) {
+    for px in rgba.chunks_exact_mut(4) {
+        let (r, g, b) = (px[0] as u32, px[1] as u32, px[2] as u32);
+        let tone = |wr: u32, wg: u32, wb: u32| ((r * wr + g * wg + b * wb) / 1000).min(255) as u8;
+        px[0] = tone(393, 769, 189);
+        px[1] = tone(349, 686, 168);
+        px[2] = tone(272, 534, 131);
     }
+}
+
+/// BT.601 luma mapped on to the line from `dark` (black) to `light` (white).
+pub fn duotone_in_place(rgba: &mut [u8], dark: [u8; 4], light: [u8; 4]) {
+    for px in rgba.chunks_exact_mut(4) {
+        let y = luma(px) as u32;
+        for c in 0..3 {
+            px[c] = ((dark[c] as u32 * (255 - y) + light[c] as u32 * y + 127) / 255) as u8;
+        }
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "wasm32"))]
+mod simd {
+    use core::arch::wasm32::*;
 
-    let out_len = 8 + rgba.len();
-    // SAFETY: serialised calls; we return ptr/len and the host copies the bytes
-    // out before the next call clears this buffer
-    unsafe {
-        let out = &mut *core::ptr::addr_of_mut!(OUTPUT);
-        out.clear();
-        out.reserve(out_len);
-        out.extend_from_slice(&w.to_le_bytes());
-        out.extend_from_slice(&h.to_le_bytes());
-        for px in rgba.chunks_exact(4) {
-            let y = (px[0] as u32 * 299 + px[1] as u32 * 587 + px[2] as u32 * 114) / 1000;
-            let g = y as u8;
-            out.push(g);
-            out.push(g);
-            out.push(g);
-            out.push(px[3]); // preserve alpha
+    /// four pixels per step as u32 lanes; returns the tail still to do. The
+    /// weighted sum is < 2^18, so it's exact in f32 and a correctly rounded
+    /// `/ 1000` truncates to the same integer as the scalar division.
+    #[target_feature(enable = "simd128")]
+    pub unsafe fn luma_in_place(rgba: &mut [u8]) -> &mut [u8] {
+        let byte = u32x4_splat(0xff);
+        let alpha = u32x4_splat(0xff00_0000);
+        let thousand = f32x4_splat(1000.0);
+        let mut chunks = rgba.chunks_exact_mut(16);
+        for chunk in &mut chunks {
+            let p = chunk.as_mut_ptr() as *mut v128;
+            let v = v128_load(p);
+            let r = v128_and(v, byte);
+            let g = v128_and(u32x4_shr(v, 8), byte);
+            let b = v128_and(u32x4_shr(v, 16), byte);
+            let sum = u32x4_add(
+                u32x4_add(
+                    u32x4_mul(r, u32x4_splat(299)),
+                    u32x4_mul(g, u32x4_splat(587)),
+                ),
+                u32x4_mul(b, u32x4_splat(114)),
+            );
+            let y = u32x4_trunc_sat_f32x4(f32x4_div(f32x4_convert_u32x4(sum), thousand));
+            let gray = v128_or(v128_or(y, u32x4_shl(y, 8)), u32x4_shl(y, 16));
+            v128_store(p, v128_or(gray, v128_and(v, alpha)));
         }
-        let p = out.as_ptr() as i64;
-        (p << 32) | (out_len as i64 & 0xffff_ffff)
+        chunks.into_remainder()
     }
 }
@@ -0,0 +1,38 @@
+//! Scalar vs simd128 throughput of the pixel kernels under wasmtime. Only the
+//! time inside the hook counts — the host's copies in and out are the same for
+//! both builds and would drown the difference.
+//!
+//!     cargo run --release -p capscr-plugin-harness --example simd_speedup
+
+use capscr_plugin_harness::{Image, Plugin};
+use capscr_plugin_sdk::image::Mode;
+
+const RUNS: u32 = 7;
+
+/// best-of-RUNS guest megapixels per second for one on_capture call.
+fn mps(plugin: &mut Plugin, img: &Image) -> f64 {
+    let best = (0..RUNS)
+        .map(|_| {
+            let before = plugin.guest_time();
+            plugin.on_capture(img, Mode::FullScreen);
+            (plugin.guest_time() - before).as_secs_f64()
+        })
+        .fold(f64::INFINITY, f64::min);
+    (img.width as f64 * img.height as f64) / 1e6 / best
+}
+
+fn main() {
+    for (label, w, h) in [("4K", 3840, 2160), ("8K", 7680, 4320)] {
+        let img = Image::synthetic(w, h);
+        for id in ["grayscale", "downscale", "borders"] {
+            let load =
+                |features: &[&str]| Plugin::load_with(id, features).config("max_dimension", "1920");
+            let scalar = mps(&mut load(&[]), &img);
+            let simd = mps(&mut load(&["simd"]), &img);
+            println!(
+                "{label} {id:<10} scalar {scalar:>8.1} MP/s   simd {simd:>8.1} MP/s   x{:.2}",
+                simd / scalar
+            );
+        }
+    }
+}
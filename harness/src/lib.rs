@@ -0,0 +1,485 @@
+//! Host emulator for capscr WASM plugins, for tests and benchmarks.
+//!
+//! [`Plugin::load`] builds a plugin crate for wasm32-unknown-unknown (into
+//! `target/harness/`, trybuild-style, so it never fights the outer cargo for
+//! the build lock), instantiates it under wasmtime with the `capscr` imports,
+//! and drives its hooks the way capscr does. Protocol violations panic with the
+//! reason the real host would reject the reply.
+
+pub mod manifest;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use capscr_plugin_sdk::abi::{
+    pack, unpack, unpack_size, CAPTURE_HEADER_LEN, IN_PLACE, REPLY_HEADER_LEN,
+};
+use capscr_plugin_sdk::image::Mode;
+use wasmtime::{Caller, Engine, Extern, Linker, Memory, Module, Store, TypedFunc};
+
+const WASM_TARGET: &str = "wasm32-unknown-unknown";
+
+/// an rgba image on the host side.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, rgba: Vec<u8>) -> Image {
+        assert_eq!(
+            rgba.len(),
+            width as usize * height as usize * 4,
+            "rgba size"
+        );
+        Image {
+            width,
+            height,
+            rgba,
+        }
+    }
+
+    pub fn filled(width: u32, height: u32, px: [u8; 4]) -> Image {
+        Image::new(width, height, px.repeat(width as usize * height as usize))
+    }
+
+    /// a deterministic test card: gradients, noise and some translucency.
+    pub fn synthetic(width: u32, height: u32) -> Image {
+        let row = width as usize * 4;
+        let mut rgba = vec![0; row * height as usize];
+        for (y, r) in rgba.chunks_exact_mut(row).enumerate() {
+            pattern_row(y as u32, r);
+        }
+        Image::new(width, height, rgba)
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        self.rgba[i..i + 4].try_into().unwrap()
+    }
+}
+
+/// pixel (x, y) of [`Image::synthetic`], computable without the whole image.
+pub fn pattern_px(x: u32, y: u32) -> [u8; 4] {
+    let mut h = x.wrapping_mul(0x9e37_79b1) ^ y.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 13;
+    let a = if h & 7 == 0 { (h >> 8) as u8 } else { 255 };
+    [(x * 3 + y) as u8, (x ^ y) as u8, (h >> 16) as u8, a]
+}
+
+/// fill one row (`width * 4` bytes) of the synthetic pattern.
+pub fn pattern_row(y: u32, row: &mut [u8]) {
+    for (x, px) in row.chunks_exact_mut(4).enumerate() {
+        px.copy_from_slice(&pattern_px(x as u32, y));
+    }
+}
+
+/// what an `on_capture` hook answered.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Reply {
+    Unchanged,
+    /// a new `[w][h][rgba]` blob
+    Replaced(Image),
+    /// the input blob's rgba, rewritten in place
+    InPlace(Image),
+    Cancelled,
+}
+
+impl Reply {
+    /// the new image, however it was returned; panics on any other reply.
+    pub fn image(self) -> Image {
+        match self {
+            Reply::Replaced(img) | Reply::InPlace(img) => img,
+            other => panic!("expected a modified image, got {other:?}"),
+        }
+    }
+}
+
+struct HostState {
+    config: HashMap<String, String>,
+    logs: Vec<(i32, String)>,
+}
+
+/// one instantiated plugin, with its own store and config.
+pub struct Plugin {
+    store: Store<HostState>,
+    instance: wasmtime::Instance,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    // wall time spent inside hook exports
+    guest: Duration,
+}
+
+impl Plugin {
+    /// build (if stale) and instantiate the plugin crate `capscr-<id>`.
+    pub fn load(id: &str) -> Plugin {
+        Plugin::load_with(id, &[])
+    }
+
+    /// as [`Plugin::load`], with cargo features enabled on the plugin crate.
+    pub fn load_with(id: &str, features: &[&str]) -> Plugin {
+        let module = module(&build(id, features));
+        let mut linker = Linker::new(engine());
+        linker
+            .func_wrap(
+                "capscr",
+                "log",
+                |mut c: Caller<'_, HostState>, level: i32, ptr: i32, len: i32| {
+                    let msg = read_str(&mut c, ptr, len);
+                    c.data_mut().logs.push((level, msg));
+                },
+            )
+            .unwrap();
+        linker
+            .func_wrap(
+                "capscr",
+                "config_get",
+                |mut c: Caller<'_, HostState>, ptr: i32, len: i32| -> i64 {
+                    let key = read_str(&mut c, ptr, len);
+                    let Some(value) = c.data().config.get(&key).cloned() else {
+                        return 0;
+                    };
+                    let Some(Extern::Func(alloc)) = c.get_export("capscr_alloc") else {
+                        return 0;
+                    };
+                    let alloc = alloc.typed::<i32, i32>(&c).unwrap();
+                    let at = alloc.call(&mut c, value.len() as i32).unwrap();
+                    guest_memory(&mut c)
+                        .write(&mut c, at as usize, value.as_bytes())
+                        .unwrap();
+                    pack(at as usize, value.len())
+                },
+            )
+            .unwrap();
+        linker.define_unknown_imports_as_traps(&module).unwrap();
+
+        let state = HostState {
+            config: HashMap::new(),
+            logs: Vec::new(),
+        };
+        let mut store = Store::new(engine(), state);
+        let instance = linker.instantiate(&mut store, &module).unwrap();
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .expect("plugin exports `memory`");
+        let alloc = instance
+            .get_typed_func(&mut store, "capscr_alloc")
+            .expect("plugin exports `capscr_alloc`");
+        Plugin {
+            store,
+            instance,
+            memory,
+            alloc,
+            guest: Duration::ZERO,
+        }
+    }
+
+    /// set a config.toml value, as the host's `config_get` would return it.
+    pub fn config(mut self, key: &str, value: &str) -> Plugin {
+        self.store
+            .data_mut()
+            .config
+            .insert(key.to_owned(), value.to_owned());
+        self
+    }
+
+    /// everything the plugin sent to the `log` import, as (level, message).
+    pub fn logs(&self) -> &[(i32, String)] {
+        &self.store.data().logs
+    }
+
+    /// current linear-memory size. wasm memory never shrinks, so this is also
+    /// the peak since instantiation.
+    pub fn memory_size(&self) -> usize {
+        self.memory.data_size(&self.store)
+    }
+
+    /// wall time spent inside hook exports since instantiation — the plugin's
+    /// own work, without the host-side copies in and out of linear memory.
+    pub fn guest_time(&self) -> Duration {
+        self.guest
+    }
+
+    pub fn exports(&mut self, name: &str) -> bool {
+        self.instance.get_func(&mut self.store, name).is_some()
+    }
+
+    fn func<P: wasmtime::WasmParams, R: wasmtime::WasmResults>(
+        &mut self,
+        name: &str,
+    ) -> TypedFunc<P, R> {
+        self.instance
+            .get_typed_func(&mut self.store, name)
+            .unwrap_or_else(|e| panic!("plugin export `{name}`: {e}"))
+    }
+
+    /// call a hook export, adding its run time to [`Plugin::guest_time`].
+    fn call<P: wasmtime::WasmParams, R: wasmtime::WasmResults>(
+        &mut self,
+        hook: &TypedFunc<P, R>,
+        args: P,
+    ) -> R {
+        let start = Instant::now();
+        let ret = hook.call(&mut self.store, args).unwrap();
+        self.guest += start.elapsed();
+        ret
+    }
+
+    /// capscr_alloc a payload and write `parts` into it back to back.
+    fn write(&mut self, parts: &[&[u8]]) -> (i32, i32) {
+        let len: usize = parts.iter().map(|p| p.len()).sum();
+        let ptr = self.alloc.call(&mut self.store, len as i32).unwrap();
+        let mut at = ptr as usize;
+        for part in parts {
+            self.memory.write(&mut self.store, at, part).unwrap();
+            at += part.len();
+        }
+        (ptr, len as i32)
+    }
+
+    /// copy a packed (ptr,len) region out of guest memory.
+    fn read(&self, packed: i64) -> Vec<u8> {
+        let (ptr, len) = unpack(packed);
+        let data = self.memory.data(&self.store);
+        assert!(
+            ptr + len <= data.len(),
+            "reply ({ptr},{len}) is outside linear memory"
+        );
+        data[ptr..ptr + len].to_vec()
+    }
+
+    /// run `capscr_on_capture` once.
+    pub fn on_capture(&mut self, img: &Image, mode: Mode) -> Reply {
+        let hook = self.func::<(i32, i32), i64>("capscr_on_capture");
+        let (ptr, len) = self.write(&[
+            &img.width.to_le_bytes(),
+            &img.height.to_le_bytes(),
+            &mode.to_u32().to_le_bytes(),
+            &img.rgba,
+        ]);
+        let ret = self.call(&hook, (ptr, len));
+        match ret {
+            0 => Reply::Unchanged,
+            IN_PLACE => {
+                let at = ptr as usize + CAPTURE_HEADER_LEN;
+                let rgba = self.read(pack(at, len as usize - CAPTURE_HEADER_LEN));
+                Reply::InPlace(Image::new(img.width, img.height, rgba))
+            }
+            r if r < 0 => Reply::Cancelled,
+            r => {
+                let blob = self.read(r);
+                Reply::Replaced(parse_reply(&blob))
+            }
+        }
+    }
+
+    /// run `capscr_on_capture_inspect` once.
+    pub fn on_capture_inspect(&mut self, img: &Image, mode: Mode) {
+        let hook = self.func::<(i32, i32), ()>("capscr_on_capture_inspect");
+        let (ptr, len) = self.write(&[
+            &img.width.to_le_bytes(),
+            &img.height.to_le_bytes(),
+            &mode.to_u32().to_le_bytes(),
+            &img.rgba,
+        ]);
+        self.call(&hook, (ptr, len));
+    }
+
+    /// stream a `width`×`height` capture through the tiled hook trio,
+    /// `band_rows` rows at a time. `source` fills input row y; `sink` receives
+    /// each batch of output rows. Returns the output size, or None if the
+    /// plugin left the capture untouched.
+    pub fn on_capture_tiled(
+        &mut self,
+        (width, height): (u32, u32),
+        mode: Mode,
+        band_rows: u32,
+        mut source: impl FnMut(u32, &mut [u8]),
+        mut sink: impl FnMut(&[u8]),
+    ) -> Option<(u32, u32)> {
+        let begin = self.func::<(i32, i32), i64>("capscr_on_capture_tiled_begin");
+        let band = self.func::<(i32, i32), i64>("capscr_on_capture_tiled_band");
+        let end = self.func::<(), i64>("capscr_on_capture_tiled_end");
+
+        let (ptr, len) = self.write(&[
+            &width.to_le_bytes(),
+            &height.to_le_bytes(),
+            &mode.to_u32().to_le_bytes(),
+        ]);
+        let ret = self.call(&begin, (ptr, len));
+        if ret == 0 {
+            return None;
+        }
+        let (out_w, out_h) = unpack_size(ret);
+        let out_row = out_w as usize * 4;
+        let row = width as usize * 4;
+        let mut out_rows = 0usize;
+        let mut collect = |plugin: &Plugin, ret: i64, step: &str| {
+            assert!(ret >= 0, "plugin aborted the stream at {step}");
+            if ret > 0 {
+                let rows = plugin.read(ret);
+                assert_eq!(
+                    rows.len() % out_row,
+                    0,
+                    "{step} returned a partial output row"
+                );
+                out_rows += rows.len() / out_row;
+                sink(&rows);
+            }
+        };
+
+        let mut buf = vec![0u8; row * band_rows as usize];
+        let mut y = 0;
+        while y < height {
+            let n = band_rows.min(height - y);
+            let rows = &mut buf[..row * n as usize];
+            for (i, r) in rows.chunks_exact_mut(row).enumerate() {
+                source(y + i as u32, r);
+            }
+            let (ptr, len) = self.write(&[&y.to_le_bytes(), rows]);
+            let ret = self.call(&band, (ptr, len));
+            collect(self, ret, &format!("band y={y}"));
+            y += n;
+        }
+        let ret = self.call(&end, ());
+        collect(self, ret, "end");
+        assert_eq!(
+            out_rows, out_h as usize,
+            "tiled stream emitted the wrong number of rows"
+        );
+        Some((out_w, out_h))
+    }
+}
+
+/// run a capture through plugins in host order: each modifier's replacement
+/// feeds the next, then every inspector sees the final image. None if a
+/// modifier cancelled the capture.
+pub fn run_chain(
+    modifiers: &mut [Plugin],
+    inspectors: &mut [Plugin],
+    img: &Image,
+    mode: Mode,
+) -> Option<Image> {
+    let mut current = img.clone();
+    for plugin in modifiers {
+        match plugin.on_capture(&current, mode) {
+            Reply::Unchanged => {}
+            Reply::Replaced(next) | Reply::InPlace(next) => current = next,
+            Reply::Cancelled => return None,
+        }
+    }
+    for plugin in inspectors {
+        plugin.on_capture_inspect(&current, mode);
+    }
+    Some(current)
+}
+
+/// validate a `[w][h][rgba]` replacement the way the host does.
+fn parse_reply(blob: &[u8]) -> Image {
+    assert!(
+        blob.len() >= REPLY_HEADER_LEN,
+        "replacement shorter than its header"
+    );
+    let w = u32::from_le_bytes(blob[0..4].try_into().unwrap());
+    let h = u32::from_le_bytes(blob[4..8].try_into().unwrap());
+    let rgba = &blob[REPLY_HEADER_LEN..];
+    assert_eq!(
+        rgba.len(),
+        w as usize * h as usize * 4,
+        "replacement size mismatch for {w}x{h}"
+    );
+    Image::new(w, h, rgba.to_vec())
+}
+
+fn guest_memory(c: &mut Caller<'_, HostState>) -> Memory {
+    match c.get_export("memory") {
+        Some(Extern::Memory(m)) => m,
+        _ => panic!("plugin exports no `memory`"),
+    }
+}
+
+fn read_str(c: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> String {
+    let mem = guest_memory(c);
+    let bytes = &mem.data(&c)[ptr as usize..ptr as usize + len as usize];
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(Engine::default)
+}
+
+fn module(path: &Path) -> Module {
+    static MODULES: OnceLock<Mutex<HashMap<PathBuf, Module>>> = OnceLock::new();
+    let mut cache = MODULES.get_or_init(Default::default).lock().unwrap();
+    cache
+        .entry(path.to_owned())
+        .or_insert_with(|| Module::from_file(engine(), path).unwrap())
+        .clone()
+}
+
+pub(crate) fn workspace_root() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap()
+}
+
+/// `cargo build --release --target wasm32-unknown-unknown -p capscr-<id>`
+/// into a per-feature-set dir under target/harness; returns the .wasm path.
+pub fn build(id: &str, features: &[&str]) -> PathBuf {
+    static BUILT: OnceLock<Mutex<HashMap<String, PathBuf>>> = OnceLock::new();
+    let variant = if features.is_empty() {
+        "default".to_owned()
+    } else {
+        features.join("-")
+    };
+    let key = format!("{id}/{variant}");
+    let mut built = BUILT.get_or_init(Default::default).lock().unwrap();
+    if let Some(path) = built.get(&key) {
+        return path.clone();
+    }
+
+    let target_dir = workspace_root()
+        .join("target")
+        .join("harness")
+        .join(&variant);
+    let mut cmd = Command::new(std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into()));
+    cmd.current_dir(workspace_root())
+        .args(["build", "--release", "--target", WASM_TARGET, "-p"])
+        .arg(format!("capscr-{id}"))
+        .arg("--target-dir")
+        .arg(&target_dir);
+    if !features.is_empty() {
+        cmd.arg("--features").arg(features.join(","));
+    }
+    // don't inherit the outer build's flags/wrappers (e.g. clippy-driver)
+    for var in [
+        "RUSTFLAGS",
+        "CARGO_ENCODED_RUSTFLAGS",
+        "RUSTC_WRAPPER",
+        "RUSTC_WORKSPACE_WRAPPER",
+        "CARGO_TARGET_DIR",
+        "CARGO_BUILD_TARGET",
+    ] {
+        cmd.env_remove(var);
+    }
+    let out = cmd.output().expect("failed to run cargo");
+    assert!(
+        out.status.success(),
+        "building {key} for {WASM_TARGET} failed (is the target installed? \
+         `rustup target add {WASM_TARGET}`):\n{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    let path = target_dir
+        .join(WASM_TARGET)
+        .join("release")
+        .join(format!("capscr_{}.wasm", id.replace('-', "_")));
+    built.insert(key, path.clone());
+    path
+}
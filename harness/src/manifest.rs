@@ -0,0 +1,158 @@
+//! plugin.toml checks: the hooks a manifest declares must agree with the
+//! capabilities it asks for, and every hook must be exported by the module.
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::workspace_root;
+
+/// hooks that may replace the image; they need `image = ["read", "modify"]`.
+pub const MODIFYING_HOOKS: &[&str] = &["on_capture", "on_capture_tiled"];
+/// hooks that only see the final image; `image = ["read"]` is enough.
+pub const READ_ONLY_HOOKS: &[&str] = &["on_capture_inspect"];
+/// event hooks with a utf-8 payload and no image access.
+pub const EVENT_HOOKS: &[&str] = &["on_capture_saved", "on_upload_success"];
+
+/// the parts of a sectioned plugin.toml the harness cares about.
+#[derive(Debug)]
+pub struct Manifest {
+    pub id: String,
+    pub runtime: Option<String>,
+    /// hook name → export name
+    pub hooks: BTreeMap<String, String>,
+    /// capability → permissions (e.g. "image" → ["read"])
+    pub capabilities: BTreeMap<String, Vec<String>>,
+}
+
+impl Manifest {
+    /// read `<id>/plugin.toml` from the workspace.
+    pub fn load(id: &str) -> Manifest {
+        let path = workspace_root().join(id).join("plugin.toml");
+        let text = fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+        Manifest::parse(&text).unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Manifest, String> {
+        let doc: toml::Table = text.parse().map_err(|e| format!("{e}"))?;
+        let section = |name: &str| doc.get(name).and_then(|v| v.as_table());
+        let id = section("plugin")
+            .and_then(|p| p.get("id"))
+            .and_then(|v| v.as_str())
+            .ok_or("missing [plugin] id")?
+            .to_owned();
+        let runtime = section("runtime")
+            .and_then(|r| r.get("type"))
+            .and_then(|v| v.as_str())
+            .map(str::to_owned);
+        let mut hooks = BTreeMap::new();
+        for (name, export) in section("hooks").into_iter().flatten() {
+            let export = export
+                .as_str()
+                .ok_or(format!("hook {name} is not a string"))?;
+            hooks.insert(name.clone(), export.to_owned());
+        }
+        let mut capabilities = BTreeMap::new();
+        for (name, perms) in section("capabilities").into_iter().flatten() {
+            let perms = perms
+                .as_array()
+                .ok_or(format!("capability {name} is not an array"))?
+                .iter()
+                .map(|p| {
+                    p.as_str()
+                        .map(str::to_owned)
+                        .ok_or(format!("{name}: non-string entry"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            capabilities.insert(name.clone(), perms);
+        }
+        Ok(Manifest {
+            id,
+            runtime,
+            hooks,
+            capabilities,
+        })
+    }
+
+    pub fn is_wasm(&self) -> bool {
+        self.runtime.as_deref() == Some("wasm")
+    }
+
+    fn image(&self, perm: &str) -> bool {
+        self.capabilities
+            .get("image")
+            .is_some_and(|perms| perms.iter().any(|p| p == perm))
+    }
+
+    fn declares(&self, hooks: &[&str]) -> bool {
+        hooks.iter().any(|h| self.hooks.contains_key(*h))
+    }
+
+    /// every way hooks and capabilities disagree; empty when consistent.
+    pub fn problems(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        for hook in self.hooks.keys() {
+            let known = [MODIFYING_HOOKS, READ_ONLY_HOOKS, EVENT_HOOKS]
+                .iter()
+                .any(|set| set.contains(&hook.as_str()));
+            if !known {
+                out.push(format!("unknown hook `{hook}`"));
+            }
+        }
+        for perm in self.capabilities.get("image").into_iter().flatten() {
+            if perm != "read" && perm != "modify" {
+                out.push(format!("unknown image permission `{perm}`"));
+            }
+        }
+        let modifies = self.declares(MODIFYING_HOOKS);
+        let reads = modifies || self.declares(READ_ONLY_HOOKS);
+        if modifies && !(self.image("read") && self.image("modify")) {
+            out.push("on_capture hooks need image = [\"read\", \"modify\"]".to_owned());
+        }
+        if reads && !self.image("read") {
+            out.push("on_capture_inspect needs image = [\"read\"]".to_owned());
+        }
+        if self.image("modify") && !modifies {
+            out.push("image \"modify\" requested but no hook can modify the image".to_owned());
+        }
+        if self.image("read") && !reads {
+            out.push("image \"read\" requested but no hook receives the image".to_owned());
+        }
+        if self.hooks.contains_key("on_capture_tiled") && !self.hooks.contains_key("on_capture") {
+            out.push(
+                "on_capture_tiled needs an on_capture fallback for hosts without tiling".to_owned(),
+            );
+        }
+        out
+    }
+
+    /// the exports the module must provide for the declared hooks.
+    pub fn required_exports(&self) -> Vec<String> {
+        let mut out = vec!["capscr_alloc".to_owned()];
+        for (hook, export) in &self.hooks {
+            if hook == "on_capture_tiled" {
+                out.extend(["_begin", "_band", "_end"].map(|s| format!("{export}{s}")));
+            } else {
+                out.push(export.clone());
+            }
+        }
+        out
+    }
+}
+
+/// ids of every WASM plugin in the workspace. Metadata-only plugins (sounds,
+/// hotbar) still use the flat manifest form and are skipped.
+pub fn wasm_plugins() -> Vec<String> {
+    let mut ids: Vec<String> = fs::read_dir(workspace_root())
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            fs::read_to_string(e.path().join("plugin.toml"))
+                .ok()
+                .and_then(|text| Manifest::parse(&text).ok())
+                .is_some_and(|m| m.is_wasm())
+        })
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .collect();
+    ids.sort();
+    ids
+}
@@ -0,0 +1,218 @@
+//! auto-levels: the full-range guard, luminance and per-channel stretching,
+//! clip percentiles, CLAHE raising local contrast without touching hue or
+//! alpha, and config handling.
+
+use capscr_plugin_harness::{Image, Plugin, Reply};
+use capscr_plugin_sdk::image::Mode;
+use capscr_plugin_sdk::log::WARN;
+
+fn auto_levels(plugin: &mut Plugin, img: &Image) -> Image {
+    match plugin.on_capture(img, Mode::Region) {
+        Reply::InPlace(out) => out,
+        other => panic!("expected an in-place reply, got {other:?}"),
+    }
+}
+
+/// a `w`×`h` image whose columns ramp through `lo..=hi` in each channel,
+/// tinted by `tint` (added, then clamped).
+fn ramp(w: u32, h: u32, lo: u8, hi: u8, tint: [i32; 3]) -> Image {
+    let mut rgba = Vec::new();
+    for _ in 0..h {
+        for x in 0..w {
+            let v = lo as u32 + x * (hi - lo) as u32 / (w - 1);
+            for t in tint {
+                rgba.push((v as i32 + t).clamp(0, 255) as u8);
+            }
+            rgba.push(255);
+        }
+    }
+    Image::new(w, h, rgba)
+}
+
+/// min and max of channel `c` over `img`.
+fn extent(img: &Image, c: usize) -> (u8, u8) {
+    img.rgba
+        .chunks_exact(4)
+        .fold((255, 0), |(lo, hi), px| (lo.min(px[c]), hi.max(px[c])))
+}
+
+/// light UI: white with blocks of black "text" and a grey toolbar.
+fn ui() -> Image {
+    let mut img = Image::filled(200, 120, [255; 4]);
+    for (i, px) in img.rgba.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i % 200, i / 200);
+        if y < 20 {
+            px[..3].copy_from_slice(&[230, 232, 236]);
+        } else if y % 12 < 7 && x % 9 < 6 && x > 10 {
+            px[..3].copy_from_slice(&[0, 0, 0]);
+        }
+    }
+    img
+}
+
+#[test]
+fn full_range_captures_pass_through() {
+    let img = ui();
+    for method in ["levels", "clahe"] {
+        let mut plugin = Plugin::load("auto-levels").config("method", method);
+        assert_eq!(auto_levels(&mut plugin, &img), img, "{method}");
+        assert!(plugin.logs().iter().any(|(_, m)| m.contains("full range")));
+    }
+    // a margin of 0 still counts exact black and white as full range
+    let mut plugin = Plugin::load("auto-levels").config("full_range_margin", "0");
+    assert_eq!(auto_levels(&mut plugin, &img), img);
+}
+
+#[test]
+fn luminance_levels_stretch_every_channel_alike() {
+    let img = ramp(141, 40, 40, 180, [0, 0, 0]);
+    let out = auto_levels(&mut Plugin::load("auto-levels"), &img);
+    for c in 0..3 {
+        assert_eq!(extent(&out, c), (0, 255));
+    }
+    // halfway (110) lands halfway
+    assert_eq!(out.pixel(70, 5), [128, 128, 128, 255]);
+
+    // a tint survives: the same stretch applies to all three channels
+    let tinted = ramp(141, 40, 40, 180, [20, 0, -20]);
+    let out = auto_levels(&mut Plugin::load("auto-levels"), &tinted);
+    let px = out.pixel(70, 5);
+    assert!(px[0] > px[1] && px[1] > px[2], "{px:?}");
+}
+
+#[test]
+fn per_channel_levels_remove_a_color_cast() {
+    let cast = ramp(141, 40, 40, 180, [30, 0, -30]);
+    let mut plugin = Plugin::load("auto-levels").config("histogram", "per-channel");
+    let out = auto_levels(&mut plugin, &cast);
+    for c in 0..3 {
+        assert_eq!(extent(&out, c), (0, 255), "channel {c}");
+    }
+    let px = out.pixel(70, 5);
+    assert!(px[0].abs_diff(px[2]) <= 2, "{px:?}");
+}
+
+#[test]
+fn clip_percentiles_ignore_outliers() {
+    // a dim ramp with 1% of its pixels near black and 1% near white
+    let mut img = ramp(100, 100, 60, 160, [0, 0, 0]);
+    for i in 0..100 {
+        let v = if i % 2 == 0 { 5 } else { 250 };
+        img.rgba[i * 4 * 100..][..3].copy_from_slice(&[v; 3]);
+    }
+    // the default 0.5% lets half of them through, and the ramp isn't stretched
+    // (the guard sees black and white)
+    let out = auto_levels(&mut Plugin::load("auto-levels"), &img);
+    assert_eq!(out, img);
+
+    // clipping 1% at each end of a capture that isn't full range stretches
+    // the ramp itself
+    let mut plugin = Plugin::load("auto-levels")
+        .config("clip_low", "1")
+        .config("clip_high", "1")
+        .config("full_range_margin", "0");
+    let out = auto_levels(&mut plugin, &img);
+    let row: Vec<u8> = (1..100).map(|x| out.pixel(x, 50)[0]).collect();
+    assert_eq!((row[0], row[98]), (0, 255), "{row:?}");
+}
+
+#[test]
+fn near_flat_captures_are_left_alone() {
+    let img = ramp(64, 16, 100, 120, [0, 0, 0]);
+    let mut plugin = Plugin::load("auto-levels");
+    assert_eq!(auto_levels(&mut plugin, &img), img);
+    assert!(plugin.logs().iter().any(|(_, m)| m.contains("too flat")));
+}
+
+#[test]
+fn clahe_raises_local_contrast() {
+    // two low-contrast halves, one dim and one bright
+    let dim = ramp(128, 128, 20, 60, [0, 0, 0]);
+    let mut img = ramp(256, 128, 190, 230, [0, 0, 0]);
+    for y in 0..128 {
+        let src = &dim.rgba[y * 128 * 4..][..128 * 4];
+        img.rgba[y * 256 * 4..][..128 * 4].copy_from_slice(src);
+    }
+    let mut plugin = Plugin::load("auto-levels")
+        .config("method", "clahe")
+        .config("tiles", "2");
+    let out = auto_levels(&mut plugin, &img);
+    assert!(plugin.logs().iter().all(|(l, _)| *l != WARN));
+    let spread = |img: &Image, x0: u32| {
+        let row: Vec<u8> = (x0..x0 + 100).map(|x| img.pixel(x, 64)[0]).collect();
+        row.iter().max().unwrap() - row.iter().min().unwrap()
+    };
+    for x0 in [14, 142] {
+        assert!(
+            spread(&out, x0) as f32 > 1.5 * spread(&img, x0) as f32,
+            "x0 {x0}: {} -> {}",
+            spread(&img, x0),
+            spread(&out, x0)
+        );
+    }
+    // greys stay grey; alpha is never touched
+    for px in out.rgba.chunks_exact(4) {
+        assert!(px[0] == px[1] && px[1] == px[2] && px[3] == 255, "{px:?}");
+    }
+}
+
+#[test]
+fn clahe_shifts_all_channels_by_the_same_amount() {
+    let mut img = Image::synthetic(90, 70);
+    for px in img.rgba.chunks_exact_mut(4) {
+        for c in &mut px[..3] {
+            *c = 60 + *c / 3;
+        }
+    }
+    let mut plugin = Plugin::load("auto-levels")
+        .config("method", "clahe")
+        .config("tiles", "3");
+    let out = auto_levels(&mut plugin, &img);
+    assert_ne!(out, img);
+    for (a, b) in img.rgba.chunks_exact(4).zip(out.rgba.chunks_exact(4)) {
+        // channels well inside 0..=255 move together
+        let d: Vec<i32> = (0..3).map(|c| b[c] as i32 - a[c] as i32).collect();
+        assert!(d[0] == d[1] && d[1] == d[2], "{a:?} -> {b:?}");
+        assert_eq!(a[3], b[3]);
+    }
+}
+
+#[test]
+fn clahe_handles_captures_smaller_than_a_tile() {
+    // one tile, with a limit high enough not to clip its few pixels
+    let img = ramp(10, 6, 80, 140, [0, 0, 0]);
+    let mut plugin = Plugin::load("auto-levels")
+        .config("method", "clahe")
+        .config("clip_limit", "40");
+    let out = auto_levels(&mut plugin, &img);
+    assert_eq!((out.width, out.height), (10, 6));
+    assert!(extent(&out, 0).1 - extent(&out, 0).0 > 60);
+}
+
+#[test]
+fn invalid_settings_warn_and_fall_back() {
+    let img = ramp(141, 20, 40, 180, [0, 0, 0]);
+    let default = auto_levels(&mut Plugin::load("auto-levels"), &img);
+    for (key, value) in [
+        ("method", "equalize"),
+        ("histogram", "rgb"),
+        ("clip_low", "20"),
+        ("clip_high", "-1"),
+        ("full_range_margin", "100"),
+        ("tiles", "0"),
+        ("clip_limit", "0.5"),
+    ] {
+        let mut plugin = Plugin::load("auto-levels").config(key, value);
+        assert_eq!(auto_levels(&mut plugin, &img), default, "{key} = {value}");
+        let warned = plugin.logs().iter().filter(|(l, _)| *l == WARN).count();
+        assert_eq!(warned, 1, "{key} = {value}");
+    }
+}
+
+#[test]
+fn only_modes_skips_other_captures() {
+    let img = ramp(141, 20, 40, 180, [0, 0, 0]);
+    let mut plugin = Plugin::load("auto-levels").config("only_modes", "[\"window\"]");
+    assert_eq!(plugin.on_capture(&img, Mode::Region), Reply::Unchanged);
+    assert_ne!(plugin.on_capture(&img, Mode::Window).image(), img);
+}
@@ -0,0 +1,202 @@
+//! beautify: canvas size (padding and aspect), the capture intact in the
+//! middle, gradient and solid backdrops, the corner mask and the shadow.
+
+use capscr_plugin_harness::{Image, Plugin, Reply};
+use capscr_plugin_sdk::image::Mode;
+use capscr_plugin_sdk::log::WARN;
+
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+/// the synthetic pattern with every pixel made opaque.
+fn opaque(w: u32, h: u32) -> Image {
+    let mut img = Image::synthetic(w, h);
+    img.rgba.chunks_exact_mut(4).for_each(|px| px[3] = 255);
+    img
+}
+
+fn beautified(plugin: &mut Plugin, img: &Image) -> Image {
+    match plugin.on_capture(img, Mode::Region) {
+        Reply::Replaced(out) => out,
+        other => panic!("expected a replacement, got {other:?}"),
+    }
+}
+
+/// a plain white backdrop with no corners or shadow, for isolating one effect.
+fn plain() -> Plugin {
+    Plugin::load("beautify")
+        .config("background", "solid")
+        .config("color", "#ffffff")
+        .config("corner_radius", "0")
+        .config("shadow", "false")
+}
+
+#[test]
+fn capture_sits_untouched_in_the_middle_of_the_padding() {
+    let img = opaque(50, 30);
+    let out = beautified(&mut plain().config("padding", "20"), &img);
+    assert_eq!((out.width, out.height), (90, 70));
+    for y in 0..30 {
+        for x in 0..50 {
+            assert_eq!(out.pixel(x + 20, y + 20), img.pixel(x, y), "({x},{y})");
+        }
+    }
+    for (x, y) in [(0, 0), (19, 35), (70, 69), (45, 19)] {
+        assert_eq!(out.pixel(x, y), WHITE, "({x},{y})");
+    }
+}
+
+#[test]
+fn defaults_round_the_corners_and_cast_a_shadow() {
+    let img = Image::filled(100, 60, [10, 200, 30, 255]);
+    let out = beautified(&mut Plugin::load("beautify"), &img);
+    assert_eq!((out.width, out.height), (228, 188));
+    // the capture's corner pixel is backdrop and shadow now, its centre intact
+    assert_ne!(out.pixel(64, 64), [10, 200, 30, 255]);
+    assert_eq!(out.pixel(64 + 50, 64 + 30), [10, 200, 30, 255]);
+    // the backdrop is opaque everywhere
+    assert!(out.rgba.chunks_exact(4).all(|px| px[3] == 255));
+}
+
+#[test]
+fn linear_gradient_runs_along_the_angle() {
+    let img = opaque(8, 8);
+    let mut plugin = plain()
+        .config("background", "linear")
+        .config("color", "[0, 0, 0]")
+        .config("color_to", "[200, 100, 50]")
+        .config("angle", "90")
+        .config("padding", "40");
+    let out = beautified(&mut plugin, &img);
+    // 90° runs left to right: the ends are the two stops, columns are uniform
+    assert!(out.pixel(0, 0)[0] <= 3, "{:?}", out.pixel(0, 0));
+    assert!(out.pixel(87, 0)[0] >= 197, "{:?}", out.pixel(87, 0));
+    assert_eq!(out.pixel(30, 0), out.pixel(30, 87));
+    let reds: Vec<u8> = (0..88).map(|x| out.pixel(x, 2)[0]).collect();
+    assert!(reds.windows(2).all(|p| p[0] <= p[1]), "{reds:?}");
+
+    // the default 135° runs from the top-left corner to the bottom-right one
+    let out = beautified(&mut plugin.config("angle", "135"), &img);
+    assert!(out.pixel(0, 0)[0] <= 3);
+    assert!(out.pixel(87, 87)[0] >= 197);
+    assert_eq!(out.pixel(87, 0), out.pixel(0, 87));
+}
+
+#[test]
+fn radial_gradient_starts_in_the_centre() {
+    let img = opaque(2, 2);
+    let mut plugin = plain()
+        .config("background", "radial")
+        .config("color", "[0, 0, 0]")
+        .config("color_to", "[250, 250, 250]")
+        .config("padding", "50");
+    let out = beautified(&mut plugin, &img);
+    assert!(out.pixel(50, 50)[0] < 10, "{:?}", out.pixel(50, 50));
+    assert!(out.pixel(0, 0)[0] > 240, "{:?}", out.pixel(0, 0));
+    assert_eq!(out.pixel(0, 0), out.pixel(101, 101));
+}
+
+#[test]
+fn aspect_grows_the_short_side_and_keeps_the_capture_centred() {
+    let img = opaque(100, 100);
+    for (aspect, size) in [
+        ("16:9", (356, 200)),
+        ("1:1", (200, 200)),
+        ("3:4", (200, 267)),
+    ] {
+        let out = beautified(
+            &mut plain().config("padding", "50").config("aspect", aspect),
+            &img,
+        );
+        assert_eq!((out.width, out.height), size, "{aspect}");
+        let (left, top) = ((size.0 - 100) / 2, (size.1 - 100) / 2);
+        assert_eq!(out.pixel(left, top), img.pixel(0, 0), "{aspect}");
+        assert_eq!(
+            out.pixel(left + 99, top + 99),
+            img.pixel(99, 99),
+            "{aspect}"
+        );
+    }
+}
+
+#[test]
+fn corner_mask_is_transparent_to_the_backdrop() {
+    let img = Image::filled(40, 40, [0, 0, 0, 255]);
+    let out = beautified(
+        &mut plain()
+            .config("padding", "10")
+            .config("corner_radius", "10"),
+        &img,
+    );
+    // the cut corner shows the white backdrop, the arc blends, the rest is black
+    assert_eq!(out.pixel(10, 10), WHITE);
+    assert!((0..10).any(|i| (1..255).contains(&out.pixel(10 + i, 10 + i)[0])));
+    assert_eq!(out.pixel(20, 10), [0, 0, 0, 255]);
+    assert_eq!(out.pixel(30, 30), [0, 0, 0, 255]);
+}
+
+#[test]
+fn shadow_falls_below_the_capture() {
+    let img = Image::filled(60, 40, [0, 0, 255, 255]);
+    let out = beautified(
+        &mut plain()
+            .config("padding", "40")
+            .config("shadow", "true")
+            .config("shadow_offset_y", "12")
+            .config("shadow_opacity", "0.5"),
+        &img,
+    );
+    // just below the capture is darker than just above it
+    let (above, below) = (out.pixel(70, 38), out.pixel(70, 81));
+    assert!(below[0] < above[0], "above {above:?} below {below:?}");
+    assert!(below[0] < 200, "{below:?}");
+    // and far from it the backdrop is clean
+    assert_eq!(out.pixel(0, 0), WHITE);
+}
+
+#[test]
+fn only_modes_limits_which_captures_are_beautified() {
+    let img = opaque(10, 10);
+    let mut plugin = Plugin::load("beautify").config("only_modes", "[\"window\", \"region\"]");
+    assert_eq!(plugin.on_capture(&img, Mode::FullScreen), Reply::Unchanged);
+    assert!(matches!(
+        plugin.on_capture(&img, Mode::Window),
+        Reply::Replaced(_)
+    ));
+}
+
+#[test]
+fn invalid_values_fall_back_with_a_warning() {
+    let img = opaque(10, 10);
+    let mut plugin = Plugin::load("beautify")
+        .config("background", "plaid")
+        .config("aspect", "16x9")
+        .config("padding", "-1")
+        .config("shadow_opacity", "2");
+    let out = beautified(&mut plugin, &img);
+    assert_eq!((out.width, out.height), (138, 138));
+    let warned: Vec<_> = plugin.logs().iter().filter(|(l, _)| *l == WARN).collect();
+    assert_eq!(warned.len(), 4, "{warned:?}");
+
+    // past 10:1 either way: ignored with a warning, not a canvas too big to
+    // return
+    for aspect in ["100:1", "1:11"] {
+        let mut plugin = Plugin::load("beautify").config("aspect", aspect);
+        let out = beautified(&mut plugin, &img);
+        assert_eq!((out.width, out.height), (138, 138), "{aspect}");
+        let warned = plugin.logs().iter().filter(|(l, _)| *l == WARN).count();
+        assert_eq!(warned, 1, "{aspect}");
+    }
+}
+
+#[test]
+fn oversized_canvas_is_skipped() {
+    let mut plugin = plain().config("padding", "8");
+    assert!(matches!(
+        plugin.on_capture(&Image::filled(16368, 1, WHITE), Mode::Region),
+        Reply::Replaced(_)
+    ));
+    assert_eq!(
+        plugin.on_capture(&Image::filled(16369, 1, WHITE), Mode::Region),
+        Reply::Unchanged
+    );
+}
@@ -0,0 +1,73 @@
+//! The SDK's separable Gaussian blur against a direct 2-D convolution.
+
+use capscr_plugin_sdk::blur::{blur_plane, kernel, Edge};
+
+/// the same blur, as one 2-D sum per output pixel.
+fn direct(src: &[f32], w: usize, h: usize, k: &[f32], edge: Edge) -> Vec<f32> {
+    let r = (k.len() / 2) as isize;
+    let at = |x: isize, y: isize| -> f32 {
+        match edge {
+            _ if (0..w as isize).contains(&x) && (0..h as isize).contains(&y) => {
+                src[y as usize * w + x as usize]
+            }
+            Edge::Zero => 0.0,
+            Edge::Clamp => {
+                src[y.clamp(0, h as isize - 1) as usize * w + x.clamp(0, w as isize - 1) as usize]
+            }
+        }
+    };
+    let mut out = vec![0.0; w * h];
+    for y in 0..h as isize {
+        for x in 0..w as isize {
+            let mut acc = 0.0;
+            for (j, ky) in k.iter().enumerate() {
+                for (i, kx) in k.iter().enumerate() {
+                    acc += ky * kx * at(x + i as isize - r, y + j as isize - r);
+                }
+            }
+            out[y as usize * w + x as usize] = acc;
+        }
+    }
+    out
+}
+
+#[test]
+fn kernel_is_normalised_and_symmetric() {
+    for sigma in [0.3, 1.0, 2.5, 8.0, 40.0] {
+        let k = kernel(sigma);
+        assert_eq!(k.len(), 2 * (sigma * 3.0f32).ceil() as usize + 1);
+        assert!((k.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        assert!(k.iter().zip(k.iter().rev()).all(|(a, b)| a == b));
+    }
+    assert_eq!(kernel(0.0), vec![1.0]);
+}
+
+#[test]
+fn separable_matches_direct() {
+    // wider than one vertical-pass strip, so strip edges get checked too
+    let (w, h) = (150, 23);
+    let src: Vec<f32> = (0..w * h)
+        .map(|i| ((i * 7919) % 101) as f32 / 100.0)
+        .collect();
+    for edge in [Edge::Zero, Edge::Clamp] {
+        for sigma in [0.8, 3.0] {
+            let k = kernel(sigma);
+            let mut got = src.clone();
+            blur_plane(&mut got, w, h, &k, edge);
+            let want = direct(&src, w, h, &k, edge);
+            for (i, (g, w)) in got.iter().zip(&want).enumerate() {
+                assert!(
+                    (g - w).abs() < 1e-4,
+                    "{edge:?} σ={sigma} at {i}: {g} vs {w}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn clamp_keeps_a_flat_plane_flat() {
+    let mut plane = vec![0.5; 40 * 30];
+    blur_plane(&mut plane, 40, 30, &kernel(6.0), Edge::Clamp);
+    assert!(plane.iter().all(|v| (v - 0.5).abs() < 1e-5));
+}
@@ -0,0 +1,348 @@
+//! borders: the config keys, their validation, the unconfigured output
+//! staying byte-identical to the hardcoded v0.2.0 border, and a golden image
+//! per style.
+
+use capscr_plugin_harness::{Image, Plugin, Reply};
+use capscr_plugin_sdk::image::Mode;
+use capscr_plugin_sdk::log::WARN;
+
+const GRAY: [u8; 4] = [40, 40, 40, 255];
+const CLEAR: [u8; 4] = [0; 4];
+
+/// the v0.2.0 loop: fill with `color`, blit the capture `border` px in.
+fn reference(img: &Image, border: u32, color: [u8; 4]) -> Image {
+    let (nw, nh) = (img.width + 2 * border, img.height + 2 * border);
+    let mut out = Image::filled(nw, nh, color);
+    let row = img.width as usize * 4;
+    for y in 0..img.height as usize {
+        let dst = ((y + border as usize) * nw as usize + border as usize) * 4;
+        out.rgba[dst..dst + row].copy_from_slice(&img.rgba[y * row..][..row]);
+    }
+    out
+}
+
+/// render `style` at `size` around a `w`×`h` capture and compare it with an
+/// ascii golden: `#` colour, `d`/`l` its dark/light 3-D shades, `.` clear,
+/// `o` the capture.
+fn golden(style: &str, size: u32, (w, h): (u32, u32), want: &[&str]) {
+    let img = Image::filled(w, h, [1, 2, 3, 255]);
+    let mut plugin = Plugin::load("borders")
+        .config("style", style)
+        .config("size", &size.to_string())
+        .config("color", "[100, 100, 100]");
+    let out = bordered(&mut plugin, &img);
+    let got: Vec<String> = (0..out.height)
+        .map(|y| {
+            (0..out.width)
+                .map(|x| match out.pixel(x, y) {
+                    [100, 100, 100, 255] => '#',
+                    [50, 50, 50, 255] => 'd',
+                    [177, 177, 177, 255] => 'l',
+                    [0, 0, 0, 0] => '.',
+                    [1, 2, 3, 255] => 'o',
+                    _ => '?',
+                })
+                .collect()
+        })
+        .collect();
+    assert_eq!(got, want, "{style} {size}px");
+}
+
+fn bordered(plugin: &mut Plugin, img: &Image) -> Image {
+    match plugin.on_capture(img, Mode::Region) {
+        Reply::Replaced(out) => out,
+        other => panic!("expected a replacement, got {other:?}"),
+    }
+}
+
+#[test]
+fn defaults_match_v0_2() {
+    let mut plugin = Plugin::load("borders");
+    for (w, h) in [(1, 1), (33, 17), (200, 3)] {
+        let img = Image::synthetic(w, h);
+        assert_eq!(
+            bordered(&mut plugin, &img),
+            reference(&img, 8, GRAY),
+            "{w}x{h}"
+        );
+    }
+    assert!(plugin.logs().is_empty());
+}
+
+#[test]
+fn size_and_color() {
+    let img = Image::synthetic(20, 10);
+    let mut plugin = Plugin::load("borders")
+        .config("size", "3")
+        .config("color", "#ff000080");
+    assert_eq!(
+        bordered(&mut plugin, &img),
+        reference(&img, 3, [255, 0, 0, 128])
+    );
+    let mut plugin = Plugin::load("borders").config("color", "[1, 2, 3]");
+    assert_eq!(
+        bordered(&mut plugin, &img),
+        reference(&img, 8, [1, 2, 3, 255])
+    );
+}
+
+#[test]
+fn padding_is_transparent() {
+    let img = Image::filled(4, 4, [9, 9, 9, 255]);
+    let out = bordered(
+        &mut Plugin::load("borders")
+            .config("size", "2")
+            .config("padding", "3"),
+        &img,
+    );
+    assert_eq!((out.width, out.height), (14, 14));
+    for (x, y, want) in [
+        (0, 0, GRAY),
+        (1, 7, GRAY),
+        (2, 2, CLEAR),
+        (4, 7, CLEAR),
+        (7, 2, CLEAR),
+        (5, 5, [9, 9, 9, 255]),
+        (8, 8, [9, 9, 9, 255]),
+        (9, 7, CLEAR),
+        (12, 7, GRAY),
+        (7, 13, GRAY),
+    ] {
+        assert_eq!(out.pixel(x, y), want, "({x},{y})");
+    }
+}
+
+#[test]
+fn corner_radius_rounds_the_outside_only() {
+    let img = Image::filled(40, 30, [200, 200, 200, 255]);
+    let out = bordered(
+        &mut Plugin::load("borders").config("corner_radius", "10"),
+        &img,
+    );
+    let (w, h) = (out.width - 1, out.height - 1);
+    for (x, y) in [(0, 0), (w, 0), (0, h), (w, h), (1, 1)] {
+        assert_eq!(out.pixel(x, y)[3], 0, "corner ({x},{y}) should be cut away");
+    }
+    // edges, centre and the area just inside the arc stay opaque
+    for (x, y) in [(10, 0), (0, 10), (w / 2, 0), (w / 2, h / 2), (w - 4, h - 4)] {
+        assert_eq!(out.pixel(x, y)[3], 255, "({x},{y}) should be untouched");
+    }
+    // anti-aliased: some pixel on the arc is partially covered
+    assert!((0..10).any(|i| (1..255).contains(&out.pixel(i, 10 - i - 1)[3])));
+    // colour channels are left alone; only alpha is scaled
+    assert_eq!(out.pixel(0, 0)[..3], GRAY[..3]);
+}
+
+#[test]
+fn radius_alone_rounds_the_capture() {
+    let img = Image::filled(20, 20, [5, 6, 7, 255]);
+    let out = bordered(
+        &mut Plugin::load("borders")
+            .config("size", "0")
+            .config("corner_radius", "50"),
+        &img,
+    );
+    assert_eq!((out.width, out.height), (20, 20));
+    assert_eq!(out.pixel(0, 0)[3], 0);
+    assert_eq!(out.pixel(10, 10), [5, 6, 7, 255]);
+}
+
+#[test]
+fn nothing_to_draw_leaves_the_capture() {
+    let img = Image::synthetic(8, 8);
+    let mut plugin = Plugin::load("borders").config("size", "0");
+    assert_eq!(plugin.on_capture(&img, Mode::Region), Reply::Unchanged);
+}
+
+#[test]
+fn invalid_values_fall_back_with_a_warning() {
+    let img = Image::synthetic(12, 12);
+    let mut plugin = Plugin::load("borders")
+        .config("size", "-4")
+        .config("padding", "99999")
+        .config("color", "chartreuse")
+        .config("corner_radius", "round");
+    assert_eq!(bordered(&mut plugin, &img), reference(&img, 8, GRAY));
+    let warned: Vec<_> = plugin.logs().iter().filter(|(l, _)| *l == WARN).collect();
+    assert_eq!(warned.len(), 4, "{warned:?}");
+}
+
+#[test]
+fn oversized_output_is_skipped() {
+    // 16384 - 2*8 fits exactly; one more pixel would not
+    let mut plugin = Plugin::load("borders");
+    assert!(matches!(
+        plugin.on_capture(&Image::filled(16368, 1, GRAY), Mode::Region),
+        Reply::Replaced(_)
+    ));
+    assert_eq!(
+        plugin.on_capture(&Image::filled(16369, 1, GRAY), Mode::Region),
+        Reply::Unchanged
+    );
+    let mut plugin = Plugin::load("borders").config("padding", "8000");
+    assert_eq!(
+        plugin.on_capture(&Image::filled(400, 1, GRAY), Mode::Region),
+        Reply::Unchanged
+    );
+}
+
+#[test]
+fn golden_solid() {
+    golden(
+        "solid",
+        2,
+        (3, 1),
+        &["#######", "#######", "##ooo##", "#######", "#######"],
+    );
+}
+
+#[test]
+fn golden_double() {
+    golden(
+        "double",
+        5,
+        (4, 2),
+        &[
+            "##############",
+            "##############",
+            "##..........##",
+            "##.########.##",
+            "##.########.##",
+            "##.##oooo##.##",
+            "##.##oooo##.##",
+            "##.########.##",
+            "##.########.##",
+            "##..........##",
+            "##############",
+            "##############",
+        ],
+    );
+    // too thin to split: drawn solid
+    golden(
+        "double",
+        2,
+        (1, 1),
+        &["#####", "#####", "##o##", "#####", "#####"],
+    );
+}
+
+#[test]
+fn golden_dashed() {
+    golden(
+        "dashed",
+        2,
+        (16, 2),
+        &[
+            "####..####..####..##",
+            "####..####..####..##",
+            "##oooooooooooooooo##",
+            "##oooooooooooooooo##",
+            "####..####..####..##",
+            "####..####..####..##",
+        ],
+    );
+}
+
+#[test]
+fn golden_dotted() {
+    golden(
+        "dotted",
+        4,
+        (12, 4),
+        &[
+            ".##......##......##.",
+            "####....####....####",
+            "####....####....####",
+            ".##......##......##.",
+            "....oooooooooooo....",
+            "....oooooooooooo....",
+            "....oooooooooooo....",
+            "....oooooooooooo....",
+            ".##......##......##.",
+            "####....####....####",
+            "####....####....####",
+            ".##......##......##.",
+        ],
+    );
+}
+
+#[test]
+fn golden_inset_outset() {
+    golden(
+        "inset",
+        3,
+        (2, 2),
+        &[
+            "dddddddd", "dddddddl", "ddddddll", "dddoolll", "dddoolll", "ddllllll", "dlllllll",
+            "llllllll",
+        ],
+    );
+    golden(
+        "outset",
+        3,
+        (2, 2),
+        &[
+            "llllllll", "llllllld", "lllllldd", "lllooddd", "lllooddd", "lldddddd", "lddddddd",
+            "dddddddd",
+        ],
+    );
+}
+
+#[test]
+fn golden_groove_ridge() {
+    golden(
+        "groove",
+        4,
+        (2, 2),
+        &[
+            "dddddddddd",
+            "dddddddddl",
+            "ddllllllll",
+            "ddllllldll",
+            "ddllooddll",
+            "ddllooddll",
+            "ddldddddll",
+            "ddddddddll",
+            "dlllllllll",
+            "llllllllll",
+        ],
+    );
+    golden(
+        "ridge",
+        4,
+        (2, 2),
+        &[
+            "llllllllll",
+            "llllllllld",
+            "lldddddddd",
+            "lldddddldd",
+            "llddoolldd",
+            "llddoolldd",
+            "lldllllldd",
+            "lllllllldd",
+            "lddddddddd",
+            "dddddddddd",
+        ],
+    );
+}
+
+#[test]
+fn unknown_style_is_solid_with_a_warning() {
+    let img = Image::synthetic(6, 6);
+    let mut plugin = Plugin::load("borders").config("style", "wavy");
+    assert_eq!(bordered(&mut plugin, &img), reference(&img, 8, GRAY));
+    assert_eq!(plugin.logs().iter().filter(|(l, _)| *l == WARN).count(), 1);
+}
+
+#[test]
+fn every_style_respects_max_dim() {
+    for style in [
+        "solid", "double", "dashed", "dotted", "groove", "ridge", "inset", "outset",
+    ] {
+        let mut plugin = Plugin::load("borders").config("style", style);
+        let fits = plugin.on_capture(&Image::filled(1, 16368, GRAY), Mode::Region);
+        assert_eq!(fits.image().height, 16384, "{style}");
+        let over = plugin.on_capture(&Image::filled(1, 16369, GRAY), Mode::Region);
+        assert_eq!(over, Reply::Unchanged, "{style}");
+    }
+}
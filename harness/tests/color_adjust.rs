@@ -0,0 +1,203 @@
+//! color-adjust: each per-channel control against reference values, the
+//! saturation / hue matrix, greys and alpha kept, auto_levels stretching
+//! dim captures and leaving full-range ones alone, and config handling.
+
+use capscr_plugin_harness::{Image, Plugin, Reply};
+use capscr_plugin_sdk::image::Mode;
+use capscr_plugin_sdk::log::WARN;
+
+/// one pixel per value, as grey.
+fn greys(values: &[u8]) -> Image {
+    let rgba: Vec<u8> = values.iter().flat_map(|&v| [v, v, v, 255]).collect();
+    Image::new(values.len() as u32, 1, rgba)
+}
+
+fn adjust(plugin: &mut Plugin, img: &Image) -> Image {
+    match plugin.on_capture(img, Mode::Region) {
+        Reply::InPlace(out) => out,
+        other => panic!("expected an in-place reply, got {other:?}"),
+    }
+}
+
+/// the red channel of each pixel.
+fn reds(img: &Image) -> Vec<u8> {
+    img.rgba.chunks_exact(4).map(|px| px[0]).collect()
+}
+
+#[test]
+fn defaults_leave_the_capture_alone() {
+    let img = Image::synthetic(40, 30);
+    let mut plugin = Plugin::load("color-adjust");
+    assert_eq!(plugin.on_capture(&img, Mode::Region), Reply::Unchanged);
+    // explicitly neutral is the same
+    let mut plugin = Plugin::load("color-adjust")
+        .config("gamma", "1.0")
+        .config("saturation", "1")
+        .config("white_point", "255");
+    assert_eq!(plugin.on_capture(&img, Mode::Region), Reply::Unchanged);
+}
+
+#[test]
+fn per_channel_controls_match_reference_values() {
+    for (key, value, input, want) in [
+        (
+            "gamma",
+            "2.2",
+            &[0, 64, 128, 200, 255][..],
+            &[0, 136, 186, 228, 255][..],
+        ),
+        ("brightness", "0.2", &[0, 100, 250], &[51, 151, 255]),
+        ("brightness", "-0.2", &[0, 100, 255], &[0, 49, 204]),
+        ("contrast", "1.5", &[0, 100, 160, 255], &[0, 86, 176, 255]),
+        ("contrast", "0", &[0, 100, 255], &[128, 128, 128]),
+    ] {
+        let mut plugin = Plugin::load("color-adjust").config(key, value);
+        let out = adjust(&mut plugin, &greys(input));
+        assert_eq!(reds(&out), want, "{key} = {value}");
+        assert!(plugin.logs().iter().all(|(l, _)| *l != WARN));
+    }
+}
+
+#[test]
+fn levels_stretch_between_the_points() {
+    let mut plugin = Plugin::load("color-adjust")
+        .config("black_point", "50")
+        .config("white_point", "200");
+    let out = adjust(&mut plugin, &greys(&[20, 50, 125, 199, 230]));
+    assert_eq!(reds(&out), [0, 0, 128, 253, 255]);
+}
+
+#[test]
+fn saturation_and_hue_mix_the_channels() {
+    let img = Image::new(
+        3,
+        1,
+        [[255, 0, 0, 255], [30, 90, 200, 77], [128, 128, 128, 255]].concat(),
+    );
+    for (saturation, hue, want) in [
+        ("0", "0", [[54, 54, 54], [85, 85, 85]]),
+        ("2", "0", [[255, 0, 0], [0, 95, 255]]),
+        ("1", "180", [[0, 109, 109], [140, 80, 0]]),
+        ("1", "90", [[0, 91, 0], [200, 45, 140]]),
+        ("0.5", "-60", [[128, 25, 128], [22, 104, 90]]),
+    ] {
+        let mut plugin = Plugin::load("color-adjust")
+            .config("saturation", saturation)
+            .config("hue", hue);
+        let out = adjust(&mut plugin, &img);
+        let label = format!("saturation {saturation}, hue {hue}");
+        assert_eq!(out.pixel(0, 0)[..3], want[0], "{label}");
+        assert_eq!(out.pixel(1, 0)[..3], want[1], "{label}");
+        assert_eq!(out.pixel(2, 0), [128, 128, 128, 255], "{label}: grey");
+        assert_eq!(out.pixel(1, 0)[3], 77, "{label}: alpha");
+    }
+}
+
+#[test]
+fn alpha_is_never_touched() {
+    let img = Image::synthetic(61, 40);
+    let mut plugin = Plugin::load("color-adjust")
+        .config("gamma", "0.5")
+        .config("contrast", "3")
+        .config("hue", "45");
+    let out = adjust(&mut plugin, &img);
+    for (a, b) in img.rgba.chunks_exact(4).zip(out.rgba.chunks_exact(4)) {
+        assert_eq!(a[3], b[3]);
+    }
+}
+
+#[test]
+fn auto_levels_stretches_dim_captures() {
+    // a remote-desktop-style capture squeezed into 40..=180, ten rows of it
+    let ramp: Vec<u8> = (40..=180).collect();
+    let mut img = greys(&ramp.repeat(10));
+    // plus a few stray pixels (a cursor) that auto_clip lets through
+    for x in 0..5 {
+        img.rgba[x * 4..][..3].copy_from_slice(&[255, 255, 255]);
+        img.rgba[(100 + x) * 4..][..3].copy_from_slice(&[0, 0, 0]);
+    }
+    let mut plugin = Plugin::load("color-adjust").config("auto_levels", "true");
+    let out = adjust(&mut plugin, &img);
+    // the second copy of the ramp, clear of the strays, spans the full range
+    let ramp = &reds(&out)[141..282];
+    assert_eq!((ramp[0], ramp[140]), (0, 255));
+    assert_eq!(ramp[70], 128);
+    assert!(plugin.logs().iter().any(|(_, m)| m.contains("40..=180")));
+    assert!(plugin.logs().iter().all(|(l, _)| *l != WARN));
+}
+
+#[test]
+fn auto_levels_leaves_full_range_and_flat_captures_alone() {
+    // black text on white, with some antialiasing in between
+    let mut values = vec![0; 40];
+    values.extend(0..=255);
+    values.extend([255; 200]);
+    let full = greys(&values);
+    let mut plugin = Plugin::load("color-adjust").config("auto_levels", "true");
+    assert_eq!(adjust(&mut plugin, &full), full);
+    assert!(plugin.logs().iter().any(|(_, m)| m.contains("full range")));
+
+    // 20 levels of faint texture: stretching would only amplify it
+    let flat = greys(&(100..120).collect::<Vec<u8>>());
+    let mut plugin = Plugin::load("color-adjust").config("auto_levels", "true");
+    assert_eq!(adjust(&mut plugin, &flat), flat);
+}
+
+#[test]
+fn auto_levels_combines_with_the_other_controls() {
+    let dim = greys(&(0..=180).collect::<Vec<u8>>());
+    let mut plugin = Plugin::load("color-adjust")
+        .config("auto_levels", "true")
+        .config("auto_clip", "0")
+        // ignored: auto_levels picks the points
+        .config("white_point", "250")
+        .config("saturation", "0");
+    let out = adjust(&mut plugin, &dim);
+    assert_eq!(out.pixel(0, 0), [0, 0, 0, 255]);
+    assert_eq!(out.pixel(180, 0), [255, 255, 255, 255]);
+}
+
+#[test]
+fn invalid_settings_warn_and_fall_back() {
+    let img = greys(&[0, 100, 200]);
+    for (key, value) in [
+        ("gamma", "0"),
+        ("brightness", "2"),
+        ("contrast", "-1"),
+        ("saturation", "lots"),
+        ("hue", "270"),
+        ("black_point", "255"),
+        ("auto_clip", "50"),
+    ] {
+        let mut plugin = Plugin::load("color-adjust").config(key, value);
+        assert_eq!(
+            plugin.on_capture(&img, Mode::Region),
+            Reply::Unchanged,
+            "{key} = {value}"
+        );
+        let warned = plugin.logs().iter().filter(|(l, _)| *l == WARN).count();
+        assert_eq!(warned, 1, "{key} = {value}");
+    }
+
+    // a white point at or below the black point keeps the black point only
+    let mut plugin = Plugin::load("color-adjust")
+        .config("black_point", "100")
+        .config("white_point", "100");
+    let out = adjust(&mut plugin, &img);
+    assert_eq!(reds(&out), [0, 0, 165]);
+    let warned = plugin.logs().iter().filter(|(l, _)| *l == WARN).count();
+    assert_eq!(warned, 1);
+}
+
+#[test]
+fn only_modes_skips_other_captures() {
+    let img = Image::synthetic(40, 30);
+    let mut plugin = Plugin::load("color-adjust")
+        .config("contrast", "2")
+        .config("only_modes", "[\"window\"]");
+    assert_eq!(plugin.on_capture(&img, Mode::Region), Reply::Unchanged);
+    assert!(matches!(
+        plugin.on_capture(&img, Mode::Window),
+        Reply::InPlace(_)
+    ));
+}
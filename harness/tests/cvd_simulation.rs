@@ -0,0 +1,174 @@
+//! cvd-simulation: reference colours for each deficiency, neutrals kept,
+//! severity 0 as the identity, the side-by-side layout and its width limit,
+//! and config handling.
+
+use capscr_plugin_harness::{Image, Plugin, Reply};
+use capscr_plugin_sdk::image::Mode;
+use capscr_plugin_sdk::log::WARN;
+
+const SAMPLES: [[u8; 4]; 4] = [
+    [255, 0, 0, 255],
+    [0, 128, 0, 200],
+    [30, 90, 200, 255],
+    [255, 255, 0, 0],
+];
+
+fn samples() -> Image {
+    Image::new(4, 1, SAMPLES.concat())
+}
+
+fn simulate(plugin: &mut Plugin, img: &Image) -> Image {
+    match plugin.on_capture(img, Mode::Region) {
+        Reply::Replaced(out) => out,
+        other => panic!("expected a replacement, got {other:?}"),
+    }
+}
+
+#[test]
+fn matches_reference_colors() {
+    // computed independently from the published matrices, in linear light
+    for (deficiency, severity, want) in [
+        (
+            "protanopia",
+            "1",
+            [[109, 95, 0], [131, 114, 0], [0, 104, 204], [255, 244, 0]],
+        ),
+        (
+            "deuteranopia",
+            "1",
+            [[163, 144, 0], [119, 106, 24], [0, 89, 198], [255, 250, 49]],
+        ),
+        (
+            "deuteranopia",
+            "0.5",
+            [[195, 118, 0], [101, 114, 18], [0, 90, 199], [255, 251, 35]],
+        ),
+        (
+            "tritanopia",
+            "1",
+            [[255, 0, 15], [0, 124, 108], [0, 117, 136], [255, 238, 217]],
+        ),
+    ] {
+        let mut plugin = Plugin::load("cvd-simulation")
+            .config("deficiency", deficiency)
+            .config("severity", severity);
+        let out = simulate(&mut plugin, &samples());
+        for (x, rgb) in want.iter().enumerate() {
+            let px = out.pixel(x as u32, 0);
+            assert_eq!(px[..3], rgb[..], "{deficiency} {severity} #{x}");
+            assert_eq!(px[3], SAMPLES[x][3], "alpha is kept");
+        }
+        assert!(plugin.logs().iter().all(|(l, _)| *l != WARN));
+    }
+}
+
+#[test]
+fn neutrals_are_unchanged() {
+    let greys = Image::new(
+        4,
+        1,
+        [
+            [0, 0, 0, 255],
+            [128, 128, 128, 255],
+            [200, 200, 200, 9],
+            [255; 4],
+        ]
+        .concat(),
+    );
+    for deficiency in ["protanopia", "deuteranopia", "tritanopia", "achromatopsia"] {
+        let mut plugin = Plugin::load("cvd-simulation").config("deficiency", deficiency);
+        assert_eq!(simulate(&mut plugin, &greys), greys, "{deficiency}");
+    }
+}
+
+#[test]
+fn severity_zero_is_the_identity() {
+    let img = Image::synthetic(61, 40);
+    for deficiency in ["protanopia", "deuteranopia", "tritanopia", "achromatopsia"] {
+        let mut plugin = Plugin::load("cvd-simulation")
+            .config("deficiency", deficiency)
+            .config("severity", "0");
+        assert_eq!(simulate(&mut plugin, &img), img, "{deficiency}");
+    }
+}
+
+#[test]
+fn achromatopsia_keeps_only_luminance() {
+    let img = Image::synthetic(61, 40);
+    let mut plugin = Plugin::load("cvd-simulation").config("deficiency", "achromatopsia");
+    let out = simulate(&mut plugin, &img);
+    for (a, b) in img.rgba.chunks_exact(4).zip(out.rgba.chunks_exact(4)) {
+        assert!(b[0] == b[1] && b[1] == b[2], "{a:?} -> {b:?}");
+        assert_eq!(a[3], b[3]);
+    }
+    // same grey as grayscale's luminance-based mode
+    let mut grey = Plugin::load("grayscale").config("mode", "lightness");
+    assert_eq!(out, grey.on_capture(&img, Mode::Region).image());
+
+    // half severity: halfway there, in linear light
+    let mut plugin = Plugin::load("cvd-simulation")
+        .config("deficiency", "achromatopsia")
+        .config("severity", "0.5");
+    let out = simulate(&mut plugin, &Image::filled(1, 1, [255, 0, 0, 255]));
+    assert_eq!(out.pixel(0, 0), [204, 92, 92, 255]);
+}
+
+#[test]
+fn side_by_side_puts_the_original_on_the_left() {
+    let img = Image::synthetic(50, 30);
+    let simulated = simulate(&mut Plugin::load("cvd-simulation"), &img);
+    let mut plugin = Plugin::load("cvd-simulation").config("side_by_side", "true");
+    let out = simulate(&mut plugin, &img);
+    assert_eq!((out.width, out.height), (100, 30));
+    for y in 0..30 {
+        for x in 0..50 {
+            assert_eq!(out.pixel(x, y), img.pixel(x, y), "({x},{y})");
+            assert_eq!(out.pixel(50 + x, y), simulated.pixel(x, y), "({x},{y})");
+        }
+    }
+}
+
+#[test]
+fn side_by_side_too_wide_warns_and_simulates_alone() {
+    let img = Image::synthetic(8193, 2);
+    let mut plugin = Plugin::load("cvd-simulation").config("side_by_side", "true");
+    let out = simulate(&mut plugin, &img);
+    assert_eq!((out.width, out.height), (8193, 2));
+    assert_eq!(out, simulate(&mut Plugin::load("cvd-simulation"), &img));
+    let warned = plugin.logs().iter().filter(|(l, _)| *l == WARN).count();
+    assert_eq!(warned, 1);
+
+    // exactly at the limit still doubles
+    let img = Image::synthetic(8192, 1);
+    let mut plugin = Plugin::load("cvd-simulation").config("side_by_side", "true");
+    assert_eq!(simulate(&mut plugin, &img).width, 16384);
+}
+
+#[test]
+fn invalid_settings_warn_and_fall_back() {
+    let img = Image::synthetic(40, 30);
+    let default = simulate(&mut Plugin::load("cvd-simulation"), &img);
+    for (key, value) in [
+        ("deficiency", "colorblind"),
+        ("severity", "1.5"),
+        ("severity", "-0.1"),
+        ("severity", "full"),
+        ("side_by_side", "maybe"),
+    ] {
+        let mut plugin = Plugin::load("cvd-simulation").config(key, value);
+        assert_eq!(simulate(&mut plugin, &img), default, "{key} = {value}");
+        let warned = plugin.logs().iter().filter(|(l, _)| *l == WARN).count();
+        assert_eq!(warned, 1, "{key} = {value}");
+    }
+}
+
+#[test]
+fn only_modes_skips_other_captures() {
+    let img = Image::synthetic(40, 30);
+    let mut plugin = Plugin::load("cvd-simulation").config("only_modes", "[\"window\"]");
+    assert_eq!(plugin.on_capture(&img, Mode::Region), Reply::Unchanged);
+    assert!(matches!(
+        plugin.on_capture(&img, Mode::Window),
+        Reply::Replaced(_)
+    ));
+}
@@ -0,0 +1,343 @@
+//! downscale: the integer box default, and the exact-fit `filter` kernels —
+//! output size, flat colour and linear ramps preserved, no aliasing of
+//! pixel-level detail, and bad config falling back to the default. Plus
+//! `linear_light` goldens: thin dark strokes and transparent edges, and the
+//! width / height / megapixel / per-mode limits.
+
+use capscr_plugin_harness::{Image, Plugin, Reply};
+use capscr_plugin_sdk::image::Mode;
+use capscr_plugin_sdk::log::WARN;
+
+const FILTERS: &[&str] = &["box", "bilinear", "mitchell", "lanczos3"];
+
+const RED: [u8; 4] = [220, 40, 40, 255];
+const SHADOW: [u8; 4] = [0, 0, 0, 128];
+const CLEAR: [u8; 4] = [0; 4];
+
+fn downscale(img: &Image, max_dim: &str, filter: Option<&str>) -> Image {
+    let mut plugin = Plugin::load("downscale").config("max_dimension", max_dim);
+    if let Some(f) = filter {
+        plugin = plugin.config("filter", f);
+    }
+    plugin.on_capture(img, Mode::FullScreen).image()
+}
+
+fn linear(img: &Image, max_dim: &str, filter: Option<&str>) -> Image {
+    let mut plugin = Plugin::load("downscale")
+        .config("max_dimension", max_dim)
+        .config("linear_light", "true");
+    if let Some(f) = filter {
+        plugin = plugin.config("filter", f);
+    }
+    plugin.on_capture(img, Mode::FullScreen).image()
+}
+
+/// the output size for a `w`×`h` capture in `mode` under `config`, or None
+/// if it's left alone.
+fn size(w: u32, h: u32, mode: Mode, config: &[(&str, &str)]) -> Option<(u32, u32)> {
+    let mut plugin = config
+        .iter()
+        .fold(Plugin::load("downscale"), |p, (k, v)| p.config(k, v));
+    match plugin.on_capture(&Image::filled(w, h, [9, 9, 9, 255]), mode) {
+        Reply::Unchanged => None,
+        Reply::Replaced(out) => Some((out.width, out.height)),
+        other => panic!("{other:?}"),
+    }
+}
+
+/// an 8×8 "A": one-px black strokes on white, like small UI text.
+fn glyph() -> Image {
+    let rows = [
+        "...##...", "..#..#..", ".#....#.", ".#....#.", ".######.", ".#....#.", ".#....#.",
+        "........",
+    ];
+    let rgba = rows
+        .iter()
+        .flat_map(|r| r.bytes())
+        .flat_map(|b| if b == b'#' { [0, 0, 0, 255] } else { [255; 4] })
+        .collect();
+    Image::new(8, 8, rgba)
+}
+
+/// the grey level of each pixel of a 4×4 result.
+fn greys(img: &Image) -> Vec<u8> {
+    assert_eq!((img.width, img.height), (4, 4));
+    img.rgba
+        .chunks_exact(4)
+        .map(|px| {
+            assert_eq!((px[0], px[1], px[3]), (px[2], px[2], 255));
+            px[0]
+        })
+        .collect()
+}
+
+/// a window edge: opaque red, then a soft shadow, then nothing.
+fn window_edge() -> Image {
+    let row = [RED, RED, RED, CLEAR, SHADOW, CLEAR];
+    Image::new(6, 2, [row, row].concat().concat())
+}
+
+#[test]
+fn filters_fit_the_longest_side_exactly() {
+    for (w, h, fit) in [(1921, 1080, (1920, 1079)), (1080, 1921, (1079, 1920))] {
+        let img = Image::synthetic(w, h);
+        // the integer path halves a capture one px over the limit
+        let out = downscale(&img, "1920", None);
+        assert_eq!((out.width, out.height), (w.div_ceil(2), h.div_ceil(2)));
+        for &f in FILTERS {
+            let out = downscale(&img, "1920", Some(f));
+            assert_eq!((out.width, out.height), fit, "{f} {w}x{h}");
+        }
+    }
+    // the short side never rounds away to nothing
+    let out = downscale(&Image::synthetic(5000, 1), "100", Some("lanczos3"));
+    assert_eq!((out.width, out.height), (100, 1));
+}
+
+#[test]
+fn flat_colour_stays_flat_through_every_filter() {
+    let c = [10, 200, 30, 128];
+    let img = Image::filled(333, 101, c);
+    for &f in FILTERS {
+        let out = downscale(&img, "100", Some(f));
+        assert!(
+            out.rgba.chunks_exact(4).all(|px| px == c),
+            "{f} changed a flat capture"
+        );
+    }
+}
+
+#[test]
+fn box_at_an_integer_factor_matches_the_integer_path() {
+    let img = Image::synthetic(300, 210);
+    let fast = downscale(&img, "100", None);
+    let exact = downscale(&img, "100", Some("box"));
+    assert_eq!((exact.width, exact.height), (fast.width, fast.height));
+    // same blocks; the integer path truncates where the filter rounds
+    for (a, b) in fast.rgba.iter().zip(&exact.rgba) {
+        assert!(a.abs_diff(*b) <= 1, "{a} vs {b}");
+    }
+}
+
+#[test]
+fn linear_ramps_survive_every_filter() {
+    // 0..=255 across 256 px; every symmetric kernel reproduces a straight
+    // line away from the edges
+    let ramp: Vec<u8> = (0..64u32)
+        .flat_map(|_| (0..=255u8).flat_map(|v| [v, v, v, 255]))
+        .collect();
+    let img = Image::new(256, 64, ramp);
+    for &f in FILTERS {
+        let out = downscale(&img, "100", Some(f));
+        assert_eq!(out.width, 100);
+        for x in 10..90 {
+            let want = (x as f32 + 0.5) * 2.56 - 0.5;
+            let got = out.pixel(x, 20)[0] as f32;
+            assert!((got - want).abs() <= 1.0, "{f} x={x}: {got} vs {want}");
+        }
+    }
+}
+
+#[test]
+fn pixel_checkerboards_average_to_grey() {
+    let board: Vec<u8> = (0..300u32)
+        .flat_map(|y| (0..400u32).map(move |x| if (x + y) % 2 == 0 { 255 } else { 0 }))
+        .flat_map(|v| [v, v, v, 255])
+        .collect();
+    let img = Image::new(400, 300, board);
+    // 2× and a fractional 2.5×: no moiré from the kernels that stretch
+    for max_dim in ["200", "160"] {
+        for &f in FILTERS {
+            let out = downscale(&img, max_dim, Some(f));
+            for y in 5..out.height - 5 {
+                for x in 5..out.width - 5 {
+                    let v = out.pixel(x, y)[0];
+                    assert!(v.abs_diff(128) <= 8, "{f} @{max_dim} ({x},{y}) = {v}");
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn unknown_filter_warns_and_uses_the_integer_path() {
+    let img = Image::synthetic(1921, 1080);
+    let mut plugin = Plugin::load("downscale").config("filter", "sinc");
+    let out = plugin.on_capture(&img, Mode::FullScreen).image();
+    assert_eq!((out.width, out.height), (961, 540));
+    let warnings: Vec<_> = plugin.logs().iter().filter(|(l, _)| *l == WARN).collect();
+    assert_eq!(warnings.len(), 1, "{warnings:?}");
+    assert!(warnings[0].1.contains("filter"), "{warnings:?}");
+}
+
+#[test]
+fn thin_strokes_keep_their_weight_in_linear_light() {
+    // 2×2 blocks holding 0, 1 or 2 black px
+    #[rustfmt::skip]
+    const SRGB: [u8; 16] = [
+        255, 127, 127, 255,
+        127, 255, 255, 127,
+        127, 127, 127, 127,
+        191, 255, 255, 191,
+    ];
+    // half-covered averages to half the light, sRGB 188 — not 127
+    #[rustfmt::skip]
+    const LINEAR: [u8; 16] = [
+        255, 188, 188, 255,
+        188, 255, 255, 188,
+        188, 188, 188, 188,
+        225, 255, 255, 225,
+    ];
+    assert_eq!(greys(&downscale(&glyph(), "4", None)), SRGB);
+    assert_eq!(greys(&linear(&glyph(), "4", None)), LINEAR);
+    assert_eq!(greys(&linear(&glyph(), "4", Some("box"))), LINEAR);
+}
+
+#[test]
+fn transparent_edges_get_no_dark_fringe_in_linear_light() {
+    let img = window_edge();
+    // straight-alpha averaging pulls the window's colour towards the
+    // transparent px's black
+    let srgb = downscale(&img, "3", None);
+    assert_eq!(srgb.rgba, [RED, [110, 20, 20, 127], [0, 0, 0, 64]].concat());
+    let golden = [RED, [220, 40, 40, 128], [0, 0, 0, 64]].concat();
+    assert_eq!(linear(&img, "3", None).rgba, golden);
+    assert_eq!(linear(&img, "3", Some("box")).rgba, golden);
+}
+
+#[test]
+fn every_filter_keeps_edge_colour_in_linear_light() {
+    // red on the left, transparent on the right, at a fractional scale
+    let rgba = (0..10)
+        .flat_map(|_| (0..40).map(|x| if x < 17 { RED } else { CLEAR }))
+        .flatten()
+        .collect();
+    let img = Image::new(40, 10, rgba);
+    for &f in FILTERS {
+        let out = linear(&img, "15", Some(f));
+        for px in out.rgba.chunks_exact(4).filter(|px| px[3] > 0) {
+            assert_eq!(px[..3], RED[..3], "{f}: {px:?}");
+        }
+        // the soft edge is there, not just opaque and clear
+        assert!(out.rgba.chunks_exact(4).any(|px| (1..255).contains(&px[3])));
+    }
+}
+
+#[test]
+fn linear_light_leaves_flat_colour_and_bounds_alone() {
+    let img = Image::filled(333, 101, [10, 200, 30, 128]);
+    for f in [None, Some("box"), Some("lanczos3")] {
+        let out = linear(&img, "100", f);
+        assert!(
+            out.rgba.chunks_exact(4).all(|px| px == [10, 200, 30, 128]),
+            "{f:?}"
+        );
+    }
+}
+
+#[test]
+fn width_and_height_caps_apply_on_their_own_axis() {
+    let fs = Mode::FullScreen;
+    // a width cap replaces the 1920 default rather than adding to it
+    assert_eq!(size(2500, 2500, fs, &[("max_width", "2560")]), None);
+    assert_eq!(
+        size(3000, 1000, fs, &[("max_width", "2560")]),
+        Some((1500, 500))
+    );
+    let exact = [("max_width", "2560"), ("filter", "box")];
+    assert_eq!(size(3000, 1000, fs, &exact), Some((2560, 853)));
+    let exact = [("max_height", "500"), ("filter", "box")];
+    assert_eq!(size(1200, 1000, fs, &exact), Some((600, 500)));
+    // 0 is no limit at all
+    assert_eq!(size(3000, 3000, fs, &[("max_dimension", "0")]), None);
+}
+
+#[test]
+fn megapixel_cap_keeps_the_aspect_ratio() {
+    let fs = Mode::FullScreen;
+    let config = [("max_megapixels", "8"), ("filter", "lanczos3")];
+    let (w, h) = size(3840, 2160, fs, &config).unwrap();
+    assert!(w * h <= 8_000_000, "{w}x{h}");
+    assert!(w * h > 7_990_000, "{w}x{h} undershoots");
+    assert!((w as f64 / h as f64 - 16.0 / 9.0).abs() < 1e-3, "{w}x{h}");
+    // 8.3 MP: the integer path halves it
+    let config = [("max_megapixels", "8")];
+    assert_eq!(size(3840, 2160, fs, &config), Some((1920, 1080)));
+    assert_eq!(size(3840, 2080, fs, &config), None);
+}
+
+#[test]
+fn the_tightest_limit_wins() {
+    let fs = Mode::FullScreen;
+    let config = [
+        ("max_dimension", "1920"),
+        ("max_height", "800"),
+        ("max_megapixels", "2"),
+        ("filter", "box"),
+    ];
+    assert_eq!(size(3840, 2160, fs, &config), Some((1422, 800)));
+    assert_eq!(size(3000, 600, fs, &config), Some((1920, 384)));
+    // square: 2 MP is tighter than both
+    assert_eq!(size(2000, 2000, fs, &config), Some((800, 800)));
+    // the integer path takes the smallest factor that satisfies both
+    let config = [("max_dimension", "1920"), ("max_height", "700")];
+    assert_eq!(size(3840, 2160, fs, &config), Some((960, 540)));
+}
+
+#[test]
+fn limits_can_be_overridden_per_mode() {
+    let config = [
+        ("max_dimension", "1920"),
+        ("gif_max_dimension", "0"),
+        ("window_max_width", "1000"),
+        ("region_max_megapixels", "1"),
+        ("filter", "box"),
+    ];
+    assert_eq!(size(2400, 1800, Mode::Gif, &config), None);
+    assert_eq!(
+        size(2400, 1800, Mode::FullScreen, &config),
+        Some((1920, 1440))
+    );
+    // the global max_dimension still applies next to a mode's own limit
+    assert_eq!(size(2400, 1800, Mode::Window, &config), Some((1000, 750)));
+    assert_eq!(size(1500, 700, Mode::Window, &config), Some((1000, 467)));
+    // 1155×866 would round over 1 MP
+    assert_eq!(size(1600, 1200, Mode::Region, &config), Some((1154, 866)));
+    // with no global key at all, a mode override sits on the 1920 default
+    let config = [("gif_max_dimension", "480")];
+    assert_eq!(size(1000, 500, Mode::Gif, &config), Some((334, 167)));
+    assert_eq!(size(3840, 2160, Mode::Window, &config), Some((1920, 1080)));
+}
+
+#[test]
+fn a_mode_can_opt_out_of_every_limit() {
+    let config = [
+        ("max_dimension", "1920"),
+        ("max_width", "1000"),
+        ("gif_max_dimension", "0"),
+        ("filter", "box"),
+    ];
+    // a zeroed limit lifts only itself: max_width still applies
+    assert_eq!(size(2400, 1800, Mode::Gif, &config), Some((1000, 750)));
+    let off = [config.as_slice(), &[("gif_enabled", "false")]].concat();
+    assert_eq!(size(2400, 1800, Mode::Gif, &off), None);
+    assert_eq!(size(2400, 1800, Mode::Window, &off), Some((1000, 750)));
+    // with no limit keys at all, too: not even the 1920 default
+    assert_eq!(
+        size(3840, 2160, Mode::Gif, &[("gif_enabled", "false")]),
+        None
+    );
+}
+
+#[test]
+fn invalid_limits_warn_and_are_ignored() {
+    let mut plugin = Plugin::load("downscale")
+        .config("max_width", "-5")
+        .config("max_megapixels", "lots")
+        .config("gif_max_height", "1.5");
+    let img = Image::filled(4000, 100, [9, 9, 9, 255]);
+    // set but unusable: no limits left, not the 1920 default
+    assert_eq!(plugin.on_capture(&img, Mode::Gif), Reply::Unchanged);
+    let warnings = plugin.logs().iter().filter(|(l, _)| *l == WARN).count();
+    assert_eq!(warnings, 3, "{:?}", plugin.logs());
+}
@@ -0,0 +1,229 @@
+//! drop-shadow: canvas growth, the capture left intact on top, and the shadow
+//! matching a direct 2-D Gaussian on both the opaque fast path and the
+//! general alpha-plane path.
+
+use capscr_plugin_harness::{Image, Plugin, Reply};
+use capscr_plugin_sdk::blur::kernel;
+use capscr_plugin_sdk::image::Mode;
+use capscr_plugin_sdk::log::WARN;
+
+/// the synthetic pattern with every pixel made opaque.
+fn opaque(w: u32, h: u32) -> Image {
+    let mut img = Image::synthetic(w, h);
+    img.rgba.chunks_exact_mut(4).for_each(|px| px[3] = 255);
+    img
+}
+
+fn shadowed(plugin: &mut Plugin, img: &Image) -> Image {
+    match plugin.on_capture(img, Mode::Region) {
+        Reply::Replaced(out) => out,
+        other => panic!("expected a replacement, got {other:?}"),
+    }
+}
+
+/// the expected shadow alpha at every canvas pixel: the capture's alpha
+/// placed at (x0, y0), convolved with the full 2-D Gaussian.
+fn reference_alpha(
+    img: &Image,
+    (nw, nh): (u32, u32),
+    (x0, y0): (i64, i64),
+    blur: u32,
+    strength: f64,
+) -> Vec<u8> {
+    let k = kernel(blur as f32 / 2.0);
+    let r = (k.len() / 2) as i64;
+    let alpha = |x: i64, y: i64| -> f64 {
+        let (cx, cy) = (x - x0, y - y0);
+        if (0..img.width as i64).contains(&cx) && (0..img.height as i64).contains(&cy) {
+            img.pixel(cx as u32, cy as u32)[3] as f64 / 255.0
+        } else {
+            0.0
+        }
+    };
+    let mut out = Vec::new();
+    for y in 0..nh as i64 {
+        for x in 0..nw as i64 {
+            let mut acc = 0.0;
+            for (j, ky) in k.iter().enumerate() {
+                for (i, kx) in k.iter().enumerate() {
+                    acc += (*ky as f64) * (*kx as f64) * alpha(x + i as i64 - r, y + j as i64 - r);
+                }
+            }
+            out.push((acc * strength * 255.0 + 0.5) as u8);
+        }
+    }
+    out
+}
+
+fn assert_shadow(out: &Image, want: &[u8], capture: (u32, u32, u32, u32)) {
+    let (left, top, w, h) = capture;
+    for y in 0..out.height {
+        for x in 0..out.width {
+            if (left..left + w).contains(&x) && (top..top + h).contains(&y) {
+                continue;
+            }
+            let (got, want) = (out.pixel(x, y)[3], want[(y * out.width + x) as usize]);
+            assert!(
+                got.abs_diff(want) <= 1,
+                "shadow alpha at ({x},{y}): {got} vs {want}"
+            );
+        }
+    }
+}
+
+#[test]
+fn default_canvas_and_capture_position() {
+    // blur 16 → σ 8 → 24 px reach; offset (0, 8)
+    let img = opaque(50, 30);
+    let out = shadowed(&mut Plugin::load("drop-shadow"), &img);
+    assert_eq!((out.width, out.height), (50 + 48, 30 + 16 + 32));
+    for y in 0..30 {
+        for x in 0..50 {
+            assert_eq!(
+                out.pixel(x + 24, y + 16),
+                img.pixel(x, y),
+                "capture pixel ({x},{y})"
+            );
+        }
+    }
+    // the canvas corners are fully transparent; the shadow is black
+    assert_eq!(out.pixel(0, 0), [0, 0, 0, 0]);
+    assert_eq!(out.pixel(0, out.height - 1)[3], 0);
+}
+
+#[test]
+fn opaque_shadow_matches_direct_gaussian() {
+    let img = Image::filled(20, 12, [200, 10, 10, 255]);
+    let mut plugin = Plugin::load("drop-shadow")
+        .config("blur", "6")
+        .config("offset_x", "-4")
+        .config("offset_y", "5")
+        .config("opacity", "0.5")
+        .config("color", "#102030");
+    let out = shadowed(&mut plugin, &img);
+    // reach 9: left 9 + 4, right 9 - 4, top 9 - 5, bottom 9 + 5
+    assert_eq!((out.width, out.height), (13 + 20 + 5, 4 + 12 + 14));
+    let want = reference_alpha(&img, (out.width, out.height), (13 - 4, 4 + 5), 6, 0.5);
+    assert_shadow(&out, &want, (13, 4, 20, 12));
+    assert_eq!(out.pixel(5, 20)[..3], [0x10, 0x20, 0x30]);
+}
+
+#[test]
+fn translucent_capture_blurs_its_alpha() {
+    // a capture with a transparent notch and a half-transparent stripe
+    let mut img = Image::filled(16, 16, [90, 90, 90, 255]);
+    for y in 0..16 {
+        for x in 0..16 {
+            let a = if x < 5 && y < 5 {
+                0
+            } else if y == 10 {
+                128
+            } else {
+                255
+            };
+            img.rgba[(y * 16 + x) * 4 + 3] = a;
+        }
+    }
+    let mut plugin = Plugin::load("drop-shadow")
+        .config("blur", "4")
+        .config("offset_y", "0")
+        .config("opacity", "1");
+    let out = shadowed(&mut plugin, &img);
+    assert_eq!((out.width, out.height), (28, 28));
+    let want = reference_alpha(&img, (28, 28), (6, 6), 4, 1.0);
+    assert_shadow(&out, &want, (6, 6, 16, 16));
+    // the shadow shows through the notch
+    assert_eq!(out.pixel(6, 6)[3].abs_diff(want[6 * 28 + 6]), 0);
+    assert!(out.pixel(6, 6)[3] > 0);
+    // translucent pixels are composited over the (black) shadow: darker and
+    // more opaque than they were
+    let stripe = out.pixel(14, 16);
+    assert!(stripe[0] < 90 && stripe[0] == stripe[1] && stripe[1] == stripe[2]);
+    assert!(stripe[3] > 128);
+}
+
+#[test]
+fn translucent_pixels_across_tiles() {
+    // 256-px tiles: rounded-ish corners at both ends plus a translucent run
+    // straddling the tile boundary
+    let mut img = Image::filled(300, 20, [30, 60, 90, 255]);
+    for (x, y, a) in [
+        (0, 0, 0),
+        (1, 0, 40),
+        (0, 1, 40),
+        (299, 19, 0),
+        (298, 19, 90),
+        (299, 18, 90),
+    ] {
+        img.rgba[(y * 300 + x) * 4 + 3] = a;
+    }
+    for x in 250..262 {
+        img.rgba[(7 * 300 + x) * 4 + 3] = 60;
+    }
+    let mut plugin = Plugin::load("drop-shadow")
+        .config("blur", "4")
+        .config("offset_x", "3")
+        .config("offset_y", "-2")
+        .config("opacity", "1");
+    let out = shadowed(&mut plugin, &img);
+    // reach 6: left 3, right 9, top 8, bottom 4
+    assert_eq!((out.width, out.height), (312, 32));
+    let want = reference_alpha(&img, (312, 32), (6, 6), 4, 1.0);
+    assert_shadow(&out, &want, (3, 8, 300, 20));
+}
+
+#[test]
+fn hard_shadow_without_blur() {
+    let img = Image::filled(4, 4, [255; 4]);
+    let mut plugin = Plugin::load("drop-shadow")
+        .config("blur", "0")
+        .config("offset_x", "2")
+        .config("offset_y", "2");
+    let out = shadowed(&mut plugin, &img);
+    assert_eq!((out.width, out.height), (6, 6));
+    assert_eq!(out.pixel(5, 5), [0, 0, 0, 102]);
+    assert_eq!(out.pixel(5, 1), [0, 0, 0, 0]);
+    assert_eq!(out.pixel(1, 1), [255; 4]);
+}
+
+#[test]
+fn padding_adds_a_margin() {
+    let img = Image::filled(10, 10, [255; 4]);
+    let base = shadowed(&mut Plugin::load("drop-shadow"), &img);
+    let padded = shadowed(
+        &mut Plugin::load("drop-shadow").config("padding", "7"),
+        &img,
+    );
+    assert_eq!(
+        (padded.width, padded.height),
+        (base.width + 14, base.height + 14)
+    );
+    assert_eq!(padded.pixel(7 + 24, 7 + 16), [255; 4]);
+}
+
+#[test]
+fn invalid_values_fall_back_with_a_warning() {
+    let img = Image::synthetic(10, 10);
+    let want = shadowed(&mut Plugin::load("drop-shadow"), &img);
+    let mut plugin = Plugin::load("drop-shadow")
+        .config("blur", "huge")
+        .config("offset_y", "99999")
+        .config("opacity", "1.5")
+        .config("color", "[1, 2]");
+    assert_eq!(shadowed(&mut plugin, &img), want);
+    assert_eq!(plugin.logs().iter().filter(|(l, _)| *l == WARN).count(), 4);
+}
+
+#[test]
+fn oversized_output_is_skipped() {
+    // default padding is 24 + 24 wide, 16 + 32 tall
+    let mut plugin = Plugin::load("drop-shadow");
+    assert!(matches!(
+        plugin.on_capture(&Image::filled(16336, 1, [255; 4]), Mode::Region),
+        Reply::Replaced(_)
+    ));
+    assert_eq!(
+        plugin.on_capture(&Image::filled(16337, 1, [255; 4]), Mode::Region),
+        Reply::Unchanged
+    );
+}
@@ -0,0 +1,153 @@
+//! grayscale: a golden row for every `mode`, duotone colours, and the
+//! BT.601 default staying what it always was.
+
+use capscr_plugin_harness::{Image, Plugin};
+use capscr_plugin_sdk::image::Mode;
+use capscr_plugin_sdk::log::WARN;
+
+// saturated primaries (each alpha different), a warm mid-tone, white, black,
+// a blue and mid grey
+const PIXELS: [[u8; 4]; 8] = [
+    [255, 0, 0, 255],
+    [0, 255, 0, 128],
+    [0, 0, 255, 0],
+    [200, 150, 100, 255],
+    [255, 255, 255, 255],
+    [0, 0, 0, 10],
+    [18, 120, 210, 200],
+    [128, 128, 128, 255],
+];
+
+fn row() -> Image {
+    Image::new(PIXELS.len() as u32, 1, PIXELS.concat())
+}
+
+fn convert(plugin: &mut Plugin) -> Vec<[u8; 4]> {
+    let out = plugin.on_capture(&row(), Mode::Region).image();
+    assert!(plugin.logs().iter().all(|(l, _)| *l != WARN));
+    (0..out.width).map(|x| out.pixel(x, 0)).collect()
+}
+
+/// the expected grey for each of `PIXELS`, alpha kept.
+fn greys(values: [u8; 8]) -> Vec<[u8; 4]> {
+    PIXELS
+        .iter()
+        .zip(values)
+        .map(|(px, v)| [v, v, v, px[3]])
+        .collect()
+}
+
+/// the expected colour for each of `PIXELS`, alpha kept.
+fn tinted(values: [[u8; 3]; 8]) -> Vec<[u8; 4]> {
+    PIXELS
+        .iter()
+        .zip(values)
+        .map(|(px, [r, g, b])| [r, g, b, px[3]])
+        .collect()
+}
+
+#[test]
+fn default_is_bt601_luma() {
+    let bt601 = greys([76, 149, 29, 159, 255, 0, 99, 128]);
+    assert_eq!(convert(&mut Plugin::load("grayscale")), bt601);
+    let mut plugin = Plugin::load("grayscale").config("mode", "bt601");
+    assert_eq!(convert(&mut plugin), bt601);
+}
+
+#[test]
+fn bt709_luma() {
+    let mut plugin = Plugin::load("grayscale").config("mode", "bt709");
+    assert_eq!(
+        convert(&mut plugin),
+        greys([54, 182, 18, 157, 255, 0, 104, 128])
+    );
+}
+
+#[test]
+fn lightness_keeps_perceived_brightness() {
+    // the same L* as the colour: pure red reads as mid grey, not dark grey
+    let mut plugin = Plugin::load("grayscale").config("mode", "lightness");
+    assert_eq!(
+        convert(&mut plugin),
+        greys([127, 220, 76, 160, 255, 0, 118, 128])
+    );
+}
+
+#[test]
+fn channel_average() {
+    let mut plugin = Plugin::load("grayscale").config("mode", "average");
+    assert_eq!(
+        convert(&mut plugin),
+        greys([85, 85, 85, 150, 255, 0, 116, 128])
+    );
+}
+
+#[test]
+fn sepia_tones() {
+    let mut plugin = Plugin::load("grayscale").config("mode", "sepia");
+    assert_eq!(
+        convert(&mut plugin),
+        tinted([
+            [100, 88, 69],
+            [196, 174, 136],
+            [48, 42, 33],
+            [212, 189, 147],
+            [255, 255, 238],
+            [0, 0, 0],
+            [139, 123, 96],
+            [172, 153, 119],
+        ])
+    );
+}
+
+#[test]
+fn duotone_maps_luma_between_two_colours() {
+    let mut plugin = Plugin::load("grayscale").config("mode", "duotone");
+    assert_eq!(
+        convert(&mut plugin),
+        tinted([
+            [100, 85, 99],
+            [161, 138, 120],
+            [60, 51, 86],
+            [169, 145, 123],
+            [250, 214, 150],
+            [36, 30, 78],
+            [119, 101, 106],
+            [143, 122, 114],
+        ])
+    );
+
+    // black to white is plain BT.601 again
+    let mut plugin = Plugin::load("grayscale")
+        .config("mode", "duotone")
+        .config("duotone_dark", "#000000")
+        .config("duotone_light", "[255, 255, 255]");
+    assert_eq!(
+        convert(&mut plugin),
+        greys([76, 149, 29, 159, 255, 0, 99, 128])
+    );
+
+    // the ends land exactly on the configured colours
+    let mut plugin = Plugin::load("grayscale")
+        .config("mode", "duotone")
+        .config("duotone_dark", "#102030")
+        .config("duotone_light", "#f0e0d0");
+    let out = convert(&mut plugin);
+    assert_eq!(out[4], [0xf0, 0xe0, 0xd0, 255]);
+    assert_eq!(out[5], [0x10, 0x20, 0x30, 10]);
+}
+
+#[test]
+fn invalid_settings_warn_and_fall_back() {
+    let mut plugin = Plugin::load("grayscale").config("mode", "rec2020");
+    let out = plugin.on_capture(&row(), Mode::Region).image();
+    assert_eq!(out.pixel(0, 0), [76, 76, 76, 255]);
+    assert_eq!(plugin.logs().iter().filter(|(l, _)| *l == WARN).count(), 1);
+
+    let mut plugin = Plugin::load("grayscale")
+        .config("mode", "duotone")
+        .config("duotone_dark", "navy");
+    let out = plugin.on_capture(&row(), Mode::Region).image();
+    assert_eq!(out.pixel(5, 0), [36, 30, 78, 10]);
+    assert_eq!(plugin.logs().iter().filter(|(l, _)| *l == WARN).count(), 1);
+}
@@ -0,0 +1,194 @@
+//! hidpi-normalize: exact recovery of 2× and 3× nearest-upscaled images, per
+//! axis and at an offset, leaving unduplicated and flat captures alone, and
+//! the explicit `scale`.
+
+use capscr_plugin_harness::{Image, Plugin, Reply};
+use capscr_plugin_sdk::image::Mode;
+use capscr_plugin_sdk::log::WARN;
+
+/// `img` with every pixel repeated `kx` times across and `ky` times down.
+fn replicate(img: &Image, kx: u32, ky: u32) -> Image {
+    let (w, h) = (img.width * kx, img.height * ky);
+    let mut rgba = Vec::with_capacity(w as usize * h as usize * 4);
+    for y in 0..h {
+        for x in 0..w {
+            rgba.extend_from_slice(&img.pixel(x / kx, y / ky));
+        }
+    }
+    Image::new(w, h, rgba)
+}
+
+/// the `w`×`h` part of `img` starting at (x0, y0).
+fn crop(img: &Image, (x0, y0): (u32, u32), (w, h): (u32, u32)) -> Image {
+    let mut rgba = Vec::with_capacity(w as usize * h as usize * 4);
+    for y in y0..y0 + h {
+        for x in x0..x0 + w {
+            rgba.extend_from_slice(&img.pixel(x, y));
+        }
+    }
+    Image::new(w, h, rgba)
+}
+
+fn normalize(plugin: &mut Plugin, img: &Image) -> Image {
+    match plugin.on_capture(img, Mode::Region) {
+        Reply::Replaced(out) => out,
+        other => panic!("expected a replacement, got {other:?}"),
+    }
+}
+
+#[test]
+fn collapses_2x_and_3x_duplication_exactly() {
+    let native = Image::synthetic(120, 80);
+    for k in [2, 3, 4] {
+        let mut plugin = Plugin::load("hidpi-normalize");
+        let out = normalize(&mut plugin, &replicate(&native, k, k));
+        assert_eq!(out, native, "{k}x");
+        assert!(plugin.logs().iter().all(|(l, _)| *l != WARN));
+    }
+}
+
+#[test]
+fn each_axis_is_detected_on_its_own() {
+    let native = Image::synthetic(90, 70);
+    for (kx, ky) in [(2, 1), (1, 2), (3, 2)] {
+        let mut plugin = Plugin::load("hidpi-normalize");
+        let out = normalize(&mut plugin, &replicate(&native, kx, ky));
+        assert_eq!(out, native, "{kx}x{ky}");
+    }
+}
+
+#[test]
+fn blocks_cut_by_the_capture_edge_still_collapse() {
+    // a region that starts mid-block and ends mid-block on both axes: the
+    // partial blocks at the edges each become one pixel like any other
+    let native = Image::synthetic(60, 40);
+    let doubled = replicate(&native, 2, 2);
+    let cropped = crop(&doubled, (1, 1), (118, 78));
+    let out = normalize(&mut Plugin::load("hidpi-normalize"), &cropped);
+    assert_eq!((out.width, out.height), (60, 40));
+    assert_eq!(out, native);
+
+    let tripled = replicate(&native, 3, 3);
+    let cropped = crop(&tripled, (2, 1), (176, 117));
+    let out = normalize(&mut Plugin::load("hidpi-normalize"), &cropped);
+    assert_eq!(out, native);
+}
+
+#[test]
+fn native_resolution_captures_are_left_alone() {
+    let mut plugin = Plugin::load("hidpi-normalize");
+    let native = Image::synthetic(200, 150);
+    assert_eq!(plugin.on_capture(&native, Mode::Region), Reply::Unchanged);
+
+    // duplicated everywhere but one stray native pixel, e.g. the cursor
+    let mut almost = replicate(&native, 2, 2);
+    let i = (101 * almost.width as usize + 151) * 4;
+    almost.rgba[i] ^= 0xff;
+    assert_eq!(plugin.on_capture(&almost, Mode::Region), Reply::Unchanged);
+
+    for px in [[0, 0, 0, 255], [255, 255, 255, 0]] {
+        let flat = Image::filled(64, 48, px);
+        assert_eq!(plugin.on_capture(&flat, Mode::Region), Reply::Unchanged);
+    }
+}
+
+#[test]
+fn flat_stripes_collapse_only_the_varied_axis() {
+    // vertical 2-px stripes: columns are duplicated, rows are all identical —
+    // the width halves and the height, being flat, is kept
+    let native = Image::synthetic(50, 1);
+    let stripes = replicate(&native, 2, 30);
+    let out = normalize(&mut Plugin::load("hidpi-normalize"), &stripes);
+    assert_eq!((out.width, out.height), (50, 30));
+    assert_eq!(out, replicate(&native, 1, 30));
+}
+
+#[test]
+fn max_factor_caps_the_detected_factor() {
+    let native = Image::synthetic(40, 30);
+    let quad = replicate(&native, 4, 4);
+    let mut plugin = Plugin::load("hidpi-normalize").config("max_factor", "2");
+    let out = normalize(&mut plugin, &quad);
+    assert_eq!(out, replicate(&native, 2, 2));
+}
+
+#[test]
+fn explicit_scale_box_averages() {
+    let img = Image::new(
+        4,
+        3,
+        [
+            [0, 0, 0, 255],
+            [100, 0, 0, 255],
+            [10, 10, 10, 255],
+            [20, 20, 20, 255],
+            [200, 0, 0, 255],
+            [0, 100, 0, 255],
+            [30, 30, 30, 255],
+            [40, 40, 40, 255],
+            [7, 7, 7, 255],
+            [9, 9, 9, 255],
+            [50, 50, 50, 255],
+            [51, 51, 51, 255],
+        ]
+        .concat(),
+    );
+    let out = normalize(
+        &mut Plugin::load("hidpi-normalize").config("scale", "0.5"),
+        &img,
+    );
+    assert_eq!((out.width, out.height), (2, 2));
+    assert_eq!(out.pixel(0, 0), [75, 25, 0, 255]);
+    assert_eq!(out.pixel(1, 0), [25, 25, 25, 255]);
+    assert_eq!(out.pixel(0, 1), [8, 8, 8, 255]);
+    assert_eq!(out.pixel(1, 1), [51, 51, 51, 255]);
+
+    // not duplicated, but scaled all the same; thirds round up at the edge
+    let native = Image::synthetic(301, 200);
+    for (scale, size) in [
+        ("0.5", (151, 100)),
+        ("0.333", (101, 67)),
+        ("0.25", (76, 50)),
+    ] {
+        let mut plugin = Plugin::load("hidpi-normalize").config("scale", scale);
+        let out = normalize(&mut plugin, &native);
+        assert_eq!((out.width, out.height), size, "{scale}");
+    }
+}
+
+#[test]
+fn explicit_scale_of_duplicated_content_is_lossless() {
+    let native = Image::synthetic(64, 48);
+    let mut plugin = Plugin::load("hidpi-normalize").config("scale", "0.5");
+    assert_eq!(normalize(&mut plugin, &replicate(&native, 2, 2)), native);
+}
+
+#[test]
+fn invalid_settings_warn_and_fall_back_to_auto() {
+    let native = Image::synthetic(50, 40);
+    let doubled = replicate(&native, 2, 2);
+    for (key, value) in [
+        ("scale", "0.7"),
+        ("scale", "2"),
+        ("scale", "0"),
+        ("scale", "half"),
+        ("max_factor", "1"),
+        ("max_factor", "12"),
+    ] {
+        let mut plugin = Plugin::load("hidpi-normalize").config(key, value);
+        assert_eq!(normalize(&mut plugin, &doubled), native, "{key} = {value}");
+        let warned = plugin.logs().iter().filter(|(l, _)| *l == WARN).count();
+        assert_eq!(warned, 1, "{key} = {value}");
+    }
+}
+
+#[test]
+fn only_modes_skips_other_captures() {
+    let doubled = replicate(&Image::synthetic(40, 30), 2, 2);
+    let mut plugin = Plugin::load("hidpi-normalize").config("only_modes", "[\"window\"]");
+    assert_eq!(plugin.on_capture(&doubled, Mode::Region), Reply::Unchanged);
+    assert!(matches!(
+        plugin.on_capture(&doubled, Mode::Window),
+        Reply::Replaced(_)
+    ));
+}
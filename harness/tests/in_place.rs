@@ -0,0 +1,42 @@
+//! The in-place on_capture return: same-size filters must not need a second
+//! image-sized buffer in guest memory.
+
+use capscr_plugin_harness::{Image, Plugin, Reply};
+use capscr_plugin_sdk::image::Mode;
+
+const MIB: usize = 1 << 20;
+
+#[test]
+fn grayscale_rewrites_the_input_in_place() {
+    let img = Image::synthetic(33, 17);
+    let reply = Plugin::load("grayscale").on_capture(&img, Mode::Region);
+    let Reply::InPlace(out) = reply else {
+        panic!("expected an in-place reply, got {reply:?}");
+    };
+    for y in 0..img.height {
+        for x in 0..img.width {
+            let [r, g, b, a] = img.pixel(x, y);
+            let l = ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8;
+            assert_eq!(out.pixel(x, y), [l, l, l, a], "pixel ({x},{y})");
+        }
+    }
+}
+
+#[test]
+fn grayscale_peak_memory_stays_near_one_image() {
+    let img = Image::synthetic(2048, 2048); // 16 MiB
+    let mut plugin = Plugin::load("grayscale");
+    let before = plugin.memory_size();
+    plugin.on_capture(&img, Mode::FullScreen).image();
+    let growth = plugin.memory_size() - before;
+    assert!(
+        growth <= img.rgba.len() + img.rgba.len() / 20 + MIB,
+        "linear memory grew {growth} bytes for a {} byte image",
+        img.rgba.len()
+    );
+
+    // the scratch buffer is reused: a second capture costs nothing more
+    let after_first = plugin.memory_size();
+    plugin.on_capture(&img, Mode::FullScreen).image();
+    assert_eq!(plugin.memory_size(), after_first);
+}
@@ -0,0 +1,88 @@
+//! The read-only on_capture_inspect hook and the manifest rules around it.
+
+use capscr_plugin_harness::manifest::{wasm_plugins, Manifest};
+use capscr_plugin_harness::{run_chain, Image, Plugin};
+use capscr_plugin_sdk::image::Mode;
+use capscr_plugin_sdk::log::INFO;
+
+#[test]
+fn capture_stats_logs_a_summary() {
+    let mut img = Image::filled(4, 2, [10, 20, 30, 255]);
+    img.rgba[4..8].copy_from_slice(&[50, 60, 70, 0]);
+    let mut plugin = Plugin::load("capture-stats");
+    plugin.on_capture_inspect(&img, Mode::Window);
+    assert_eq!(
+        plugin.logs(),
+        [(
+            INFO,
+            "4x2 window: mean rgba(15, 25, 35, 223), 2 colors, 12.5% translucent".to_owned()
+        )]
+    );
+}
+
+#[test]
+fn inspectors_see_the_output_of_the_modifying_chain() {
+    let img = Image::synthetic(64, 32);
+    let mut modifiers = [
+        Plugin::load("grayscale"),
+        Plugin::load("downscale").config("max_dimension", "16"),
+    ];
+    let mut inspectors = [Plugin::load("capture-stats")];
+    let out = run_chain(&mut modifiers, &mut inspectors, &img, Mode::Region).unwrap();
+    assert_eq!((out.width, out.height), (16, 8));
+    let logs = inspectors[0].logs();
+    assert_eq!(logs.len(), 1);
+    assert!(logs[0].1.starts_with("16x8 region: "), "{}", logs[0].1);
+}
+
+#[test]
+fn every_manifest_is_consistent_and_exported() {
+    let ids = wasm_plugins();
+    assert!(ids.iter().any(|id| id == "capture-stats"), "{ids:?}");
+    for id in ids {
+        let manifest = Manifest::load(&id);
+        assert_eq!(manifest.id, id);
+        assert_eq!(
+            manifest.problems(),
+            Vec::<String>::new(),
+            "{id}/plugin.toml"
+        );
+        let mut plugin = Plugin::load(&id);
+        for export in manifest.required_exports() {
+            assert!(
+                plugin.exports(&export),
+                "{id} declares but doesn't export `{export}`"
+            );
+        }
+    }
+}
+
+#[test]
+fn manifest_rules_catch_capability_mismatches() {
+    let manifest = |hooks: &str, image: &str| {
+        Manifest::parse(&format!(
+            "[plugin]\nid = \"x\"\n[runtime]\ntype = \"wasm\"\n[hooks]\n{hooks}\n[capabilities]\n{image}\n"
+        ))
+        .unwrap()
+        .problems()
+    };
+    let inspect = "on_capture_inspect = \"capscr_on_capture_inspect\"";
+    assert!(manifest(inspect, "image = [\"read\"]").is_empty());
+    // an inspector asking for modify is over-privileged
+    assert_eq!(manifest(inspect, "image = [\"read\", \"modify\"]").len(), 1);
+    assert_eq!(manifest(inspect, "").len(), 1);
+    // a modifying hook can't make do with read
+    assert_eq!(
+        manifest("on_capture = \"capscr_on_capture\"", "image = [\"read\"]").len(),
+        1
+    );
+    assert_eq!(
+        manifest(
+            "on_capture_tiled = \"capscr_on_capture_tiled\"",
+            "image = [\"read\", \"modify\"]"
+        )
+        .len(),
+        1
+    );
+    assert_eq!(manifest("on_capture_bogus = \"f\"", "").len(), 1);
+}
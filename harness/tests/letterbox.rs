@@ -0,0 +1,187 @@
+//! letterbox: canvas size for each aspect, alignment, the three fills, and
+//! `only_modes`.
+
+use capscr_plugin_harness::{Image, Plugin, Reply};
+use capscr_plugin_sdk::image::Mode;
+use capscr_plugin_sdk::log::WARN;
+
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+const RED: [u8; 4] = [220, 20, 20, 255];
+const BLUE: [u8; 4] = [20, 20, 220, 255];
+
+/// the synthetic pattern with every pixel made opaque.
+fn opaque(w: u32, h: u32) -> Image {
+    let mut img = Image::synthetic(w, h);
+    img.rgba.chunks_exact_mut(4).for_each(|px| px[3] = 255);
+    img
+}
+
+fn boxed(plugin: &mut Plugin, img: &Image) -> Image {
+    match plugin.on_capture(img, Mode::Region) {
+        Reply::Replaced(out) => out,
+        other => panic!("expected a replacement, got {other:?}"),
+    }
+}
+
+/// true if `img` sits in `out` with its top-left at (x0, y0).
+fn contains_at(out: &Image, img: &Image, (x0, y0): (u32, u32)) -> bool {
+    (0..img.height).all(|y| (0..img.width).all(|x| out.pixel(x0 + x, y0 + y) == img.pixel(x, y)))
+}
+
+#[test]
+fn pads_to_each_aspect_without_cropping() {
+    for ((w, h), aspect, size, at) in [
+        ((100, 100), "16:9", (178, 100), (39, 0)),
+        ((200, 100), "1:1", (200, 200), (0, 50)),
+        ((100, 100), "4:5", (100, 125), (0, 12)),
+        ((90, 160), "9:16", (90, 160), (0, 0)),
+        ((300, 100), "16:9", (300, 169), (0, 34)),
+    ] {
+        let img = opaque(w, h);
+        let mut plugin = Plugin::load("letterbox").config("aspect", aspect);
+        if size == (w, h) {
+            assert_eq!(plugin.on_capture(&img, Mode::Region), Reply::Unchanged);
+            continue;
+        }
+        let out = boxed(&mut plugin, &img);
+        assert_eq!((out.width, out.height), size, "{aspect}");
+        assert!(contains_at(&out, &img, at), "{aspect}");
+        assert_eq!(out.pixel(0, 0), BLACK, "{aspect}");
+        assert_eq!(out.pixel(size.0 - 1, size.1 - 1), BLACK, "{aspect}");
+    }
+}
+
+#[test]
+fn alignment_moves_the_capture_along_the_padded_axis() {
+    let wide = opaque(200, 100);
+    let tall = opaque(100, 200);
+    for (align, wide_at, tall_at) in [
+        ("start", (0, 0), (0, 0)),
+        ("top", (0, 0), (0, 0)),
+        ("center", (0, 50), (50, 0)),
+        ("end", (0, 100), (100, 0)),
+        ("right", (0, 100), (100, 0)),
+    ] {
+        let load = || {
+            Plugin::load("letterbox")
+                .config("aspect", "1:1")
+                .config("align", align)
+        };
+        assert!(
+            contains_at(&boxed(&mut load(), &wide), &wide, wide_at),
+            "{align}"
+        );
+        assert!(
+            contains_at(&boxed(&mut load(), &tall), &tall, tall_at),
+            "{align}"
+        );
+    }
+}
+
+#[test]
+fn solid_and_transparent_fills() {
+    let img = opaque(40, 40);
+    let out = boxed(
+        &mut Plugin::load("letterbox")
+            .config("aspect", "2:1")
+            .config("color", "#102030"),
+        &img,
+    );
+    assert_eq!(out.pixel(0, 20), [0x10, 0x20, 0x30, 255]);
+    assert_eq!(out.pixel(79, 20), [0x10, 0x20, 0x30, 255]);
+
+    let out = boxed(
+        &mut Plugin::load("letterbox")
+            .config("aspect", "2:1")
+            .config("fill", "transparent")
+            .config("color", "#102030"),
+        &img,
+    );
+    assert_eq!(out.pixel(0, 20), [0; 4]);
+    assert!(contains_at(&out, &img, (20, 0)));
+}
+
+#[test]
+fn blur_fill_extends_the_edges() {
+    // top half red, bottom half blue: the side bars continue both, blended
+    // only around the middle row
+    let mut img = Image::filled(60, 80, RED);
+    for y in 40..80 {
+        for x in 0..60 {
+            img.rgba[((y * 60 + x) * 4) as usize..][..4].copy_from_slice(&BLUE);
+        }
+    }
+    let out = boxed(
+        &mut Plugin::load("letterbox")
+            .config("aspect", "1:1")
+            .config("fill", "blur")
+            .config("blur", "8"),
+        &img,
+    );
+    assert_eq!((out.width, out.height), (80, 80));
+    assert!(contains_at(&out, &img, (10, 0)));
+    for x in [0, 5, 9, 70, 79] {
+        assert_eq!(out.pixel(x, 0), RED, "x={x}");
+        assert_eq!(out.pixel(x, 79), BLUE, "x={x}");
+        // the seam between the halves is blurred: red fades into blue
+        let reds: Vec<u8> = (0..80).map(|y| out.pixel(x, y)[0]).collect();
+        assert!(reds.windows(2).all(|p| p[0] >= p[1]), "x={x}: {reds:?}");
+        let mid = out.pixel(x, 40)[0];
+        assert!((60..180).contains(&mid), "x={x}: {mid}");
+    }
+
+    // a uniform capture extends into uniform bars, above and below too
+    let img = Image::filled(80, 20, BLUE);
+    let out = boxed(
+        &mut Plugin::load("letterbox")
+            .config("aspect", "1:1")
+            .config("fill", "blur"),
+        &img,
+    );
+    assert!(out.rgba.chunks_exact(4).all(|px| px == BLUE));
+}
+
+#[test]
+fn only_modes_limits_which_captures_are_padded() {
+    let img = opaque(30, 10);
+    let mut plugin = Plugin::load("letterbox").config("only_modes", "[\"region\"]");
+    assert_eq!(plugin.on_capture(&img, Mode::Window), Reply::Unchanged);
+    assert_eq!(plugin.on_capture(&img, Mode::FullScreen), Reply::Unchanged);
+    assert!(matches!(
+        plugin.on_capture(&img, Mode::Region),
+        Reply::Replaced(_)
+    ));
+}
+
+#[test]
+fn invalid_values_fall_back_with_a_warning() {
+    let img = opaque(100, 100);
+    let mut plugin = Plugin::load("letterbox")
+        .config("aspect", "wide")
+        .config("fill", "mirror")
+        .config("align", "middle")
+        .config("blur", "1000");
+    let out = boxed(&mut plugin, &img);
+    assert_eq!((out.width, out.height), (178, 100));
+    assert!(contains_at(&out, &img, (39, 0)));
+    let warned: Vec<_> = plugin.logs().iter().filter(|(l, _)| *l == WARN).collect();
+    assert_eq!(warned.len(), 4, "{warned:?}");
+
+    // past 10:1 either way: warned about, and the default used
+    for aspect in ["100:1", "1:11"] {
+        let mut plugin = Plugin::load("letterbox").config("aspect", aspect);
+        let out = boxed(&mut plugin, &img);
+        assert_eq!((out.width, out.height), (178, 100), "{aspect}");
+        let warned = plugin.logs().iter().filter(|(l, _)| *l == WARN).count();
+        assert_eq!(warned, 1, "{aspect}");
+    }
+}
+
+#[test]
+fn oversized_canvas_is_skipped() {
+    let mut plugin = Plugin::load("letterbox").config("aspect", "16:9");
+    assert_eq!(
+        plugin.on_capture(&Image::filled(10, 9300, BLACK), Mode::Region),
+        Reply::Unchanged
+    );
+}
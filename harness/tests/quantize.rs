@@ -0,0 +1,196 @@
+//! quantize: the lossless fast path, the palette-size bound under every
+//! dither, flat colours kept exactly, dithering holding a gradient's local
+//! average, and config handling.
+
+use std::collections::HashSet;
+
+use capscr_plugin_harness::{Image, Plugin, Reply};
+use capscr_plugin_sdk::image::Mode;
+use capscr_plugin_sdk::log::WARN;
+
+const DITHERS: [&str; 3] = ["none", "floyd-steinberg", "ordered"];
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+fn colors(img: &Image) -> usize {
+    img.rgba.chunks_exact(4).collect::<HashSet<_>>().len()
+}
+
+fn quantize(plugin: &mut Plugin, img: &Image) -> Image {
+    match plugin.on_capture(img, Mode::Region) {
+        Reply::InPlace(out) => out,
+        other => panic!("expected an in-place reply, got {other:?}"),
+    }
+}
+
+/// a horizontal grey ramp, 0 at the left to 255 at the right.
+fn ramp(w: u32, h: u32) -> Image {
+    let mut rgba = Vec::new();
+    for _ in 0..h {
+        for x in 0..w {
+            let v = (x * 255 / (w - 1)) as u8;
+            rgba.extend_from_slice(&[v, v, v, 255]);
+        }
+    }
+    Image::new(w, h, rgba)
+}
+
+/// the mean grey of columns `x0..x0 + 16` of `img`.
+fn block_mean(img: &Image, x0: u32) -> f64 {
+    let mut sum = 0u32;
+    for y in 0..img.height {
+        for x in x0..x0 + 16 {
+            sum += img.pixel(x, y)[0] as u32;
+        }
+    }
+    sum as f64 / (16 * img.height) as f64
+}
+
+#[test]
+fn captures_within_the_palette_come_back_untouched() {
+    // 200 colours (of many alphas, too) against the default 256
+    let mut img = Image::synthetic(120, 90);
+    for (i, px) in img.rgba.chunks_exact_mut(4).enumerate() {
+        let c = (i % 200) as u8;
+        px.copy_from_slice(&[c, 255 - c, c / 2, 55 + c]);
+    }
+    assert_eq!(colors(&img), 200);
+    assert_eq!(quantize(&mut Plugin::load("quantize"), &img), img);
+
+    // exactly at the limit
+    let mut plugin = Plugin::load("quantize").config("colors", "200");
+    assert_eq!(quantize(&mut plugin, &img), img);
+}
+
+#[test]
+fn output_never_exceeds_the_palette_size() {
+    let img = Image::synthetic(97, 61);
+    assert!(colors(&img) > 256);
+    for n in [2, 16, 256] {
+        for dither in DITHERS {
+            let mut plugin = Plugin::load("quantize")
+                .config("colors", &n.to_string())
+                .config("dither", dither);
+            let out = quantize(&mut plugin, &img);
+            assert!(colors(&out) <= n, "{n} colours, {dither}: {}", colors(&out));
+            assert!(plugin.logs().iter().all(|(l, _)| *l != WARN));
+        }
+    }
+}
+
+#[test]
+fn flat_colors_survive_exactly() {
+    // white above, darker noise below: white needs a palette entry of its
+    // own, and gets back exactly itself
+    let mut img = Image::synthetic(80, 60);
+    for (i, px) in img.rgba.chunks_exact_mut(4).enumerate() {
+        if i < 80 * 30 {
+            px.copy_from_slice(&WHITE);
+        } else {
+            px.iter_mut().take(3).for_each(|c| *c /= 2);
+            px[3] = 255;
+        }
+    }
+    for dither in ["none", "floyd-steinberg"] {
+        let mut plugin = Plugin::load("quantize")
+            .config("colors", "16")
+            .config("dither", dither);
+        let out = quantize(&mut plugin, &img);
+        for y in 0..30 {
+            for x in 0..80 {
+                assert_eq!(out.pixel(x, y), WHITE, "{dither} ({x},{y})");
+            }
+        }
+    }
+}
+
+#[test]
+fn nearest_color_is_close_for_clustered_input() {
+    // eight colours, each smeared by ±2: eight palette entries cover them
+    let centres = [
+        [30, 30, 30],
+        [220, 220, 220],
+        [200, 40, 40],
+        [40, 200, 40],
+        [40, 40, 200],
+        [200, 200, 40],
+        [40, 200, 200],
+        [120, 60, 180],
+    ];
+    let mut img = Image::synthetic(64, 64);
+    for (i, px) in img.rgba.chunks_exact_mut(4).enumerate() {
+        let c = centres[(i / 7) % 8];
+        let jitter = px[2] as i32 % 5 - 2;
+        for ch in 0..3 {
+            px[ch] = (c[ch] + jitter) as u8;
+        }
+        px[3] = 255;
+    }
+    assert!(colors(&img) > 8);
+    let out = quantize(&mut Plugin::load("quantize").config("colors", "8"), &img);
+    assert_eq!(colors(&out), 8);
+    for (a, b) in img.rgba.chunks_exact(4).zip(out.rgba.chunks_exact(4)) {
+        for ch in 0..4 {
+            assert!(a[ch].abs_diff(b[ch]) <= 2, "{a:?} -> {b:?}");
+        }
+    }
+}
+
+#[test]
+fn dithering_keeps_the_local_average_of_a_gradient() {
+    // two colours (about 64 and 192) for a 0-255 ramp: undithered, each
+    // 16-column block between them collapses to one of the two; dithered,
+    // its average stays close to the ramp's
+    let img = ramp(256, 32);
+    let worst = |dither: &str| {
+        let mut plugin = Plugin::load("quantize")
+            .config("colors", "2")
+            .config("dither", dither);
+        let out = quantize(&mut plugin, &img);
+        (64..192)
+            .step_by(16)
+            .map(|x| (block_mean(&out, x) - block_mean(&img, x)).abs())
+            .fold(0.0, f64::max)
+    };
+    assert!(worst("none") > 40.0);
+    assert!(
+        worst("floyd-steinberg") < 8.0,
+        "{}",
+        worst("floyd-steinberg")
+    );
+    assert!(worst("ordered") < 24.0, "{}", worst("ordered"));
+}
+
+#[test]
+fn alpha_is_matched_never_dithered() {
+    let mut img = Image::synthetic(70, 50);
+    img.rgba.chunks_exact_mut(4).for_each(|px| px[3] = 255);
+    for dither in DITHERS {
+        let mut plugin = Plugin::load("quantize")
+            .config("colors", "8")
+            .config("dither", dither);
+        let out = quantize(&mut plugin, &img);
+        assert!(out.rgba.chunks_exact(4).all(|px| px[3] == 255), "{dither}");
+    }
+}
+
+#[test]
+fn invalid_settings_warn_and_fall_back() {
+    let img = Image::synthetic(97, 61);
+    for (key, value) in [("colors", "1"), ("colors", "257"), ("dither", "bayer")] {
+        let mut plugin = Plugin::load("quantize").config(key, value);
+        let out = quantize(&mut plugin, &img);
+        assert!(colors(&out) <= 256, "{key} = {value}");
+        let warned = plugin.logs().iter().filter(|(l, _)| *l == WARN).count();
+        assert_eq!(warned, 1, "{key} = {value}");
+    }
+}
+
+#[test]
+fn only_modes_skips_other_captures() {
+    let img = Image::synthetic(40, 30);
+    let mut plugin = Plugin::load("quantize")
+        .config("colors", "4")
+        .config("only_modes", "[\"window\"]");
+    assert_eq!(plugin.on_capture(&img, Mode::Region), Reply::Unchanged);
+    assert!(colors(&plugin.on_capture(&img, Mode::Window).image()) <= 4);
+}
@@ -0,0 +1,316 @@
+//! redact: pixelated blocks inside the rectangles and nothing outside,
+//! anchors and percentages, clipping, per-mode rule sets, no original pixel
+//! surviving a fill or blur, blur noise independent of the content,
+//! per-rectangle styles, and config handling.
+
+use capscr_plugin_harness::{Image, Plugin, Reply};
+use capscr_plugin_sdk::image::Mode;
+use capscr_plugin_sdk::log::WARN;
+use capscr_plugin_sdk::srgb::Lut;
+
+fn redact(plugin: &mut Plugin, img: &Image, mode: Mode) -> Image {
+    match plugin.on_capture(img, mode) {
+        Reply::InPlace(out) => out,
+        other => panic!("expected an in-place reply, got {other:?}"),
+    }
+}
+
+fn with_rects(rects: &[&str]) -> Plugin {
+    let list: Vec<String> = rects.iter().map(|r| format!("\"{r}\"")).collect();
+    Plugin::load("redact").config("rects", &format!("[{}]", list.join(", ")))
+}
+
+/// the pixels of `out` that differ from `img`, as a bounding box
+/// `[x0, y0, x1, y1)`.
+fn changed(img: &Image, out: &Image) -> Option<[u32; 4]> {
+    let mut bounds: Option<[u32; 4]> = None;
+    for y in 0..img.height {
+        for x in 0..img.width {
+            if img.pixel(x, y) != out.pixel(x, y) {
+                let b = bounds.get_or_insert([x, y, x + 1, y + 1]);
+                *b = [b[0].min(x), b[1].min(y), b[2].max(x + 1), b[3].max(y + 1)];
+            }
+        }
+    }
+    bounds
+}
+
+/// the average of `img` over `[x0, y0, x1, y1)`, in linear light with
+/// premultiplied alpha.
+fn mean(img: &Image, [x0, y0, x1, y1]: [u32; 4]) -> [u8; 4] {
+    let lut = Lut::new();
+    let mut sum = [0.0f32; 4];
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let v = lut.premultiplied(img.pixel(x, y));
+            for c in 0..4 {
+                sum[c] += v[c];
+            }
+        }
+    }
+    let n = ((x1 - x0) * (y1 - y0)) as f32;
+    lut.unpremultiplied(sum.map(|s| s / n))
+}
+
+#[test]
+fn pixelates_inside_the_rectangle_only() {
+    let img = Image::synthetic(100, 80);
+    let mut plugin = with_rects(&["top-left 10 20 40 20"]);
+    let out = redact(&mut plugin, &img, Mode::Window);
+    assert_eq!(changed(&img, &out), Some([10, 20, 50, 40]));
+    // 16 px blocks from the rectangle's corner; the last column and row of
+    // blocks are cut short by its edges
+    for [x0, y0, x1, y1] in [
+        [10, 20, 26, 36],
+        [26, 20, 42, 36],
+        [42, 20, 50, 36],
+        [10, 36, 26, 40],
+        [42, 36, 50, 40],
+    ] {
+        let want = mean(&img, [x0, y0, x1, y1]);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                assert_eq!(out.pixel(x, y), want, "({x},{y})");
+            }
+        }
+    }
+    assert!(plugin.logs().iter().all(|(l, _)| *l != WARN));
+}
+
+#[test]
+fn anchors_measure_from_their_corner() {
+    let img = Image::synthetic(120, 90);
+    for (rect, want) in [
+        ("top-left 5 6 20 10", [5, 6, 25, 16]),
+        ("top-right 5 6 20 10", [95, 6, 115, 16]),
+        ("bottom-left 5 6 20 10", [5, 74, 25, 84]),
+        ("bottom-right 5 6 20 10", [95, 74, 115, 84]),
+        ("top-left 0 0 100% 10%", [0, 0, 120, 9]),
+        ("bottom-right 0 0 25% 100%", [90, 0, 120, 90]),
+        // just short of a third: the far edges round outward, to 52 and 30
+        ("top-left 10% 0 33.3333% 33.3333%", [12, 0, 52, 30]),
+    ] {
+        let mut plugin = with_rects(&[rect]).config("block", "2");
+        let out = redact(&mut plugin, &img, Mode::Window);
+        assert_eq!(changed(&img, &out), Some(want), "{rect}");
+    }
+}
+
+#[test]
+fn rectangles_are_clipped_to_the_capture() {
+    let img = Image::synthetic(60, 40);
+    let mut plugin = with_rects(&["bottom-right 0 0 500 20"]).config("block", "4");
+    let out = redact(&mut plugin, &img, Mode::Window);
+    assert_eq!(changed(&img, &out), Some([0, 20, 60, 40]));
+
+    // entirely off the capture, or empty: nothing to do
+    for rect in ["top-left 60 0 10 10", "top-left 0 0 0 10"] {
+        let mut plugin = with_rects(&[rect]);
+        assert_eq!(plugin.on_capture(&img, Mode::Window), Reply::Unchanged);
+    }
+}
+
+#[test]
+fn each_mode_can_have_its_own_rectangles() {
+    let img = Image::synthetic(80, 60);
+    let load = || {
+        with_rects(&["top-left 0 0 20 20"])
+            .config(
+                "window_rects",
+                "[\"top-right 0 0 20 20\", \"bottom-left 0 0 10 10\"]",
+            )
+            .config("region_rects", "[]")
+    };
+    let out = redact(&mut load(), &img, Mode::FullScreen);
+    assert_eq!(changed(&img, &out), Some([0, 0, 20, 20]));
+    let out = redact(&mut load(), &img, Mode::Window);
+    assert_ne!(out.pixel(70, 5), img.pixel(70, 5));
+    assert_ne!(out.pixel(5, 55), img.pixel(5, 55));
+    assert_eq!(out.pixel(5, 5), img.pixel(5, 5));
+    assert_eq!(load().on_capture(&img, Mode::Region), Reply::Unchanged);
+}
+
+#[test]
+fn no_rectangles_means_no_change() {
+    let img = Image::synthetic(40, 30);
+    let mut plugin = Plugin::load("redact");
+    assert_eq!(plugin.on_capture(&img, Mode::Window), Reply::Unchanged);
+}
+
+#[test]
+fn a_bad_rectangle_is_skipped_and_the_rest_apply() {
+    let img = Image::synthetic(80, 60);
+    for bad in [
+        "top-middle 0 0 10 10",
+        "top-left 0 0 10",
+        "top-left 0 0 ten 10",
+        "top-left 0 0 150% 10",
+        "top-left -5 0 10 10",
+        "top-left 0 0 10 10 smudge",
+    ] {
+        let mut plugin = with_rects(&[bad, "bottom-right 0 0 16 16"]);
+        let out = redact(&mut plugin, &img, Mode::Window);
+        assert_eq!(changed(&img, &out), Some([64, 44, 80, 60]), "{bad}");
+        let warned = plugin.logs().iter().filter(|(l, _)| *l == WARN).count();
+        assert_eq!(warned, 1, "{bad}");
+    }
+}
+
+#[test]
+fn invalid_block_warns_and_falls_back() {
+    let img = Image::synthetic(80, 60);
+    let default = redact(&mut with_rects(&["top-left 0 0 40 40"]), &img, Mode::Window);
+    for block in ["1", "5000", "big"] {
+        let mut plugin = with_rects(&["top-left 0 0 40 40"]).config("block", block);
+        assert_eq!(redact(&mut plugin, &img, Mode::Window), default, "{block}");
+        let warned = plugin.logs().iter().filter(|(l, _)| *l == WARN).count();
+        assert_eq!(warned, 1, "{block}");
+    }
+}
+
+/// inputs that make surviving pixels likely: flat (a blur of it is itself),
+/// busy, and a single pixel of detail on flat.
+fn hard_inputs() -> Vec<Image> {
+    let mut dot = Image::filled(90, 70, [200, 200, 200, 255]);
+    let i = (30 * 90 + 40) * 4;
+    dot.rgba[i..i + 4].copy_from_slice(&[0, 0, 0, 255]);
+    vec![
+        Image::synthetic(90, 70),
+        Image::filled(90, 70, [255, 255, 255, 255]),
+        Image::filled(90, 70, [0, 0, 0, 0]),
+        dot,
+    ]
+}
+
+#[test]
+fn no_original_pixel_survives_a_blur() {
+    const AREA: [u32; 4] = [10, 5, 70, 55];
+    for img in hard_inputs() {
+        for noise in ["24", "0"] {
+            let mut plugin = with_rects(&["top-left 10 5 60 50"])
+                .config("style", "blur")
+                .config("noise", noise);
+            let out = redact(&mut plugin, &img, Mode::Window);
+            assert_eq!(changed(&img, &out), Some(AREA), "noise {noise}");
+            for y in AREA[1]..AREA[3] {
+                for x in AREA[0]..AREA[2] {
+                    assert_ne!(out.pixel(x, y), img.pixel(x, y), "({x},{y})");
+                }
+            }
+            assert!(plugin.logs().iter().all(|(l, _)| *l != WARN));
+        }
+    }
+}
+
+#[test]
+fn a_blur_leaves_no_detail() {
+    // two captures differing only by a bright dot: their blurs differ by
+    // little more than the noise
+    let plain = Image::filled(90, 70, [200, 200, 200, 255]);
+    let dot = &hard_inputs()[3];
+    let blur = |img: &Image| {
+        let mut plugin = with_rects(&["top-left 10 5 60 50"])
+            .config("style", "blur")
+            .config("noise", "0");
+        redact(&mut plugin, img, Mode::Window)
+    };
+    let (a, b) = (blur(&plain), blur(dot));
+    for (p, q) in a.rgba.chunks_exact(4).zip(b.rgba.chunks_exact(4)) {
+        for c in 0..3 {
+            assert!(p[c].abs_diff(q[c]) <= 2, "{p:?} vs {q:?}");
+        }
+    }
+}
+
+#[test]
+fn a_fill_keeps_nothing_of_the_original() {
+    const AREA: [u32; 4] = [0, 0, 30, 70];
+    for img in hard_inputs() {
+        let mut plugin = with_rects(&["top-left 0 0 30 100%"])
+            .config("style", "fill")
+            .config("fill_color", "[255, 0, 128, 255]");
+        let out = redact(&mut plugin, &img, Mode::Window);
+        for y in AREA[1]..AREA[3] {
+            for x in AREA[0]..AREA[2] {
+                assert_eq!(out.pixel(x, y), [255, 0, 128, 255], "({x},{y})");
+            }
+        }
+        for y in 0..img.height {
+            for x in AREA[2]..img.width {
+                assert_eq!(out.pixel(x, y), img.pixel(x, y));
+            }
+        }
+    }
+    // the default is opaque black
+    let img = Image::synthetic(40, 30);
+    let mut plugin = with_rects(&["top-left 0 0 10 10"]).config("style", "fill");
+    assert_eq!(
+        redact(&mut plugin, &img, Mode::Window).pixel(9, 9),
+        [0, 0, 0, 255]
+    );
+}
+
+#[test]
+fn a_rectangle_can_override_the_style() {
+    let img = Image::synthetic(80, 60);
+    let mut plugin =
+        with_rects(&["top-left 0 0 20 20", "bottom-right 0 0 20 20 fill"]).config("style", "blur");
+    let out = redact(&mut plugin, &img, Mode::Window);
+    for y in 40..60 {
+        for x in 60..80 {
+            assert_eq!(out.pixel(x, y), [0, 0, 0, 255]);
+        }
+    }
+    let blurred = (0..20).flat_map(|y| (0..20).map(move |x| (x, y)));
+    assert!(blurred
+        .clone()
+        .all(|(x, y)| out.pixel(x, y) != img.pixel(x, y)));
+    assert_ne!(out.pixel(0, 0), [0, 0, 0, 255]);
+    assert!(plugin.logs().iter().all(|(l, _)| *l != WARN));
+}
+
+#[test]
+fn invalid_style_settings_warn_and_fall_back() {
+    let img = Image::synthetic(80, 60);
+    let rect = || with_rects(&["top-left 0 0 40 40"]);
+    let pixelated = redact(&mut rect(), &img, Mode::Window);
+    let blurred = redact(&mut rect().config("style", "blur"), &img, Mode::Window);
+    let filled = redact(&mut rect().config("style", "fill"), &img, Mode::Window);
+    for (key, value, style, want) in [
+        ("style", "smudge", "pixelate", &pixelated),
+        ("fill_color", "red", "fill", &filled),
+        ("blur_radius", "2", "blur", &blurred),
+        ("blur_radius", "1000", "blur", &blurred),
+        ("blur_passes", "0", "blur", &blurred),
+        ("blur_passes", "9", "blur", &blurred),
+        ("noise", "200", "blur", &blurred),
+        ("noise", "-1", "blur", &blurred),
+    ] {
+        let mut plugin = rect().config(key, value);
+        if key != "style" {
+            plugin = plugin.config("style", style);
+        }
+        assert_eq!(
+            redact(&mut plugin, &img, Mode::Window),
+            *want,
+            "{key} = {value}"
+        );
+        let warned = plugin.logs().iter().filter(|(l, _)| *l == WARN).count();
+        assert_eq!(warned, 1, "{key} = {value}");
+    }
+}
+
+#[test]
+fn identical_content_does_not_blur_identically() {
+    // the noise is seeded from a capture count and noise_key, never from the
+    // pixels: re-redacting a guess mustn't reproduce the output
+    let img = Image::synthetic(90, 70);
+    let blur = |plugin: &mut Plugin| redact(plugin, &img, Mode::Window);
+    let load = || with_rects(&["top-left 10 5 60 50"]).config("style", "blur");
+    let mut plugin = load();
+    let (first, second) = (blur(&mut plugin), blur(&mut plugin));
+    assert_ne!(first, second);
+    let keyed = |key: &str| blur(&mut load().config("noise_key", key));
+    assert_ne!(keyed("one"), keyed("two"));
+    assert_ne!(keyed("one"), first);
+}
@@ -0,0 +1,157 @@
+//! rounded-corners: transparent anti-aliased corners, nothing else touched,
+//! `only_modes`, and the combinations the README recommends — a border that
+//! hugs the curve and a shadow that shows through the cut corners.
+
+use capscr_plugin_harness::{run_chain, Image, Plugin, Reply};
+use capscr_plugin_sdk::image::Mode;
+use capscr_plugin_sdk::log::WARN;
+
+const FILL: [u8; 4] = [200, 150, 100, 255];
+
+fn rounded(plugin: &mut Plugin, img: &Image, mode: Mode) -> Image {
+    match plugin.on_capture(img, mode) {
+        Reply::InPlace(out) => out,
+        other => panic!("expected an in-place rewrite, got {other:?}"),
+    }
+}
+
+#[test]
+fn corners_are_transparent_and_the_arc_anti_aliased() {
+    let img = Image::filled(60, 40, FILL);
+    let out = rounded(
+        &mut Plugin::load("rounded-corners").config("radius", "10"),
+        &img,
+        Mode::Window,
+    );
+    assert_eq!((out.width, out.height), (60, 40));
+    for (x, y) in [(0, 0), (59, 0), (0, 39), (59, 39), (1, 1), (2, 0)] {
+        assert_eq!(out.pixel(x, y)[3], 0, "corner ({x},{y}) should be cut away");
+    }
+    // the straight edges, the centre and the inside of the arc are untouched
+    for (x, y) in [(10, 0), (0, 10), (30, 0), (30, 20), (59, 20), (5, 5)] {
+        assert_eq!(out.pixel(x, y), FILL, "({x},{y})");
+    }
+    // along the diagonal the arc crosses partially covered pixels
+    assert!((0..10).any(|i| (1..255).contains(&out.pixel(i, i)[3])));
+    // only alpha changes, and the four corners mirror each other
+    assert_eq!(out.pixel(0, 0)[..3], FILL[..3]);
+    for (x, y) in (0..10).flat_map(|x| (0..10).map(move |y| (x, y))) {
+        let a = out.pixel(x, y);
+        assert_eq!(out.pixel(59 - x, y), a, "({x},{y})");
+        assert_eq!(out.pixel(x, 39 - y), a, "({x},{y})");
+        assert_eq!(out.pixel(59 - x, 39 - y), a, "({x},{y})");
+    }
+}
+
+#[test]
+fn existing_alpha_is_scaled_not_replaced() {
+    let img = Image::filled(20, 20, [1, 2, 3, 128]);
+    let out = rounded(&mut Plugin::load("rounded-corners"), &img, Mode::Window);
+    assert_eq!(out.pixel(0, 0)[3], 0);
+    assert_eq!(out.pixel(10, 10), [1, 2, 3, 128]);
+    assert!((0..8).all(|i| out.pixel(i, i)[3] <= 128));
+}
+
+#[test]
+fn radius_is_capped_at_half_the_shorter_side() {
+    let img = Image::filled(30, 10, FILL);
+    let out = rounded(
+        &mut Plugin::load("rounded-corners").config("radius", "500"),
+        &img,
+        Mode::Region,
+    );
+    // a pill: the ends are semicircles of radius 5
+    assert_eq!(out.pixel(0, 0)[3], 0);
+    assert_eq!(out.pixel(0, 9)[3], 0);
+    assert_eq!(out.pixel(15, 0), FILL);
+    assert_eq!(out.pixel(15, 9), FILL);
+}
+
+#[test]
+fn only_modes_limits_which_captures_are_rounded() {
+    let img = Image::filled(20, 20, FILL);
+    let mut plugin = Plugin::load("rounded-corners").config("only_modes", "[\"window\"]");
+    assert_eq!(plugin.on_capture(&img, Mode::FullScreen), Reply::Unchanged);
+    assert_eq!(plugin.on_capture(&img, Mode::Region), Reply::Unchanged);
+    assert_eq!(rounded(&mut plugin, &img, Mode::Window).pixel(0, 0)[3], 0);
+}
+
+#[test]
+fn invalid_values_fall_back_with_a_warning() {
+    let img = Image::filled(20, 20, FILL);
+    let mut plugin = Plugin::load("rounded-corners")
+        .config("radius", "-3")
+        .config("only_modes", "[\"window\", \"desktop\"]");
+    // default radius 8, every mode
+    let out = rounded(&mut plugin, &img, Mode::Region);
+    assert_eq!(out.pixel(0, 0)[3], 0);
+    assert_eq!(out.pixel(8, 0), FILL);
+    let warned: Vec<_> = plugin.logs().iter().filter(|(l, _)| *l == WARN).collect();
+    assert_eq!(warned.len(), 2, "{warned:?}");
+}
+
+#[test]
+fn zero_radius_leaves_the_capture() {
+    let img = Image::synthetic(16, 16);
+    let mut plugin = Plugin::load("rounded-corners").config("radius", "0");
+    assert_eq!(plugin.on_capture(&img, Mode::Window), Reply::Unchanged);
+}
+
+#[test]
+fn a_matching_border_hugs_the_rounded_capture() {
+    let img = Image::filled(40, 30, FILL);
+    let mut chain = [
+        Plugin::load("rounded-corners").config("radius", "6"),
+        Plugin::load("borders")
+            .config("size", "4")
+            .config("corner_radius", "10")
+            .config("color", "[0, 0, 0, 255]"),
+    ];
+    let out = run_chain(&mut chain, &mut [], &img, Mode::Window).unwrap();
+    assert_eq!((out.width, out.height), (48, 38));
+    // every pixel fully inside the outer arc is opaque: no wedge between the
+    // capture's curve and the border's
+    for y in 0..10 {
+        for x in 0..10 {
+            let (dx, dy) = (10.0 - (x as f32 + 0.5), 10.0 - (y as f32 + 0.5));
+            if (dx * dx + dy * dy).sqrt() <= 10.0 - 0.75 {
+                assert_eq!(out.pixel(x, y)[3], 255, "({x},{y})");
+                assert_eq!(out.pixel(47 - x, 37 - y)[3], 255, "({x},{y})");
+            }
+        }
+    }
+    // the capture's own pixels away from the corner are untouched
+    assert_eq!(out.pixel(24, 4), FILL);
+    assert_eq!(out.pixel(4, 19), FILL);
+}
+
+#[test]
+fn the_border_alone_keeps_its_square_inner_edge() {
+    // with no rounded capture inside, the inner-corner fill has nothing to do
+    let img = Image::filled(40, 30, FILL);
+    let out = Plugin::load("borders")
+        .config("size", "4")
+        .config("corner_radius", "10")
+        .on_capture(&img, Mode::Window)
+        .image();
+    assert_eq!(out.pixel(4, 4), FILL);
+}
+
+#[test]
+fn the_shadow_shows_through_the_cut_corners() {
+    let img = Image::filled(40, 30, FILL);
+    let mut chain = [
+        Plugin::load("rounded-corners").config("radius", "10"),
+        Plugin::load("drop-shadow")
+            .config("offset_y", "0")
+            .config("opacity", "1"),
+    ];
+    let out = run_chain(&mut chain, &mut [], &img, Mode::Window).unwrap();
+    let pad = (out.width - 40) / 2;
+    // the capture's corner pixel is gone; what's left is shadow, black and
+    // partly opaque
+    let corner = out.pixel(pad, pad);
+    assert_eq!(corner[..3], [0, 0, 0]);
+    assert!((1..255).contains(&corner[3]), "{corner:?}");
+    assert_eq!(out.pixel(pad + 20, pad + 15), FILL);
+}
@@ -0,0 +1,138 @@
+//! sharpen: overshoot at edges and nothing on flat areas, luma-only changes
+//! (no colour fringes), threshold, amount and min_dimension, and config
+//! handling.
+
+use capscr_plugin_harness::{Image, Plugin, Reply};
+use capscr_plugin_sdk::image::Mode;
+use capscr_plugin_sdk::log::WARN;
+
+fn sharpen(plugin: &mut Plugin, img: &Image) -> Image {
+    match plugin.on_capture(img, Mode::Region) {
+        Reply::InPlace(out) => out,
+        other => panic!("expected an in-place reply, got {other:?}"),
+    }
+}
+
+/// `left` in columns `0..w/2`, `right` in the rest.
+fn step(w: u32, h: u32, left: [u8; 4], right: [u8; 4]) -> Image {
+    let mut rgba = Vec::new();
+    for _ in 0..h {
+        for x in 0..w {
+            rgba.extend_from_slice(if x < w / 2 { &left } else { &right });
+        }
+    }
+    Image::new(w, h, rgba)
+}
+
+#[test]
+fn edges_overshoot_and_flat_areas_stay() {
+    let img = step(40, 20, [100, 100, 100, 255], [150, 150, 150, 255]);
+    let out = sharpen(&mut Plugin::load("sharpen"), &img);
+    // either side of the edge is pushed apart
+    assert!(out.pixel(19, 10)[0] < 100, "{:?}", out.pixel(19, 10));
+    assert!(out.pixel(20, 10)[0] > 150, "{:?}", out.pixel(20, 10));
+    // further than the blur reaches, nothing changes
+    assert_eq!(out.pixel(5, 10), img.pixel(5, 10));
+    assert_eq!(out.pixel(35, 10), img.pixel(35, 10));
+
+    let flat = Image::filled(30, 20, [90, 140, 200, 255]);
+    assert_eq!(sharpen(&mut Plugin::load("sharpen"), &flat), flat);
+}
+
+#[test]
+fn only_luma_changes() {
+    // a red / blue edge of different lightness, every channel clear of 0
+    // and 255 so nothing clamps
+    let img = step(40, 20, [180, 60, 70, 255], [60, 80, 170, 255]);
+    let mut plugin = Plugin::load("sharpen").config("amount", "0.5");
+    let out = sharpen(&mut plugin, &img);
+    assert_ne!(out, img);
+    for (a, b) in img.rgba.chunks_exact(4).zip(out.rgba.chunks_exact(4)) {
+        let d: Vec<i32> = (0..3).map(|c| b[c] as i32 - a[c] as i32).collect();
+        assert!(d[0] == d[1] && d[1] == d[2], "{a:?} -> {b:?}");
+    }
+}
+
+#[test]
+fn alpha_is_never_touched() {
+    let img = Image::synthetic(61, 40);
+    let mut plugin = Plugin::load("sharpen").config("amount", "3");
+    let out = sharpen(&mut plugin, &img);
+    for (a, b) in img.rgba.chunks_exact(4).zip(out.rgba.chunks_exact(4)) {
+        assert_eq!(a[3], b[3]);
+    }
+}
+
+#[test]
+fn threshold_leaves_small_differences_alone() {
+    // a 4-level step: a soft gradient or shadow edge, not text
+    let img = step(40, 20, [100, 100, 100, 255], [104, 104, 104, 255]);
+    let mut plugin = Plugin::load("sharpen").config("threshold", "3");
+    assert_eq!(sharpen(&mut plugin, &img), img);
+    let mut plugin = Plugin::load("sharpen").config("threshold", "0");
+    assert_ne!(sharpen(&mut plugin, &img), img);
+}
+
+#[test]
+fn amount_scales_the_overshoot() {
+    let img = step(40, 20, [100, 100, 100, 255], [150, 150, 150, 255]);
+    let dip = |amount: &str| {
+        let mut plugin = Plugin::load("sharpen").config("amount", amount);
+        100 - sharpen(&mut plugin, &img).pixel(19, 10)[0] as i32
+    };
+    let (one, two) = (dip("1"), dip("2"));
+    assert!(one > 0 && (two - 2 * one).abs() <= 1, "{one} {two}");
+
+    let mut plugin = Plugin::load("sharpen").config("amount", "0");
+    assert_eq!(plugin.on_capture(&img, Mode::Region), Reply::Unchanged);
+}
+
+#[test]
+fn radius_widens_the_halo() {
+    let img = step(60, 20, [100, 100, 100, 255], [150, 150, 150, 255]);
+    let touched = |radius: &str| {
+        let mut plugin = Plugin::load("sharpen").config("radius", radius);
+        let out = sharpen(&mut plugin, &img);
+        (0..60)
+            .filter(|&x| out.pixel(x, 10) != img.pixel(x, 10))
+            .count()
+    };
+    assert!(touched("3") > touched("1"));
+}
+
+#[test]
+fn min_dimension_skips_small_captures() {
+    let img = step(120, 50, [100, 100, 100, 255], [150, 150, 150, 255]);
+    let mut plugin = Plugin::load("sharpen").config("min_dimension", "121");
+    assert_eq!(plugin.on_capture(&img, Mode::Region), Reply::Unchanged);
+    // the longer side counts
+    let mut plugin = Plugin::load("sharpen").config("min_dimension", "120");
+    assert_ne!(sharpen(&mut plugin, &img), img);
+}
+
+#[test]
+fn invalid_settings_warn_and_fall_back() {
+    let img = step(40, 20, [100, 100, 100, 255], [150, 150, 150, 255]);
+    let default = sharpen(&mut Plugin::load("sharpen"), &img);
+    for (key, value) in [
+        ("radius", "0"),
+        ("radius", "50"),
+        ("amount", "-1"),
+        ("amount", "lots"),
+        ("threshold", "300"),
+        ("min_dimension", "20000"),
+    ] {
+        let mut plugin = Plugin::load("sharpen").config(key, value);
+        assert_eq!(sharpen(&mut plugin, &img), default, "{key} = {value}");
+        let warned = plugin.logs().iter().filter(|(l, _)| *l == WARN).count();
+        assert_eq!(warned, 1, "{key} = {value}");
+    }
+}
+
+#[test]
+fn only_modes_skips_other_captures() {
+    let img = step(40, 20, [100, 100, 100, 255], [150, 150, 150, 255]);
+    let mut plugin = Plugin::load("sharpen").config("only_modes", "[\"window\"]");
+    assert_eq!(plugin.on_capture(&img, Mode::Region), Reply::Unchanged);
+    assert_ne!(plugin.on_capture(&img, Mode::Window).image(), img);
+}
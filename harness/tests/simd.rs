@@ -0,0 +1,109 @@
+//! simd128 kernels vs the scalar path: the `simd` builds must produce
+//! bit-identical output, and must really contain simd code.
+
+use capscr_plugin_harness::{build, Image, Plugin, Reply};
+use capscr_plugin_sdk::image::Mode;
+use wasmtime::{Config, Engine, Module};
+
+const PLUGINS: &[&str] = &["grayscale", "downscale", "borders"];
+
+// sizes chosen to hit every tail: widths not a multiple of 4 pixels, blocks
+// not a multiple of 4 pixels wide, single rows/columns
+const SIZES: &[(u32, u32)] = &[(1, 1), (3, 5), (17, 9), (64, 64), (257, 131), (1000, 7)];
+
+fn both(id: &str, max_dim: Option<&str>) -> (Plugin, Plugin) {
+    let mut scalar = Plugin::load(id);
+    let mut simd = Plugin::load_with(id, &["simd"]);
+    if let Some(d) = max_dim {
+        scalar = scalar.config("max_dimension", d);
+        simd = simd.config("max_dimension", d);
+    }
+    (scalar, simd)
+}
+
+fn assert_same(id: &str, scalar: &mut Plugin, simd: &mut Plugin, img: &Image) {
+    let (a, b) = (
+        scalar.on_capture(img, Mode::Region),
+        simd.on_capture(img, Mode::Region),
+    );
+    assert!(
+        a != Reply::Unchanged,
+        "{id} left a {}x{} capture alone",
+        img.width,
+        img.height
+    );
+    assert_eq!(
+        a, b,
+        "{id} simd output differs at {}x{}",
+        img.width, img.height
+    );
+}
+
+#[test]
+fn grayscale_simd_is_bit_identical() {
+    let (mut scalar, mut simd) = both("grayscale", None);
+    for &(w, h) in SIZES {
+        assert_same("grayscale", &mut scalar, &mut simd, &Image::synthetic(w, h));
+    }
+    // every byte value through every channel
+    let all: Vec<u8> = (0..=255u8)
+        .flat_map(|v| [v, 255 - v, v ^ 0x5a, v])
+        .collect();
+    assert_same(
+        "grayscale",
+        &mut scalar,
+        &mut simd,
+        &Image::new(16, 16, all),
+    );
+}
+
+#[test]
+fn downscale_simd_is_bit_identical() {
+    // factors 2, 3, 5 and 8 on a 257-wide capture
+    for max_dim in ["129", "86", "52", "33"] {
+        let (mut scalar, mut simd) = both("downscale", Some(max_dim));
+        for &(w, h) in &[(257, 131), (257, 1), (1, 257), (1000, 40)] {
+            assert_same("downscale", &mut scalar, &mut simd, &Image::synthetic(w, h));
+        }
+    }
+    // factor 2 on widths ≡ 3 mod 4: the last three pixels are a whole
+    // block and half of one, past the last full load
+    for &(w, h) in &[(3u32, 2), (7, 5), (11, 5), (15, 5), (259, 40)] {
+        let max_dim = w.div_ceil(2).to_string();
+        let (mut scalar, mut simd) = both("downscale", Some(&max_dim));
+        assert_same("downscale", &mut scalar, &mut simd, &Image::synthetic(w, h));
+    }
+    // saturated input: the u16 pair sums must not wrap
+    let (mut scalar, mut simd) = both("downscale", Some("100"));
+    assert_same(
+        "downscale",
+        &mut scalar,
+        &mut simd,
+        &Image::filled(800, 800, [255; 4]),
+    );
+}
+
+#[test]
+fn borders_simd_is_bit_identical() {
+    let (mut scalar, mut simd) = both("borders", None);
+    for &(w, h) in SIZES {
+        assert_same("borders", &mut scalar, &mut simd, &Image::synthetic(w, h));
+    }
+}
+
+#[test]
+fn simd_builds_need_simd128() {
+    let mut config = Config::new();
+    config.wasm_relaxed_simd(false).wasm_simd(false);
+    let no_simd = Engine::new(&config).unwrap();
+    for id in PLUGINS {
+        assert!(
+            Module::from_file(&no_simd, build(id, &[])).is_ok(),
+            "{id} scalar build uses simd"
+        );
+        assert!(
+            Module::from_file(&no_simd, build(id, &["simd"])).is_err(),
+            "{id} simd build has no simd128 code"
+        );
+    }
+}
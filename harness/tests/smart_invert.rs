@@ -0,0 +1,188 @@
+//! smart-invert: greys mirrored, hues kept through out-of-gamut colours,
+//! alpha kept, the saturated and photo exemptions, and config handling.
+
+use capscr_plugin_harness::{Image, Plugin, Reply};
+use capscr_plugin_sdk::image::Mode;
+use capscr_plugin_sdk::log::WARN;
+
+const DARK_BG: [u8; 4] = [30, 30, 30, 255];
+
+fn invert(plugin: &mut Plugin, img: &Image) -> Image {
+    match plugin.on_capture(img, Mode::Region) {
+        Reply::InPlace(out) => out,
+        other => panic!("expected an in-place reply, got {other:?}"),
+    }
+}
+
+fn row(pixels: &[[u8; 4]]) -> Image {
+    Image::new(pixels.len() as u32, 1, pixels.concat())
+}
+
+/// a dark UI: flat background with rows of light "text" strokes.
+fn dark_ui(w: u32, h: u32) -> Image {
+    let mut img = Image::filled(w, h, DARK_BG);
+    for (i, px) in img.rgba.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i as u32 % w, i as u32 / w);
+        if y % 10 < 6 && x % 7 < 3 {
+            px.copy_from_slice(&[212, 212, 212, 255]);
+        }
+    }
+    img
+}
+
+/// deterministic noise, as a stand-in for a photo.
+fn noise(img: &mut Image, x0: u32, y0: u32, w: u32, h: u32) {
+    let mut state = 0x2545_f491u32;
+    for y in y0..y0 + h {
+        for x in x0..x0 + w {
+            let i = (y * img.width + x) as usize * 4;
+            for c in 0..3 {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                img.rgba[i + c] = (state >> 24) as u8;
+            }
+        }
+    }
+}
+
+#[test]
+fn greys_mirror_in_lightness() {
+    let img = row(&[
+        [0, 0, 0, 255],
+        [255, 255, 255, 255],
+        [128, 128, 128, 255],
+        DARK_BG,
+    ]);
+    let out = invert(&mut Plugin::load("smart-invert"), &img);
+    assert_eq!(out.pixel(0, 0), [255, 255, 255, 255]);
+    assert_eq!(out.pixel(1, 0), [0, 0, 0, 255]);
+    assert_eq!(out.pixel(2, 0), [72, 72, 72, 255]);
+    assert_eq!(out.pixel(3, 0), [178, 178, 178, 255]);
+}
+
+#[test]
+fn colors_keep_their_hue() {
+    // reference values from the published OKLab matrices; the last three
+    // leave the sRGB gamut at their new lightness and give up chroma
+    let img = row(&[
+        [0, 102, 204, 255],
+        [220, 50, 47, 255],
+        [255, 220, 0, 255],
+        [40, 160, 70, 255],
+    ]);
+    let want = [[0, 90, 181], [145, 0, 11], [5, 4, 0], [0, 81, 26]];
+    let out = invert(&mut Plugin::load("smart-invert"), &img);
+    for (x, rgb) in want.iter().enumerate() {
+        let px = out.pixel(x as u32, 0);
+        for c in 0..3 {
+            assert!(px[c].abs_diff(rgb[c]) <= 1, "#{x}: {px:?} vs {rgb:?}");
+        }
+    }
+    // unlike a plain inversion, the blue stays blue and the red red
+    let blue = out.pixel(0, 0);
+    assert!(blue[2] > blue[1] && blue[1] > blue[0]);
+    let red = out.pixel(1, 0);
+    assert!(red[0] > red[1] && red[0] > red[2]);
+}
+
+#[test]
+fn alpha_is_never_touched() {
+    let img = Image::synthetic(61, 40);
+    let out = invert(&mut Plugin::load("smart-invert"), &img);
+    for (a, b) in img.rgba.chunks_exact(4).zip(out.rgba.chunks_exact(4)) {
+        assert_eq!(a[3], b[3]);
+    }
+}
+
+#[test]
+fn keep_saturated_leaves_vivid_colors_alone() {
+    let img = row(&[
+        [220, 50, 47, 255],
+        [40, 160, 70, 255],
+        DARK_BG,
+        // muted: chroma well under 0.1
+        [40, 45, 55, 255],
+    ]);
+    let mut plugin = Plugin::load("smart-invert").config("keep_saturated", "0.1");
+    let out = invert(&mut plugin, &img);
+    assert_eq!(out.pixel(0, 0), img.pixel(0, 0));
+    assert_eq!(out.pixel(1, 0), img.pixel(1, 0));
+    assert_eq!(out.pixel(2, 0), [178, 178, 178, 255]);
+    assert!(out.pixel(3, 0)[0] > 128);
+}
+
+#[test]
+fn keep_photos_leaves_photographic_areas_alone() {
+    // a 64×48 "photo" in a dark UI, aligned to the 16 px blocks
+    let mut img = dark_ui(160, 96);
+    noise(&mut img, 80, 32, 64, 48);
+    let mut plugin = Plugin::load("smart-invert").config("keep_photos", "true");
+    let out = invert(&mut plugin, &img);
+    for y in 0..96 {
+        for x in 0..160 {
+            let photo = (80..144).contains(&x) && (32..80).contains(&y);
+            let (a, b) = (img.pixel(x, y), out.pixel(x, y));
+            assert_eq!(a == b, photo, "({x},{y}): {a:?} -> {b:?}");
+        }
+    }
+
+    // off by default: everything is inverted
+    let out = invert(&mut Plugin::load("smart-invert"), &img);
+    assert_ne!(out.pixel(100, 50), img.pixel(100, 50));
+}
+
+#[test]
+fn an_isolated_busy_block_is_not_a_photo() {
+    // one block of noise on its own, like an icon
+    let mut img = dark_ui(96, 64);
+    noise(&mut img, 32, 16, 16, 16);
+    let mut plugin = Plugin::load("smart-invert").config("keep_photos", "true");
+    let out = invert(&mut plugin, &img);
+    assert_ne!(out.pixel(40, 24), img.pixel(40, 24));
+}
+
+#[test]
+fn photo_variance_sets_the_threshold() {
+    // faint noise (±16): a photo at a low threshold, UI at the default
+    let mut img = dark_ui(96, 64);
+    noise(&mut img, 0, 0, 96, 64);
+    for px in img.rgba.chunks_exact_mut(4) {
+        for c in &mut px[..3] {
+            *c = 112 + *c / 8;
+        }
+    }
+    let load = |variance: &str| {
+        Plugin::load("smart-invert")
+            .config("keep_photos", "true")
+            .config("photo_variance", variance)
+    };
+    assert_eq!(invert(&mut load("10"), &img), img);
+    let out = invert(&mut load("100"), &img);
+    assert_ne!(out.pixel(50, 30), img.pixel(50, 30));
+}
+
+#[test]
+fn invalid_settings_warn_and_fall_back() {
+    let img = dark_ui(60, 40);
+    let default = invert(&mut Plugin::load("smart-invert"), &img);
+    for (key, value) in [
+        ("keep_saturated", "0.5"),
+        ("keep_saturated", "vivid"),
+        ("keep_photos", "yes"),
+        ("photo_variance", "0"),
+    ] {
+        let mut plugin = Plugin::load("smart-invert").config(key, value);
+        assert_eq!(invert(&mut plugin, &img), default, "{key} = {value}");
+        let warned = plugin.logs().iter().filter(|(l, _)| *l == WARN).count();
+        assert_eq!(warned, 1, "{key} = {value}");
+    }
+}
+
+#[test]
+fn only_modes_skips_other_captures() {
+    let img = dark_ui(40, 30);
+    let mut plugin = Plugin::load("smart-invert").config("only_modes", "[\"window\"]");
+    assert_eq!(plugin.on_capture(&img, Mode::Region), Reply::Unchanged);
+    assert_ne!(plugin.on_capture(&img, Mode::Window).image(), img);
+}
@@ -0,0 +1,276 @@
+//! The tiled on_capture variant: band streaming must produce exactly what the
+//! whole-image hook does, and keep guest memory flat on very tall captures.
+
+use capscr_plugin_harness::{pattern_px, pattern_row, Image, Plugin};
+use capscr_plugin_sdk::image::Mode;
+
+const MIB: usize = 1 << 20;
+
+fn tiled(plugin: &mut Plugin, img: &Image, band_rows: u32) -> Image {
+    let mut rgba = Vec::new();
+    let row = img.width as usize * 4;
+    let (w, h) = plugin
+        .on_capture_tiled(
+            (img.width, img.height),
+            Mode::Region,
+            band_rows,
+            |y, r| r.copy_from_slice(&img.rgba[y as usize * row..][..row]),
+            |rows| rgba.extend_from_slice(rows),
+        )
+        .expect("tiled hook left the capture untouched");
+    Image::new(w, h, rgba)
+}
+
+fn luma(px: [u8; 4]) -> [u8; 4] {
+    let y = ((px[0] as u32 * 299 + px[1] as u32 * 587 + px[2] as u32 * 114) / 1000) as u8;
+    [y, y, y, px[3]]
+}
+
+/// the pre-SDK downscale loop, kept verbatim as the reference.
+fn reference_box(img: &Image, f: u32) -> Image {
+    let (w, h) = (img.width, img.height);
+    let (nw, nh) = (w.div_ceil(f), h.div_ceil(f));
+    let mut out = Vec::new();
+    for oy in 0..nh {
+        for ox in 0..nw {
+            let (mut s, mut n) = ([0u32; 4], 0u32);
+            for y in (oy * f..(oy + 1) * f).take_while(|&y| y < h) {
+                for x in (ox * f..(ox + 1) * f).take_while(|&x| x < w) {
+                    let px = img.pixel(x, y);
+                    for c in 0..4 {
+                        s[c] += px[c] as u32;
+                    }
+                    n += 1;
+                }
+            }
+            out.extend(s.map(|v| (v / n.max(1)) as u8));
+        }
+    }
+    Image::new(nw, nh, out)
+}
+
+#[test]
+fn borders_tiled_matches_whole_image() {
+    let img = Image::synthetic(45, 23);
+    let configs: [&[(&str, &str)]; 3] = [
+        &[],
+        &[("padding", "3"), ("corner_radius", "12")],
+        &[("style", "dotted"), ("size", "5")],
+    ];
+    for config in configs {
+        let load = || {
+            config
+                .iter()
+                .fold(Plugin::load("borders"), |p, (k, v)| p.config(k, v))
+        };
+        let whole = load().on_capture(&img, Mode::Region).image();
+        for band_rows in [1, 5, 23, 64] {
+            assert_eq!(
+                tiled(&mut load(), &img, band_rows),
+                whole,
+                "{config:?} band_rows={band_rows}"
+            );
+        }
+    }
+}
+
+#[test]
+fn grayscale_tiled_matches_whole_image() {
+    let img = Image::synthetic(97, 61);
+    for mode in ["bt601", "lightness", "sepia", "duotone"] {
+        let load = || Plugin::load("grayscale").config("mode", mode);
+        let whole = load().on_capture(&img, Mode::Region).image();
+        for band_rows in [1, 7, 61, 64] {
+            assert_eq!(
+                tiled(&mut load(), &img, band_rows),
+                whole,
+                "{mode} band_rows={band_rows}"
+            );
+        }
+    }
+}
+
+#[test]
+fn rounded_corners_tiled_matches_whole_image() {
+    let img = Image::synthetic(50, 33);
+    let load = || Plugin::load("rounded-corners").config("radius", "12");
+    let whole = load().on_capture(&img, Mode::Region).image();
+    for band_rows in [1, 5, 12, 33, 64] {
+        assert_eq!(
+            tiled(&mut load(), &img, band_rows),
+            whole,
+            "band_rows={band_rows}"
+        );
+    }
+}
+
+#[test]
+fn window_chrome_tiled_matches_whole_image() {
+    let img = Image::synthetic(260, 37);
+    for style in ["macos", "windows"] {
+        let load = || {
+            Plugin::load("window-chrome")
+                .config("style", style)
+                .config("title", "tiled")
+        };
+        let whole = load().on_capture(&img, Mode::Region).image();
+        for band_rows in [1, 6, 37, 64] {
+            assert_eq!(
+                tiled(&mut load(), &img, band_rows),
+                whole,
+                "{style} band_rows={band_rows}"
+            );
+        }
+    }
+}
+
+#[test]
+fn cvd_simulation_tiled_matches_whole_image() {
+    let img = Image::synthetic(97, 61);
+    for side_by_side in ["false", "true"] {
+        let load = || {
+            Plugin::load("cvd-simulation")
+                .config("deficiency", "protanopia")
+                .config("severity", "0.75")
+                .config("side_by_side", side_by_side)
+        };
+        let whole = load().on_capture(&img, Mode::Region).image();
+        for band_rows in [1, 7, 61, 64] {
+            assert_eq!(
+                tiled(&mut load(), &img, band_rows),
+                whole,
+                "side_by_side={side_by_side} band_rows={band_rows}"
+            );
+        }
+    }
+}
+
+#[test]
+fn downscale_tiled_matches_whole_image_and_reference() {
+    let img = Image::synthetic(301, 233);
+    let whole = Plugin::load("downscale")
+        .config("max_dimension", "100")
+        .on_capture(&img, Mode::FullScreen)
+        .image();
+    assert_eq!(whole, reference_box(&img, 4));
+    // band heights that do and don't line up with the 4-row blocks
+    for band_rows in [1, 3, 4, 5, 64, 233] {
+        let mut plugin = Plugin::load("downscale").config("max_dimension", "100");
+        assert_eq!(
+            tiled(&mut plugin, &img, band_rows),
+            whole,
+            "band_rows={band_rows}"
+        );
+    }
+}
+
+#[test]
+fn downscale_filters_tiled_match_whole_image() {
+    let img = Image::synthetic(301, 233);
+    for filter in ["box", "bilinear", "mitchell", "lanczos3"] {
+        let load = || {
+            Plugin::load("downscale")
+                .config("max_dimension", "120")
+                .config("filter", filter)
+        };
+        let whole = load().on_capture(&img, Mode::FullScreen).image();
+        assert_eq!((whole.width, whole.height), (120, 93));
+        for band_rows in [1, 3, 7, 64, 233] {
+            assert_eq!(
+                tiled(&mut load(), &img, band_rows),
+                whole,
+                "{filter} band_rows={band_rows}"
+            );
+        }
+    }
+}
+
+#[test]
+fn downscale_linear_light_tiled_matches_whole_image() {
+    let img = Image::synthetic(301, 233);
+    for filter in [None, Some("mitchell")] {
+        let load = || {
+            let plugin = Plugin::load("downscale")
+                .config("max_dimension", "100")
+                .config("linear_light", "true");
+            match filter {
+                Some(f) => plugin.config("filter", f),
+                None => plugin,
+            }
+        };
+        let whole = load().on_capture(&img, Mode::FullScreen).image();
+        for band_rows in [1, 4, 5, 233] {
+            assert_eq!(
+                tiled(&mut load(), &img, band_rows),
+                whole,
+                "{filter:?} band_rows={band_rows}"
+            );
+        }
+    }
+}
+
+#[test]
+fn tiled_begin_passes_captures_within_the_limit() {
+    let mut plugin = Plugin::load("downscale");
+    let ret = plugin.on_capture_tiled((640, 480), Mode::Window, 64, |_, _| {}, |_| {});
+    assert_eq!(ret, None);
+}
+
+#[test]
+fn grayscale_streams_a_20k_tall_capture_in_flat_memory() {
+    let (w, h) = (512, 20_480); // 40 MiB of rgba
+    let mut plugin = Plugin::load("grayscale");
+    let mut y = 0u32;
+    let size = plugin.on_capture_tiled((w, h), Mode::FullScreen, 256, pattern_row, |rows| {
+        for row in rows.chunks_exact(w as usize * 4) {
+            for (x, px) in row.chunks_exact(4).enumerate() {
+                assert_eq!(px, luma(pattern_px(x as u32, y)), "pixel ({x},{y})");
+            }
+            y += 1;
+        }
+    });
+    assert_eq!(size, Some((w, h)));
+    assert!(
+        plugin.memory_size() < 4 * MIB,
+        "guest memory grew to {} bytes for 512 KiB bands",
+        plugin.memory_size()
+    );
+}
+
+#[test]
+fn downscale_streams_a_20k_tall_capture_in_flat_memory() {
+    let img = Image::synthetic(600, 20_000);
+    let whole = Plugin::load("downscale")
+        .config("max_dimension", "2000")
+        .on_capture(&img, Mode::FullScreen)
+        .image();
+    assert_eq!((whole.width, whole.height), (60, 2000));
+
+    let mut plugin = Plugin::load("downscale").config("max_dimension", "2000");
+    assert_eq!(tiled(&mut plugin, &img, 333), whole);
+    assert!(
+        plugin.memory_size() < 4 * MIB,
+        "guest memory grew to {} bytes streaming a {} byte capture",
+        plugin.memory_size(),
+        img.rgba.len()
+    );
+}
+
+#[test]
+fn downscale_lanczos_streams_a_20k_tall_capture_in_flat_memory() {
+    let (w, h) = (600, 20_000);
+    let mut plugin = Plugin::load("downscale")
+        .config("max_dimension", "2000")
+        .config("filter", "lanczos3");
+    let mut rows = 0;
+    let size = plugin.on_capture_tiled((w, h), Mode::FullScreen, 333, pattern_row, |out| {
+        rows += out.len() / (60 * 4);
+    });
+    assert_eq!(size, Some((60, 2000)));
+    assert_eq!(rows, 2000);
+    assert!(
+        plugin.memory_size() < 4 * MIB,
+        "guest memory grew to {} bytes",
+        plugin.memory_size()
+    );
+}
@@ -0,0 +1,211 @@
+//! window-chrome: frame geometry for each style, the drawn controls, the
+//! caption font, themes and the rounded outer corners.
+
+use capscr_plugin_harness::{Image, Plugin, Reply};
+use capscr_plugin_sdk::image::Mode;
+use capscr_plugin_sdk::log::WARN;
+
+const LIGHT_BAR: [u8; 4] = [246, 246, 246, 255];
+const LIGHT_FRAME: [u8; 4] = [208, 208, 208, 255];
+const LIGHT_TEXT: [u8; 4] = [77, 77, 77, 255];
+const DARK_BAR: [u8; 4] = [42, 42, 42, 255];
+
+/// the synthetic pattern with every pixel made opaque.
+fn opaque(w: u32, h: u32) -> Image {
+    let mut img = Image::synthetic(w, h);
+    img.rgba.chunks_exact_mut(4).for_each(|px| px[3] = 255);
+    img
+}
+
+fn framed(plugin: &mut Plugin, img: &Image) -> Image {
+    match plugin.on_capture(img, Mode::Region) {
+        Reply::Replaced(out) => out,
+        other => panic!("expected a replacement, got {other:?}"),
+    }
+}
+
+#[test]
+fn each_style_adds_its_bar_and_a_frame() {
+    let img = opaque(300, 100);
+    for (style, title, bar) in [
+        ("macos", "", 28),
+        ("windows", "", 32),
+        ("minimal", "", 12),
+        ("minimal", "notes", 20),
+    ] {
+        let out = framed(
+            &mut Plugin::load("window-chrome")
+                .config("style", style)
+                .config("title", title),
+            &img,
+        );
+        assert_eq!((out.width, out.height), (302, 100 + bar + 2), "{style}");
+        for y in 0..100 {
+            for x in 0..300 {
+                if !(10..290).contains(&x) && y >= 90 {
+                    continue; // under the rounded bottom corners
+                }
+                assert_eq!(
+                    out.pixel(x + 1, y + 1 + bar),
+                    img.pixel(x, y),
+                    "{style} ({x},{y})"
+                );
+            }
+        }
+        // frame down the sides and along the bottom
+        assert_eq!(out.pixel(0, bar + 50), LIGHT_FRAME, "{style}");
+        assert_eq!(out.pixel(301, bar + 50), LIGHT_FRAME, "{style}");
+        assert_eq!(out.pixel(150, bar + 101), LIGHT_FRAME, "{style}");
+    }
+}
+
+#[test]
+fn outer_corners_are_rounded_and_transparent() {
+    let img = opaque(100, 60);
+    let out = framed(&mut Plugin::load("window-chrome"), &img);
+    let (w, h) = (out.width - 1, out.height - 1);
+    for (x, y) in [(0, 0), (w, 0), (0, h), (w, h), (1, 1)] {
+        assert_eq!(out.pixel(x, y)[3], 0, "({x},{y})");
+    }
+    assert!((0..10).any(|i| (1..255).contains(&out.pixel(i, i)[3])));
+    assert_eq!(out.pixel(50, 0), LIGHT_FRAME);
+
+    let square = framed(
+        &mut Plugin::load("window-chrome").config("corner_radius", "0"),
+        &img,
+    );
+    assert_eq!(square.pixel(0, 0), LIGHT_FRAME);
+}
+
+#[test]
+fn macos_draws_traffic_lights_and_a_separator() {
+    let out = framed(&mut Plugin::load("window-chrome"), &opaque(200, 50));
+    // lights centred 14, 34 and 54 px in, half-way down the 28 px bar
+    assert_eq!(out.pixel(15, 15), [255, 95, 87, 255]);
+    assert_eq!(out.pixel(35, 15), [254, 188, 46, 255]);
+    assert_eq!(out.pixel(55, 15), [40, 200, 64, 255]);
+    // anti-aliased rims, plain bar between and beyond them
+    assert!(
+        (96..246).contains(&out.pixel(9, 12)[1]),
+        "{:?}",
+        out.pixel(9, 12)
+    );
+    assert_eq!(out.pixel(25, 15), LIGHT_BAR);
+    assert_eq!(out.pixel(150, 15), LIGHT_BAR);
+    // the last bar row separates it from the content
+    assert_eq!(out.pixel(100, 28), LIGHT_FRAME);
+}
+
+#[test]
+fn windows_draws_caption_buttons_on_the_right() {
+    let out = framed(
+        &mut Plugin::load("window-chrome").config("style", "windows"),
+        &opaque(300, 50),
+    );
+    // 46 px cells ending at the right frame: close, maximise, minimise;
+    // the bar row centre is y = 1 + 16
+    let close = 301 - 23;
+    let maximise = close - 46;
+    let minimise = maximise - 46;
+    assert_eq!(out.pixel(close, 17), LIGHT_TEXT, "the X crosses the centre");
+    assert_eq!(out.pixel(close - 3, 14), LIGHT_TEXT, "and runs diagonally");
+    assert_eq!(out.pixel(close, 13), LIGHT_BAR);
+    assert_eq!(out.pixel(maximise, 17), LIGHT_BAR, "the square is hollow");
+    assert_eq!(out.pixel(maximise - 5, 17), LIGHT_TEXT, "its 1 px outline");
+    assert_eq!(out.pixel(minimise, 16), LIGHT_TEXT);
+    assert_eq!(out.pixel(minimise, 13), LIGHT_BAR);
+    assert_eq!(out.pixel(50, 17), LIGHT_BAR);
+}
+
+#[test]
+fn caption_is_drawn_in_the_built_in_font() {
+    // one "I" in a minimal 20 px bar: a 5×7 glyph at font size 1, centred
+    let out = framed(
+        &mut Plugin::load("window-chrome")
+            .config("style", "minimal")
+            .config("title", "I")
+            .config("corner_radius", "0"),
+        &opaque(41, 10),
+    );
+    let (x0, y0) = ((43 - 5) / 2, 1 + (20 - 7) / 2);
+    let glyph = [
+        ".###.", "..#..", "..#..", "..#..", "..#..", "..#..", ".###.",
+    ];
+    for (y, row) in glyph.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            let want = if c == '#' { LIGHT_TEXT } else { LIGHT_BAR };
+            assert_eq!(out.pixel(x0 + x as u32, y0 + y as u32), want, "({x},{y})");
+        }
+    }
+}
+
+#[test]
+fn long_captions_stop_short_of_the_controls() {
+    let title = "a very long window caption that cannot possibly fit ".repeat(4);
+    let out = framed(
+        &mut Plugin::load("window-chrome").config("title", &title),
+        &opaque(300, 20),
+    );
+    // text pixels only between the lights' reserved 74 px on either side
+    let lit: Vec<u32> = (1..28)
+        .flat_map(|y| (0..302).map(move |x| (x, y)))
+        .filter(|&(x, y)| out.pixel(x, y) == LIGHT_TEXT)
+        .map(|(x, _)| x)
+        .collect();
+    assert!(!lit.is_empty());
+    assert!(lit.iter().all(|&x| (74..302 - 74).contains(&x)), "{lit:?}");
+}
+
+#[test]
+fn dark_theme_and_scale() {
+    let out = framed(
+        &mut Plugin::load("window-chrome")
+            .config("theme", "dark")
+            .config("scale", "2"),
+        &opaque(300, 40),
+    );
+    // everything doubles: 2 px frame, 56 px bar, lights 24 px wide 28 px in
+    assert_eq!((out.width, out.height), (304, 40 + 56 + 4));
+    assert_eq!(out.pixel(150, 20), DARK_BAR);
+    assert_eq!(out.pixel(30, 30), [255, 95, 87, 255]);
+    assert_eq!(out.pixel(30 + 40, 30), [254, 188, 46, 255]);
+}
+
+#[test]
+fn only_modes_limits_which_captures_are_framed() {
+    let img = opaque(30, 30);
+    let mut plugin = Plugin::load("window-chrome").config("only_modes", "[\"region\"]");
+    assert_eq!(plugin.on_capture(&img, Mode::Window), Reply::Unchanged);
+    assert!(matches!(
+        plugin.on_capture(&img, Mode::Region),
+        Reply::Replaced(_)
+    ));
+}
+
+#[test]
+fn invalid_values_fall_back_with_a_warning() {
+    let img = opaque(100, 30);
+    let mut plugin = Plugin::load("window-chrome")
+        .config("style", "beos")
+        .config("theme", "sepia")
+        .config("scale", "9")
+        .config("corner_radius", "-1");
+    let out = framed(&mut plugin, &img);
+    assert_eq!((out.width, out.height), (102, 60));
+    let warned: Vec<_> = plugin.logs().iter().filter(|(l, _)| *l == WARN).collect();
+    assert_eq!(warned.len(), 4, "{warned:?}");
+}
+
+#[test]
+fn oversized_output_is_skipped() {
+    // 28 px bar and a 1 px frame above and below: 16354 rows fit exactly
+    let mut plugin = Plugin::load("window-chrome");
+    assert!(matches!(
+        plugin.on_capture(&Image::filled(10, 16354, LIGHT_BAR), Mode::Region),
+        Reply::Replaced(_)
+    ));
+    assert_eq!(
+        plugin.on_capture(&Image::filled(10, 16355, LIGHT_BAR), Mode::Region),
+        Reply::Unchanged
+    );
+}
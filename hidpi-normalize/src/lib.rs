@@ -0,0 +1,200 @@
+//! capscr plugin: undo integer pixel duplication from HiDPI displays.
+//!
+//! Content that was itself upscaled before a 200%-scaled display showed it
+//! arrives as exact k×k (or k×1, 1×k) blocks of identical pixels. By default
+//! the plugin detects that per axis — the largest factor up to `max_factor`
+//! whose every block is uniform, at whatever offset the blocks start — and
+//! keeps one pixel per block, which loses nothing. With an explicit `scale`
+//! (0.5, 1/3, …) it box-averages down by that factor instead, duplicated or
+//! not: DPI normalization rather than a size limit like `downscale`'s.
+//!
+//! Detection has to see the whole capture before it knows the output size,
+//! so it's a whole-image [`Filter`] with a [`Filter::scan`] pass.
+
+use capscr_plugin_sdk::config::{unquote, Config};
+use capscr_plugin_sdk::filter::Filter;
+use capscr_plugin_sdk::image::Header;
+use capscr_plugin_sdk::log;
+
+const DEFAULT_MAX_FACTOR: u32 = 4;
+const MAX_FACTOR: u32 = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scale {
+    /// find the duplication factor in the pixels
+    Auto,
+    /// box-average by this integer factor on both axes
+    Fixed(u32),
+}
+
+/// `"auto"`, or a scale of 1/k for a whole k in 2..=8, within rounding
+/// (`0.5`, `0.333`, `0.25`).
+fn parse_scale(s: &str) -> Option<Scale> {
+    let s = unquote(s);
+    if s.eq_ignore_ascii_case("auto") {
+        return Some(Scale::Auto);
+    }
+    let scale = s.parse::<f32>().ok().filter(|v| *v > 0.0)?;
+    let k = (1.0 / scale).round();
+    let exact = (1.0 / scale - k).abs() < 0.02;
+    (exact && (2.0..=MAX_FACTOR as f32).contains(&k)).then_some(Scale::Fixed(k as u32))
+}
+
+capscr_plugin_sdk::export_filter!(HidpiNormalize);
+
+pub struct HidpiNormalize {
+    width: u32,
+    height: u32,
+    scale: Scale,
+    max_factor: u32,
+    // Auto: the source column and row of each output pixel, once scanned
+    columns: Vec<u32>,
+    rows: Vec<u32>,
+}
+
+impl Filter for HidpiNormalize {
+    fn begin(header: &Header, config: &dyn Config) -> Option<Self> {
+        if let Some(modes) = config.modes("only_modes") {
+            if !modes.contains(&header.mode) {
+                return None;
+            }
+        }
+        let scale = config.read("scale", Scale::Auto, parse_scale);
+        let max_factor = config.u32("max_factor", DEFAULT_MAX_FACTOR, 2..=MAX_FACTOR);
+        Some(HidpiNormalize {
+            width: header.width,
+            height: header.height,
+            scale,
+            max_factor,
+            columns: Vec::new(),
+            rows: Vec::new(),
+        })
+    }
+
+    fn scan(&mut self, rgba: &[u8]) -> bool {
+        let (w, h) = (self.width as usize, self.height as usize);
+        if self.scale != Scale::Auto {
+            return w > 1 || h > 1;
+        }
+        let (same_columns, same_rows) = neighbours(rgba, w, h);
+        let across = blocks(&same_columns, self.max_factor);
+        let down = blocks(&same_rows, self.max_factor);
+        if across.is_none() && down.is_none() {
+            return false;
+        }
+        self.columns = keep(w, across);
+        self.rows = keep(h, down);
+        let factor = |b: Option<(usize, usize)>| b.map_or(1, |(k, _)| k);
+        log::info(&format!(
+            "hidpi-normalize: {}×{} pixel duplication, {w}×{h} -> {}×{}",
+            factor(across),
+            factor(down),
+            self.columns.len(),
+            self.rows.len()
+        ));
+        true
+    }
+
+    fn output_size(&self) -> (u32, u32) {
+        match self.scale {
+            Scale::Auto => (self.columns.len() as u32, self.rows.len() as u32),
+            Scale::Fixed(k) => (self.width.div_ceil(k), self.height.div_ceil(k)),
+        }
+    }
+
+    fn render(&mut self, rgba: &[u8], out: &mut Vec<u8>) {
+        let w = self.width as usize;
+        match self.scale {
+            Scale::Auto => {
+                for &y in &self.rows {
+                    let row = &rgba[y as usize * w * 4..][..w * 4];
+                    for &x in &self.columns {
+                        out.extend_from_slice(&row[x as usize * 4..][..4]);
+                    }
+                }
+            }
+            Scale::Fixed(k) => box_average(rgba, w, k as usize, out),
+        }
+    }
+}
+
+/// for every column (row) index i > 0, whether it's identical to i - 1 all
+/// the way down (across). Index 0 is false.
+fn neighbours(rgba: &[u8], w: usize, h: usize) -> (Vec<bool>, Vec<bool>) {
+    let row = |y: usize| &rgba[y * w * 4..][..w * 4];
+    let same_rows: Vec<bool> = (0..h).map(|y| y > 0 && row(y) == row(y - 1)).collect();
+    let mut same_columns = vec![true; w];
+    same_columns[0] = false;
+    let mut left = w - 1;
+    // a duplicate row can't rule anything out that its twin didn't
+    for y in (0..h).filter(|&y| !same_rows[y]) {
+        let px = row(y);
+        for (x, same) in same_columns.iter_mut().enumerate().skip(1) {
+            if *same && px[x * 4..][..4] != px[(x - 1) * 4..][..4] {
+                *same = false;
+                left -= 1;
+            }
+        }
+        if left == 0 {
+            break; // no column matches its neighbour; nothing left to check
+        }
+    }
+    (same_columns, same_rows)
+}
+
+/// the largest block size k ≤ `max_factor`, and its offset p, such that
+/// every index not starting a block (i % k != p) repeats its predecessor —
+/// and some block differs from the one before, so a flat axis isn't
+/// "duplicated" by every k.
+fn blocks(same: &[bool], max_factor: u32) -> Option<(usize, usize)> {
+    let n = same.len();
+    (2..=max_factor as usize)
+        .rev()
+        .filter(|&k| n >= 2 * k)
+        .find_map(|k| {
+            (0..k).find_map(|p| {
+                let mut varied = false;
+                for (i, &s) in same.iter().enumerate().skip(1) {
+                    if i % k != p && !s {
+                        return None;
+                    }
+                    varied |= i % k == p && !s;
+                }
+                varied.then_some((k, p))
+            })
+        })
+}
+
+/// the index kept from each block: 0 and every block start.
+fn keep(n: usize, blocks: Option<(usize, usize)>) -> Vec<u32> {
+    let (k, p) = blocks.unwrap_or((1, 0));
+    (0..n as u32)
+        .filter(|&i| i == 0 || i as usize % k == p)
+        .collect()
+}
+
+/// average every k×k block (clamped at the right and bottom edges) into one
+/// pixel.
+fn box_average(rgba: &[u8], w: usize, k: usize, out: &mut Vec<u8>) {
+    let nw = w.div_ceil(k);
+    let mut sums = vec![0u32; nw * 4];
+    for band in rgba.chunks(w * 4 * k) {
+        let rows = band.len() / (w * 4);
+        for row in band.chunks_exact(w * 4) {
+            for (block, sum) in row.chunks(k * 4).zip(sums.chunks_exact_mut(4)) {
+                for px in block.chunks_exact(4) {
+                    for (s, &c) in sum.iter_mut().zip(px) {
+                        *s += c as u32;
+                    }
+                }
+            }
+        }
+        for (bx, sum) in sums.chunks_exact_mut(4).enumerate() {
+            let n = (k.min(w - bx * k) * rows) as u32;
+            for s in sum.iter_mut() {
+                out.push(((*s + n / 2) / n) as u8);
+                *s = 0;
+            }
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! capscr plugin: pad each capture out to a fixed aspect ratio, never crop.
+//!
+//! The canvas grows along one axis until it matches `aspect`; the capture is
+//! placed at the configured alignment and the bars are filled with a solid
+//! colour, transparency, or the capture's own edges extended outwards and
+//! Gaussian blurred (the "blurred background" look of vertical video).
+//!
+//! A blurred bar only varies near the seam: past the blur's reach every tap
+//! reads the clamped edge pixel, so each bar is computed as `reach + 1`
+//! distinct columns however wide it is.
+
+use capscr_plugin_sdk::blur::{self, Edge};
+use capscr_plugin_sdk::config::Config;
+use capscr_plugin_sdk::filter::Filter;
+use capscr_plugin_sdk::image::Header;
+
+const DEFAULT_ASPECT: (u32, u32) = (16, 9);
+const DEFAULT_COLOR: [u8; 4] = [0, 0, 0, 255];
+const DEFAULT_BLUR: u32 = 32; // CSS-style blur length, px (σ = blur / 2)
+const MAX_BLUR: u32 = 64;
+const TRANSPARENT: [u8; 4] = [0; 4];
+const MAX_DIM: u32 = 16384; // host rejects larger replacements; bail to match
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Fill {
+    Solid,
+    /// the capture's edges extended outwards and blurred
+    Blur,
+    Transparent,
+}
+
+const FILLS: &[(&str, Fill)] = &[
+    ("solid", Fill::Solid),
+    ("blur", Fill::Blur),
+    ("transparent", Fill::Transparent),
+];
+
+/// where the capture sits along the padded axis.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+}
+
+// top/left and bottom/right name the same thing for whichever axis is padded
+const ALIGNS: &[(&str, Align)] = &[
+    ("center", Align::Center),
+    ("start", Align::Start),
+    ("top", Align::Start),
+    ("left", Align::Start),
+    ("end", Align::End),
+    ("bottom", Align::End),
+    ("right", Align::End),
+];
+
+capscr_plugin_sdk::export_filter!(Letterbox);
+
+pub struct Letterbox {
+    width: u32,
+    height: u32,
+    // where the capture's top-left lands on the canvas
+    left: u32,
+    top: u32,
+    fill: Fill,
+    color: [u8; 4],
+    kernel: Vec<f32>,
+    out_width: u32,
+    out_height: u32,
+}
+
+impl Filter for Letterbox {
+    fn begin(header: &Header, config: &dyn Config) -> Option<Self> {
+        let (w, h) = (header.width, header.height);
+        if let Some(modes) = config.modes("only_modes") {
+            if !modes.contains(&header.mode) {
+                return None;
+            }
+        }
+        let (aw, ah) = config.aspect("aspect", Some(DEFAULT_ASPECT))?;
+        let fill = config.choice("fill", Fill::Solid, FILLS);
+        let color = config.color("color", DEFAULT_COLOR);
+        let align = config.choice("align", Align::Center, ALIGNS);
+        let blur = config.u32("blur", DEFAULT_BLUR, 0..=MAX_BLUR);
+
+        // widen or heighten the canvas, never crop it
+        let (aw, ah) = (aw as u64, ah as u64);
+        let (mut nw, mut nh) = (w as u64, h as u64);
+        if nw * ah < nh * aw {
+            nw = (nh * aw).div_ceil(ah);
+        } else {
+            nh = (nw * ah).div_ceil(aw);
+        }
+        if (nw, nh) == (w as u64, h as u64) {
+            return None; // already the right shape
+        }
+        if nw > MAX_DIM as u64 || nh > MAX_DIM as u64 {
+            return None; // the host would reject an oversized replacement anyway
+        }
+        let (nw, nh) = (nw as u32, nh as u32);
+        let place = |slack: u32| match align {
+            Align::Start => 0,
+            Align::Center => slack / 2,
+            Align::End => slack,
+        };
+        Some(Letterbox {
+            width: w,
+            height: h,
+            left: place(nw - w),
+            top: place(nh - h),
+            fill,
+            color: match fill {
+                Fill::Transparent => TRANSPARENT,
+                _ => color,
+            },
+            kernel: blur::kernel(blur::sigma_for_radius(blur as f32)),
+            out_width: nw,
+            out_height: nh,
+        })
+    }
+
+    fn output_size(&self) -> (u32, u32) {
+        (self.out_width, self.out_height)
+    }
+
+    fn render(&mut self, rgba: &[u8], out: &mut Vec<u8>) {
+        let (w, h) = (self.width as usize, self.height as usize);
+        let (nw, nh) = (self.out_width as usize, self.out_height as usize);
+        let (left, top) = (self.left as usize, self.top as usize);
+        let row_bytes = w * 4;
+        let pixel =
+            |x: usize, y: usize| -> [u8; 4] { rgba[(y * w + x) * 4..][..4].try_into().unwrap() };
+
+        out.reserve(nw * nh * 4);
+        if self.fill != Fill::Blur {
+            // one canvas row of bar, copied from rather than filled pixel by pixel
+            let bar = self.color.repeat(nw);
+            for y in 0..nh {
+                let cy = y.wrapping_sub(top);
+                if cy >= h {
+                    out.extend_from_slice(&bar);
+                    continue;
+                }
+                out.extend_from_slice(&bar[..left * 4]);
+                out.extend_from_slice(&rgba[cy * row_bytes..][..row_bytes]);
+                out.extend_from_slice(&bar[(left + w) * 4..]);
+            }
+            return;
+        }
+
+        let k = &self.kernel;
+        if nw > w {
+            // pillarbox: bars left and right, seams run down the capture
+            let before = Bar::new(h, w, pixel, k);
+            let after = Bar::new(h, w, |u, v| pixel(w - 1 - u, v), k);
+            for y in 0..nh {
+                out.extend((0..left).flat_map(|x| before.at(left - 1 - x, y)));
+                out.extend_from_slice(&rgba[y * row_bytes..][..row_bytes]);
+                out.extend((left + w..nw).flat_map(|x| after.at(x - left - w, y)));
+            }
+        } else {
+            // letterbox: bars above and below, seams run across the capture
+            let above = Bar::new(w, h, |u, v| pixel(v, u), k);
+            let below = Bar::new(w, h, |u, v| pixel(v, h - 1 - u), k);
+            for y in 0..nh {
+                let cy = y.wrapping_sub(top);
+                if y < top {
+                    out.extend_from_slice(above.line(top - 1 - y));
+                } else if cy < h {
+                    out.extend_from_slice(&rgba[cy * row_bytes..][..row_bytes]);
+                } else {
+                    out.extend_from_slice(below.line(cy - h));
+                }
+            }
+        }
+    }
+}
+
+/// a blurred edge-extension bar: the capture clamp-extended past one edge and
+/// Gaussian blurred, as `reach + 1` columns of `len` pixels — column `d` is
+/// `d` px out from the seam, and every column past `reach` equals the last.
+struct Bar {
+    len: usize,
+    // rgba, one `len`-pixel line per distance from the seam
+    columns: Vec<u8>,
+}
+
+impl Bar {
+    /// `src(u, v)`: the capture pixel `u` px in from the edge, `v` along it;
+    /// `depth` is how far in the capture goes.
+    fn new(len: usize, depth: usize, src: impl Fn(usize, usize) -> [u8; 4], kernel: &[f32]) -> Bar {
+        let r = kernel.len() / 2;
+        let levels = r + 1;
+        // premultiplied a, r·a, g·a, b·a planes per level, along the seam
+        let mut planes = vec![vec![0.0f32; len]; levels * 4];
+        // across the seam, level d's taps centre on u = -(d + 1); every tap
+        // at u <= 0 reads the edge pixel, so those fold into one weight
+        let edge: Vec<f32> = (0..levels)
+            .map(|d| kernel[..(r + d + 2).min(kernel.len())].iter().sum())
+            .collect();
+        let mut col = vec![[0.0f32; 4]; levels];
+        for v in 0..len {
+            for (u, c) in col.iter_mut().enumerate() {
+                let [pr, pg, pb, pa] = src(u.min(depth - 1), v);
+                let a = pa as f32;
+                *c = [a, pr as f32 * a, pg as f32 * a, pb as f32 * a];
+            }
+            for (d, level) in planes.chunks_exact_mut(4).enumerate() {
+                let mut acc = col[0].map(|c| c * edge[d]);
+                for u in 1..r.saturating_sub(d) {
+                    let t = kernel[u + r + d + 1];
+                    for (a, c) in acc.iter_mut().zip(col[u]) {
+                        *a += t * c;
+                    }
+                }
+                for (plane, value) in level.iter_mut().zip(acc) {
+                    plane[v] = value;
+                }
+            }
+        }
+        let mut blurred = vec![0.0; len];
+        for plane in planes.iter_mut() {
+            blur::blur_line(plane, &mut blurred, kernel, Edge::Clamp);
+            plane.copy_from_slice(&blurred);
+        }
+        let mut columns = Vec::with_capacity(levels * len * 4);
+        for d in 0..levels {
+            let [a, r, g, b] = [0, 1, 2, 3].map(|c| &planes[d * 4 + c]);
+            columns.extend((0..len).flat_map(|v| {
+                if a[v] < 0.5 {
+                    return TRANSPARENT;
+                }
+                let un = |x: f32| (x / a[v] + 0.5).min(255.0) as u8;
+                [un(r[v]), un(g[v]), un(b[v]), (a[v] + 0.5).min(255.0) as u8]
+            }));
+        }
+        Bar { len, columns }
+    }
+
+    /// the line of pixels `d` px out from the seam (0 = touching it).
+    fn line(&self, d: usize) -> &[u8] {
+        let levels = self.columns.len() / (self.len * 4);
+        &self.columns[d.min(levels - 1) * self.len * 4..][..self.len * 4]
+    }
+
+    /// the pixel `d` px out from the seam, `v` along it.
+    fn at(&self, d: usize, v: usize) -> [u8; 4] {
+        self.line(d)[v * 4..][..4].try_into().unwrap()
+    }
+}
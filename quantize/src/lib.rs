@@ -0,0 +1,143 @@
+//! capscr plugin: reduce captures to a palette of at most N colours.
+//!
+//! UI screenshots use few colours, and an encoder that sees at most 256 of
+//! them can write a far smaller file. A capture that already has no more
+//! than `colors` distinct colours is left exactly as it is. Otherwise the
+//! palette comes from median cut (see [`palette`]) and every pixel is mapped
+//! to its nearest entry — directly, or with Floyd–Steinberg or ordered
+//! dithering for gradients and photos.
+//!
+//! Choosing a palette needs every pixel before the first is rewritten, so
+//! there's no tiled variant; the rewrite happens in place, so there's no
+//! second image-sized buffer either (capscr 0.6+).
+
+pub mod palette;
+
+use capscr_plugin_sdk::config::Config;
+use capscr_plugin_sdk::image::Header;
+use capscr_plugin_sdk::inplace::InPlaceFilter;
+
+use palette::Nearest;
+
+const DEFAULT_COLORS: u32 = 256;
+const MAX_COLORS: u32 = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dither {
+    None,
+    /// error diffusion, serpentine
+    FloydSteinberg,
+    /// 8×8 Bayer threshold map
+    Ordered,
+}
+
+const DITHERS: &[(&str, Dither)] = &[
+    ("none", Dither::None),
+    ("floyd-steinberg", Dither::FloydSteinberg),
+    ("ordered", Dither::Ordered),
+];
+
+const BAYER: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+capscr_plugin_sdk::export_in_place_filter!(Quantize);
+
+pub struct Quantize {
+    width: usize,
+    colors: usize,
+    dither: Dither,
+}
+
+impl InPlaceFilter for Quantize {
+    fn begin(header: &Header, config: &dyn Config) -> Option<Self> {
+        if let Some(modes) = config.modes("only_modes") {
+            if !modes.contains(&header.mode) {
+                return None;
+            }
+        }
+        Some(Quantize {
+            width: header.width as usize,
+            colors: config.u32("colors", DEFAULT_COLORS, 2..=MAX_COLORS) as usize,
+            dither: config.choice("dither", Dither::None, DITHERS),
+        })
+    }
+
+    fn rows(&mut self, _y: u32, rgba: &mut [u8]) {
+        if palette::fits(rgba, self.colors) {
+            return; // already within the palette: nothing to lose
+        }
+        let colors = palette::median_cut(rgba, self.colors);
+        let mut nearest = Nearest::new(&colors);
+        match self.dither {
+            Dither::None => {
+                for px in rgba.chunks_exact_mut(4) {
+                    let i = nearest.index([px[0], px[1], px[2], px[3]]);
+                    px.copy_from_slice(&colors[i]);
+                }
+            }
+            Dither::FloydSteinberg => floyd_steinberg(rgba, self.width, &colors, &mut nearest),
+            Dither::Ordered => ordered(rgba, self.width, &colors, &mut nearest),
+        }
+    }
+}
+
+/// map each pixel to the palette, pushing its r,g,b error on to the pixels
+/// not yet visited (7/16 ahead, 3/16, 5/16 and 1/16 on the row below). Rows
+/// alternate direction so the error doesn't drift one way. Alpha isn't
+/// diffused — it's matched as is.
+fn floyd_steinberg(rgba: &mut [u8], w: usize, colors: &[[u8; 4]], nearest: &mut Nearest) {
+    // error in sixteenths from the row above, and for the row below, one
+    // pixel of slack either side so the edges need no special case
+    let mut here = vec![[0i32; 3]; w + 2];
+    let mut below = vec![[0i32; 3]; w + 2];
+    for (y, row) in rgba.chunks_exact_mut(w * 4).enumerate() {
+        let forward = y % 2 == 0;
+        // the 7/16 passed on from the previous pixel
+        let mut ahead = [0i32; 3];
+        for step in 0..w {
+            let x = if forward { step } else { w - 1 - step };
+            let px = &mut row[x * 4..][..4];
+            let err = here[x + 1];
+            let mut want = [0u8; 4];
+            for c in 0..3 {
+                want[c] = (px[c] as i32 + ((err[c] + ahead[c] + 8) >> 4)).clamp(0, 255) as u8;
+            }
+            want[3] = px[3];
+            let got = colors[nearest.index(want)];
+            px.copy_from_slice(&got);
+            let (next, prev) = if forward { (x + 2, x) } else { (x, x + 2) };
+            for c in 0..3 {
+                let e = want[c] as i32 - got[c] as i32;
+                ahead[c] = 7 * e;
+                below[prev][c] += 3 * e;
+                below[x + 1][c] += 5 * e;
+                below[next][c] += e;
+            }
+        }
+        core::mem::swap(&mut here, &mut below);
+        below.fill([0; 3]);
+    }
+}
+
+/// nudge each pixel by its place in the Bayer map before matching it, by up
+/// to about half the palette's typical spacing either way.
+fn ordered(rgba: &mut [u8], w: usize, colors: &[[u8; 4]], nearest: &mut Nearest) {
+    let spread = (256.0 / (colors.len() as f32).cbrt()) as i32;
+    for (y, row) in rgba.chunks_exact_mut(w * 4).enumerate() {
+        for (x, px) in row.chunks_exact_mut(4).enumerate() {
+            // -63..=63 in steps of 2, over 128
+            let t = (2 * BAYER[y % 8][x % 8] as i32 - 63) * spread / 128;
+            let c = |v: u8| (v as i32 + t).clamp(0, 255) as u8;
+            let i = nearest.index([c(px[0]), c(px[1]), c(px[2]), px[3]]);
+            px.copy_from_slice(&colors[i]);
+        }
+    }
+}
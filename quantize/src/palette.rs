@@ -0,0 +1,296 @@
+//! Choosing the palette (median cut) and finding the nearest entry in it.
+//!
+//! Both work over a histogram of 5-bit-per-channel RGBA cells — 2^20 of
+//! them, a fixed 4 MB whatever the capture size. Median cut splits the
+//! occupied cells into boxes; each palette colour is then the exact mean of
+//! the pixels in its box, not of the cells, so a flat colour that gets a box
+//! to itself comes back unchanged.
+
+use core::ops::Range;
+
+const BITS: u32 = 5;
+const CELLS: usize = 1 << (4 * BITS);
+// Nearest's first narrowing step
+const COARSE_BITS: u32 = BITS - 1;
+// bit offset of each channel's 5 bits in a cell index
+const SHIFTS: [u32; 4] = [15, 10, 5, 0];
+
+/// the cell of a pixel at `bits` per channel: the top bits of each.
+fn cell_of(px: &[u8], bits: u32) -> usize {
+    let shift = 8 - bits;
+    ((px[0] >> shift) as usize) << (3 * bits)
+        | ((px[1] >> shift) as usize) << (2 * bits)
+        | ((px[2] >> shift) as usize) << bits
+        | (px[3] >> shift) as usize
+}
+
+/// the histogram cell of a pixel.
+fn cell(px: &[u8]) -> usize {
+    cell_of(px, BITS)
+}
+
+/// channel `ch` of a cell, 0..32.
+fn level(cell: u32, ch: usize) -> u32 {
+    (cell >> SHIFTS[ch]) & 31
+}
+
+/// whether `rgba` has at most `n` distinct colours (n ≤ 256). Stops at the
+/// first colour past `n`, so photos bail out almost at once.
+pub fn fits(rgba: &[u8], n: usize) -> bool {
+    // open addressing, colour + 1 per slot (0 = empty), kept under 1/4 full
+    const SLOTS: usize = 1 << 11;
+    let mut slots = vec![0u64; SLOTS];
+    let mut seen = 0;
+    let mut last = None;
+    for px in rgba.chunks_exact(4) {
+        let c = u32::from_le_bytes([px[0], px[1], px[2], px[3]]);
+        if last == Some(c) {
+            continue; // runs of one colour are most of a screenshot
+        }
+        last = Some(c);
+        let mut i = (c.wrapping_mul(0x9e37_79b1) >> (32 - 11)) as usize;
+        loop {
+            match slots[i] {
+                0 => {
+                    slots[i] = c as u64 + 1;
+                    seen += 1;
+                    if seen > n {
+                        return false;
+                    }
+                    break;
+                }
+                s if s == c as u64 + 1 => break,
+                _ => i = (i + 1) % SLOTS,
+            }
+        }
+    }
+    true
+}
+
+/// a run of occupied cells, and the channel it spans most.
+struct Span {
+    cells: Range<usize>,
+    pixels: u64,
+    channel: usize,
+    extent: u32,
+}
+
+impl Span {
+    fn new(cells: &[(u32, u32)], range: Range<usize>) -> Span {
+        let (mut lo, mut hi) = ([31u32; 4], [0u32; 4]);
+        let mut pixels = 0;
+        for &(cell, count) in &cells[range.clone()] {
+            for ch in 0..4 {
+                lo[ch] = lo[ch].min(level(cell, ch));
+                hi[ch] = hi[ch].max(level(cell, ch));
+            }
+            pixels += count as u64;
+        }
+        let channel = (0..4).max_by_key(|&ch| hi[ch] - lo[ch]).unwrap_or(0);
+        Span {
+            cells: range,
+            pixels,
+            channel,
+            extent: hi[channel] - lo[channel],
+        }
+    }
+
+    /// which box to split next: wide and well populated first.
+    fn score(&self) -> u64 {
+        self.extent as u64 * self.pixels
+    }
+}
+
+/// where to split `run` (sorted along `channel`, two levels at least): between
+/// two levels, wherever the halves' means are furthest apart weighted by
+/// their populations (Otsu's criterion). A plain population median would
+/// often cut straight through a cluster of near-identical colours.
+fn cut(run: &[(u32, u32)], channel: usize, pixels: u64) -> usize {
+    let total: f64 = run
+        .iter()
+        .map(|&(cell, count)| level(cell, channel) as f64 * count as f64)
+        .sum();
+    let (mut n, mut sum) = (0.0, 0.0);
+    let mut best = (f64::MIN, 1);
+    for i in 1..run.len() {
+        let (cell, count) = run[i - 1];
+        n += count as f64;
+        sum += level(cell, channel) as f64 * count as f64;
+        if level(cell, channel) == level(run[i].0, channel) {
+            continue;
+        }
+        let rest = pixels as f64 - n;
+        let apart = sum / n - (total - sum) / rest;
+        let score = apart * apart * n * rest;
+        if score > best.0 {
+            best = (score, i);
+        }
+    }
+    best.1
+}
+
+/// up to `n` colours for `rgba` by median cut: the box with the largest
+/// extent × population is split along its widest channel, until there are
+/// `n` boxes or none can be split.
+pub fn median_cut(rgba: &[u8], n: usize) -> Vec<[u8; 4]> {
+    let mut counts = vec![0u32; CELLS];
+    for px in rgba.chunks_exact(4) {
+        counts[cell(px)] += 1;
+    }
+    let mut cells: Vec<(u32, u32)> = (0..CELLS as u32)
+        .zip(counts.iter().copied())
+        .filter(|&(_, count)| count > 0)
+        .collect();
+
+    let mut spans = vec![Span::new(&cells, 0..cells.len())];
+    while spans.len() < n {
+        let Some(i) = (0..spans.len())
+            .filter(|&i| spans[i].extent > 0)
+            .max_by_key(|&i| spans[i].score())
+        else {
+            break; // every box is a single cell
+        };
+        let span = spans.swap_remove(i);
+        let run = &mut cells[span.cells.clone()];
+        run.sort_unstable_by_key(|&(cell, _)| level(cell, span.channel));
+        let mid = span.cells.start + cut(run, span.channel, span.pixels);
+        spans.push(Span::new(&cells, span.cells.start..mid));
+        spans.push(Span::new(&cells, mid..span.cells.end));
+    }
+
+    // the counts have done their job; reuse them as each cell's box
+    for (id, span) in spans.iter().enumerate() {
+        for &(cell, _) in &cells[span.cells.clone()] {
+            counts[cell as usize] = id as u32;
+        }
+    }
+    let mut sums = vec![[0u64; 5]; spans.len()];
+    for px in rgba.chunks_exact(4) {
+        let sum = &mut sums[counts[cell(px)] as usize];
+        for (s, &v) in sum.iter_mut().zip(px) {
+            *s += v as u64;
+        }
+        sum[4] += 1;
+    }
+    sums.iter()
+        .map(|s| [0, 1, 2, 3].map(|ch| ((s[ch] + s[4] / 2) / s[4]) as u8))
+        .collect()
+}
+
+fn distance(a: [u8; 4], b: [u8; 4]) -> u32 {
+    let mut d = 0;
+    for ch in 0..4 {
+        let v = a[ch].abs_diff(b[ch]) as u32;
+        d += v * v;
+    }
+    d
+}
+
+/// exact nearest-colour lookup. For each cell, on first use, the palette is
+/// narrowed to the entries that could be nearest to anything in it, closest
+/// to the cell first; a pixel then measures those until the rest can't be
+/// nearer. Cells are narrowed from their coarse (4-bit) parent's list rather
+/// than the whole palette, which keeps noisy captures, with a fresh cell
+/// every few pixels, affordable too.
+pub struct Nearest<'a> {
+    palette: &'a [[u8; 4]],
+    // per coarse cell, then per cell: where its candidates start in `runs`,
+    // + 1; 0 until needed
+    coarse: Vec<u32>,
+    cells: Vec<u32>,
+    // candidate count, then that many candidates as their distance to the
+    // cell << 8 | palette index, closest first
+    runs: Vec<u32>,
+    // the list being narrowed
+    from: Vec<u8>,
+    // the previous lookup: most of a screenshot is runs of one colour
+    last: ([u8; 4], usize),
+}
+
+impl<'a> Nearest<'a> {
+    pub fn new(palette: &'a [[u8; 4]]) -> Nearest<'a> {
+        debug_assert!(!palette.is_empty() && palette.len() <= 256);
+        Nearest {
+            palette,
+            coarse: vec![0; 1 << (4 * COARSE_BITS)],
+            cells: vec![0; CELLS],
+            runs: Vec::new(),
+            from: Vec::new(),
+            last: (palette[0], 0),
+        }
+    }
+
+    /// the index of the palette entry nearest `px` (the first, on a tie).
+    pub fn index(&mut self, px: [u8; 4]) -> usize {
+        if px == self.last.0 {
+            return self.last.1;
+        }
+        let c = cell(&px);
+        if self.cells[c] == 0 {
+            let p = cell_of(&px, COARSE_BITS);
+            if self.coarse[p] == 0 {
+                self.from.clear();
+                self.from.extend((0..self.palette.len()).map(|i| i as u8));
+                self.coarse[p] = self.narrow(px, COARSE_BITS) + 1;
+            }
+            let at = self.coarse[p] as usize - 1;
+            let n = self.runs[at] as usize;
+            let coarse = self.runs[at + 1..][..n].iter().map(|&e| e as u8);
+            self.from.clear();
+            self.from.extend(coarse);
+            self.cells[c] = self.narrow(px, BITS) + 1;
+        }
+        let at = self.cells[c] as usize - 1;
+        let n = self.runs[at] as usize;
+        // best distance so far (< 2^19) and index in one key: the smallest is
+        // the nearest, and the first of equals
+        let mut best = u32::MAX;
+        for &entry in &self.runs[at + 1..][..n] {
+            if entry >> 8 > best >> 8 {
+                break; // this and every later entry is further than `best`
+            }
+            let i = entry & 0xff;
+            best = best.min(distance(px, self.palette[i as usize]) << 8 | i);
+        }
+        let i = (best & 0xff) as usize;
+        self.last = (px, i);
+        i
+    }
+
+    /// keep those of `from` whose closest point to the cell holding `px` (at
+    /// `bits` a channel) is no further than the best farthest point: any
+    /// other is beaten everywhere in the cell. Returns where the run starts.
+    fn narrow(&mut self, px: [u8; 4], bits: u32) -> u32 {
+        let mask = !0u8 << (8 - bits);
+        let lo = px.map(|v| (v & mask) as i32);
+        let hi = lo.map(|l| l + (1 << (8 - bits)) - 1);
+        let reach = |p: [u8; 4]| {
+            let (mut near, mut far) = (0, 0);
+            for ch in 0..4 {
+                let v = p[ch] as i32;
+                let gap = (lo[ch] - v).max(v - hi[ch]).max(0);
+                let span = (v - lo[ch]).max(hi[ch] - v);
+                near += gap * gap;
+                far += span * span;
+            }
+            (near, far)
+        };
+        let palette = self.palette;
+        let best_far = self
+            .from
+            .iter()
+            .map(|&i| reach(palette[i as usize]).1)
+            .min();
+        let at = self.runs.len();
+        self.runs.push(0);
+        for &i in &self.from {
+            let near = reach(palette[i as usize]).0;
+            if Some(near) <= best_far {
+                self.runs.push((near as u32) << 8 | i as u32);
+            }
+        }
+        self.runs[at + 1..].sort_unstable();
+        self.runs[at] = (self.runs.len() - at - 1) as u32;
+        at as u32
+    }
+}
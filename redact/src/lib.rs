@@ -0,0 +1,110 @@
+//! capscr plugin: redact fixed areas of every capture.
+//!
+//! Some parts of an app always sit in the same place — a sidebar of customer
+//! names, a token field, an account badge. This redacts configured
+//! rectangles (see [`rects`]), chosen per capture mode from the header's mode
+//! field, before the capture is saved or uploaded. Each rectangle is
+//! pixelated, filled or blurred with noise (see [`styles`]); only the last
+//! two can't be reversed.
+//!
+//! Blocks and blur windows straddle band boundaries, so there's no tiled
+//! variant; the rewrite happens in place (capscr 0.6+).
+
+pub mod rects;
+pub mod styles;
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use capscr_plugin_sdk::config::Config;
+use capscr_plugin_sdk::image::Header;
+use capscr_plugin_sdk::inplace::InPlaceFilter;
+use capscr_plugin_sdk::srgb::Lut;
+
+use styles::Blur;
+
+const DEFAULT_BLOCK: u32 = 16;
+const MAX_BLOCK: u32 = 1024;
+const DEFAULT_FILL: [u8; 4] = [0, 0, 0, 255];
+const DEFAULT_BLUR_RADIUS: u32 = 32;
+const DEFAULT_BLUR_PASSES: u32 = 3;
+const DEFAULT_NOISE: u32 = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    /// `block`-square averages; partly reversible over simple content
+    Pixelate,
+    /// one solid colour
+    Fill,
+    /// multi-pass box blur plus noise
+    Blur,
+}
+
+pub const STYLES: &[(&str, Style)] = &[
+    ("pixelate", Style::Pixelate),
+    ("fill", Style::Fill),
+    ("blur", Style::Blur),
+];
+
+// captures this instance has redacted: part of every blur's noise seed
+static CAPTURES: AtomicU64 = AtomicU64::new(0);
+
+capscr_plugin_sdk::export_in_place_filter!(Redact);
+
+pub struct Redact {
+    width: usize,
+    /// `[x0, y0, x1, y1)` of each area, already in this capture's pixels,
+    /// and how to redact it
+    areas: Vec<([u32; 4], Style)>,
+    block: usize,
+    fill: [u8; 4],
+    blur: Blur,
+    lut: Box<Lut>,
+}
+
+impl InPlaceFilter for Redact {
+    fn begin(header: &Header, config: &dyn Config) -> Option<Self> {
+        let rects = rects::read(config, header.mode);
+        if rects.is_empty() {
+            return None;
+        }
+        let style = config.choice("style", Style::Pixelate, STYLES);
+        let areas: Vec<_> = rects
+            .iter()
+            .filter_map(|r| {
+                Some((
+                    r.resolve(header.width, header.height)?,
+                    r.style.unwrap_or(style),
+                ))
+            })
+            .collect();
+        if areas.is_empty() {
+            return None;
+        }
+        Some(Redact {
+            width: header.width as usize,
+            areas,
+            block: config.u32("block", DEFAULT_BLOCK, 2..=MAX_BLOCK) as usize,
+            fill: config.color("fill_color", DEFAULT_FILL),
+            blur: Blur {
+                radius: config.u32("blur_radius", DEFAULT_BLUR_RADIUS, 4..=512) as usize,
+                passes: config.u32("blur_passes", DEFAULT_BLUR_PASSES, 1..=8),
+                noise: config.u32("noise", DEFAULT_NOISE, 0..=128) as u8,
+                seed: styles::seed(
+                    &config.string("noise_key", ""),
+                    CAPTURES.fetch_add(1, Ordering::Relaxed),
+                ),
+            },
+            lut: Box::new(Lut::new()),
+        })
+    }
+
+    fn rows(&mut self, _y: u32, rgba: &mut [u8]) {
+        for &(area, style) in &self.areas {
+            match style {
+                Style::Pixelate => styles::pixelate(rgba, self.width, area, self.block, &self.lut),
+                Style::Fill => styles::fill(rgba, self.width, area, self.fill),
+                Style::Blur => styles::blur(rgba, self.width, area, &self.blur, &self.lut),
+            }
+        }
+    }
+}
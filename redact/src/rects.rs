@@ -0,0 +1,136 @@
+//! The rectangles to redact, from config.
+//!
+//! Each is one string, `"<anchor> <x> <y> <width> <height> [style]"`: the
+//! anchor is the corner the offsets are measured from (`top-left`,
+//! `top-right`, `bottom-left`, `bottom-right`), every length is pixels (`240`)
+//! or a percentage of the capture's width or height (`25%`), and the optional
+//! style overrides the `style` key for that rectangle. `rects` applies to
+//! every capture; `<mode>_rects` (`window_rects`, `gif_rects`, …) replaces it
+//! for that mode, so `region_rects = []` turns redaction off for regions.
+
+use capscr_plugin_sdk::config::Config;
+use capscr_plugin_sdk::image::Mode;
+
+use crate::{Style, STYLES};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Length {
+    Px(u32),
+    Percent(f32),
+}
+
+impl Length {
+    fn parse(s: &str) -> Option<Length> {
+        match s.strip_suffix('%') {
+            Some(p) => {
+                let p = p.parse::<f32>().ok()?;
+                (0.0..=100.0).contains(&p).then_some(Length::Percent(p))
+            }
+            None => s.parse().ok().map(Length::Px),
+        }
+    }
+
+    /// in pixels, along an axis `of` pixels long.
+    fn px(self, of: u32) -> f64 {
+        match self {
+            Length::Px(v) => v as f64,
+            Length::Percent(p) => of as f64 * p as f64 / 100.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+const ANCHORS: &[(&str, Anchor)] = &[
+    ("top-left", Anchor::TopLeft),
+    ("top-right", Anchor::TopRight),
+    ("bottom-left", Anchor::BottomLeft),
+    ("bottom-right", Anchor::BottomRight),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub anchor: Anchor,
+    pub x: Length,
+    pub y: Length,
+    pub width: Length,
+    pub height: Length,
+    /// None for the configured default
+    pub style: Option<Style>,
+}
+
+impl Rect {
+    pub fn parse(s: &str) -> Option<Rect> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let (anchor, x, y, w, h, style) = match words[..] {
+            [anchor, x, y, w, h] => (anchor, x, y, w, h, None),
+            [anchor, x, y, w, h, style] => (anchor, x, y, w, h, Some(style)),
+            _ => return None,
+        };
+        let style = match style {
+            Some(name) => Some(STYLES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name))?.1),
+            None => None,
+        };
+        let anchor = ANCHORS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(anchor))?
+            .1;
+        Some(Rect {
+            anchor,
+            x: Length::parse(x)?,
+            y: Length::parse(y)?,
+            width: Length::parse(w)?,
+            height: Length::parse(h)?,
+            style,
+        })
+    }
+
+    /// `[x0, y0, x1, y1)` in a `w`×`h` capture, rounded outward to whole
+    /// pixels (a redaction covers at least what was asked) and clipped to
+    /// the capture; None if nothing is left.
+    pub fn resolve(&self, w: u32, h: u32) -> Option<[u32; 4]> {
+        let (dx, dy) = (self.x.px(w), self.y.px(h));
+        let (rw, rh) = (self.width.px(w), self.height.px(h));
+        let x0 = match self.anchor {
+            Anchor::TopLeft | Anchor::BottomLeft => dx,
+            Anchor::TopRight | Anchor::BottomRight => w as f64 - dx - rw,
+        };
+        let y0 = match self.anchor {
+            Anchor::TopLeft | Anchor::TopRight => dy,
+            Anchor::BottomLeft | Anchor::BottomRight => h as f64 - dy - rh,
+        };
+        let clip = |v: f64, max: u32| v.clamp(0.0, max as f64) as u32;
+        let (x0, x1) = (clip(x0.floor(), w), clip((x0 + rw).ceil(), w));
+        let (y0, y1) = (clip(y0.floor(), h), clip((y0 + rh).ceil(), h));
+        (x0 < x1 && y0 < y1).then_some([x0, y0, x1, y1])
+    }
+}
+
+/// the rectangles configured for `mode`: `<mode>_rects` if set, else
+/// `rects`. An entry that doesn't parse is logged and skipped; the others
+/// still apply.
+pub fn read(config: &dyn Config, mode: Mode) -> Vec<Rect> {
+    let own = format!("{}_rects", mode.name());
+    let key = if config.get(&own).is_some() {
+        own.as_str()
+    } else {
+        "rects"
+    };
+    config
+        .list(key)
+        .iter()
+        .filter_map(|item| {
+            let rect = Rect::parse(item);
+            if rect.is_none() {
+                config.invalid(key, item);
+            }
+            rect
+        })
+        .collect()
+}
@@ -0,0 +1,181 @@
+//! What happens inside a redacted area.
+//!
+//! `pixelate` keeps the rough layout visible, but it's an average: with big
+//! blocks over simple content (large text, a few flat colours) some of it can
+//! be recovered, by eye or by matching candidate renderings. `fill` and
+//! `blur` can't be undone. A fill depends on nothing in the area; the blur
+//! is several box-blur passes, each as wide as `blur_radius`, which leaves
+//! only the coarsest shading, and then noise on top, so deconvolution has
+//! nothing clean to work from. As a last step a blurred pixel that happens
+//! to come out equal to the original is nudged, so no pixel of the original
+//! survives in place.
+//!
+//! The noise must not depend on what it hides: if it did, the same content
+//! would always redact to the same bytes, and anyone with a guess could
+//! render it, redact it and compare. It's seeded from `noise_key` and the
+//! number of captures this plugin instance has seen (see [`seed`]). Guest
+//! plugins get no clock or entropy from the host, so without a key the
+//! sequence starts over whenever capscr loads the plugin. The shading left
+//! by the blur still follows the content, too, so a guess can be compared
+//! approximately; for short values from a small set (names, numbers), a fill
+//! is the safe choice.
+
+use capscr_plugin_sdk::srgb::Lut;
+
+/// `blur`'s settings.
+pub struct Blur {
+    pub radius: usize,
+    pub passes: u32,
+    /// the most each channel moves, either way
+    pub noise: u8,
+    /// this capture's noise seed, from [`seed`]
+    pub seed: u64,
+}
+
+/// the noise seed for a capture: `key` (the user's `noise_key`, possibly
+/// empty) and `capture`, a count of captures so far. Nothing of the image.
+pub fn seed(key: &str, capture: u64) -> u64 {
+    fnv(fnv(FNV_OFFSET, key.as_bytes()), &capture.to_le_bytes())
+}
+
+/// the pixel rows of `area` in `rgba` (`w` pixels a row): byte ranges, top
+/// to bottom.
+fn spans(w: usize, [x0, y0, x1, y1]: [usize; 4]) -> impl Iterator<Item = core::ops::Range<usize>> {
+    (y0..y1).map(move |y| (y * w + x0) * 4..(y * w + x1) * 4)
+}
+
+/// replace every `block`-square of `area` in `rgba` (`w` pixels a row) with
+/// its average. Blocks start at the area's top-left corner; those cut by its
+/// right or bottom edge average only what's inside.
+pub fn pixelate(rgba: &mut [u8], w: usize, area: [u32; 4], block: usize, lut: &Lut) {
+    let [x0, y0, x1, y1] = area.map(|v| v as usize);
+    for by in (y0..y1).step_by(block) {
+        for bx in (x0..x1).step_by(block) {
+            let cell = [bx, by, (bx + block).min(x1), (by + block).min(y1)];
+            let mut sum = [0.0f32; 4];
+            for span in spans(w, cell) {
+                for px in rgba[span].chunks_exact(4) {
+                    let v = lut.premultiplied([px[0], px[1], px[2], px[3]]);
+                    for c in 0..4 {
+                        sum[c] += v[c];
+                    }
+                }
+            }
+            let n = ((cell[2] - cell[0]) * (cell[3] - cell[1])) as f32;
+            let mean = lut.unpremultiplied(sum.map(|s| s / n));
+            for span in spans(w, cell) {
+                for px in rgba[span].chunks_exact_mut(4) {
+                    px.copy_from_slice(&mean);
+                }
+            }
+        }
+    }
+}
+
+/// paint `area` solid `color`.
+pub fn fill(rgba: &mut [u8], w: usize, area: [u32; 4], color: [u8; 4]) {
+    for span in spans(w, area.map(|v| v as usize)) {
+        for px in rgba[span].chunks_exact_mut(4) {
+            px.copy_from_slice(&color);
+        }
+    }
+}
+
+/// blur `area` past recognition and add noise; see the module docs. Only
+/// the area's own pixels are read, with its edges repeated outward.
+pub fn blur(rgba: &mut [u8], w: usize, area: [u32; 4], settings: &Blur, lut: &Lut) {
+    let area = area.map(|v| v as usize);
+    let (aw, ah) = (area[2] - area[0], area[3] - area[1]);
+    let original: Vec<u8> = spans(w, area)
+        .flat_map(|span| rgba[span].to_vec())
+        .collect();
+    let mut plane: Vec<[f32; 4]> = original
+        .chunks_exact(4)
+        .map(|px| lut.premultiplied([px[0], px[1], px[2], px[3]]))
+        .collect();
+
+    let mut line = Vec::new();
+    let mut column = vec![[0.0; 4]; ah];
+    for _ in 0..settings.passes {
+        for row in plane.chunks_exact_mut(aw) {
+            box_line(row, settings.radius, &mut line);
+        }
+        for x in 0..aw {
+            for (y, v) in column.iter_mut().enumerate() {
+                *v = plane[y * aw + x];
+            }
+            box_line(&mut column, settings.radius, &mut line);
+            for (y, v) in column.iter().enumerate() {
+                plane[y * aw + x] = *v;
+            }
+        }
+    }
+
+    let mut noise = Noise::seeded(settings.seed, area);
+    let n = settings.noise as i32;
+    let out = spans(w, area).flat_map(|span| span.step_by(4));
+    for ((at, v), was) in out.zip(&plane).zip(original.chunks_exact(4)) {
+        let mut px = lut.unpremultiplied(*v);
+        for c in &mut px[..3] {
+            let jitter = (noise.next() % (2 * n as u32 + 1)) as i32 - n;
+            *c = (*c as i32 + jitter).clamp(0, 255) as u8;
+        }
+        if px[..] == *was {
+            px[0] ^= 1; // never the original pixel, even by chance
+        }
+        rgba[at..at + 4].copy_from_slice(&px);
+    }
+}
+
+/// box-average `v` over a window `2r + 1` wide, edges repeated; `scratch`
+/// holds a copy of the input.
+fn box_line(v: &mut [[f32; 4]], r: usize, scratch: &mut Vec<[f32; 4]>) {
+    let n = v.len();
+    scratch.clear();
+    scratch.extend_from_slice(v);
+    let at = |i: isize| scratch[i.clamp(0, n as isize - 1) as usize];
+    let r = r as isize;
+    let mut sum = [0.0f32; 4];
+    for i in -r..=r {
+        let s = at(i);
+        for c in 0..4 {
+            sum[c] += s[c];
+        }
+    }
+    let width = (2 * r + 1) as f32;
+    for (i, out) in v.iter_mut().enumerate() {
+        let i = i as isize;
+        *out = sum.map(|s| s / width);
+        let (enter, leave) = (at(i + r + 1), at(i - r));
+        for c in 0..4 {
+            sum[c] += enter[c] - leave[c];
+        }
+    }
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// FNV-1a of `bytes`, continuing from `h`.
+fn fnv(mut h: u64, bytes: &[u8]) -> u64 {
+    for &b in bytes {
+        h = (h ^ b as u64).wrapping_mul(0x0100_0000_01b3);
+    }
+    h
+}
+
+/// xorshift64*, one stream per area of a capture.
+struct Noise(u64);
+
+impl Noise {
+    fn seeded(seed: u64, area: [usize; 4]) -> Noise {
+        let area = area.map(|v| v as u32).map(u32::to_le_bytes).concat();
+        Noise(fnv(seed, &area) | 1)
+    }
+
+    fn next(&mut self) -> u32 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 32) as u32
+    }
+}
@@ -0,0 +1,45 @@
+//! capscr plugin: round the corners of each capture.
+//!
+//! Scales the alpha of the pixels outside a quarter circle in each corner by
+//! how much of them the arc covers (8×8 supersampled), so the corners are
+//! truly transparent and the curve is anti-aliased. Same size in and out, so
+//! it rewrites the capture in place (capscr 0.6+); only the top and bottom
+//! `radius` rows are touched, and each row is independent, so it also exports
+//! the tiled hook.
+//!
+//! Runs before `borders` or `drop-shadow` in the plugin order: both read the
+//! capture's alpha, so the border hugs the curve and the shadow shows through
+//! the cut corners.
+
+use capscr_plugin_sdk::config::Config;
+use capscr_plugin_sdk::image::Header;
+use capscr_plugin_sdk::inplace::InPlaceFilter;
+use capscr_plugin_sdk::px::Corners;
+
+const DEFAULT_RADIUS: u32 = 8; // Windows 11's top-level window radius
+const MAX_RADIUS: u32 = 8192;
+
+capscr_plugin_sdk::export_in_place_filter!(RoundedCorners, tiled);
+
+pub struct RoundedCorners(Corners);
+
+impl InPlaceFilter for RoundedCorners {
+    fn begin(header: &Header, config: &dyn Config) -> Option<Self> {
+        let radius = config.u32("radius", DEFAULT_RADIUS, 0..=MAX_RADIUS);
+        if let Some(modes) = config.modes("only_modes") {
+            if !modes.contains(&header.mode) {
+                return None;
+            }
+        }
+        // capped at half the shorter side: a circle is as round as it gets
+        let corners = Corners::new(header.width, header.height, radius);
+        if corners.radius() == 0 {
+            return None;
+        }
+        Some(RoundedCorners(corners))
+    }
+
+    fn rows(&mut self, y: u32, rows: &mut [u8]) {
+        self.0.apply(y, rows);
+    }
+}
@@ -0,0 +1,46 @@
+//! Wire-level constants and the `(ptr << 32) | len` packing every hook return
+//! and host import uses.
+
+/// `on_capture` input header: `[w:u32][h:u32][mode:u32]`, then rgba.
+pub const CAPTURE_HEADER_LEN: usize = 12;
+/// replacement image header: `[w:u32][h:u32]`, then rgba.
+pub const REPLY_HEADER_LEN: usize = 8;
+/// tiled band header: `[y:u32]` (first input row of the band), then whole rows.
+pub const BAND_HEADER_LEN: usize = 4;
+
+/// hook return: leave the capture unchanged.
+pub const CONTINUE: i64 = 0;
+/// on_capture return (capscr 0.6+): the rgba inside the input blob was
+/// rewritten in place; same width/height, the host reads it back from there.
+/// (1 can't be a packed replacement: that would be 1 byte at address 0.)
+pub const IN_PLACE: i64 = 1;
+/// tiled hook return: protocol error — the host drops the stream and keeps the
+/// original capture.
+pub const ABORT: i64 = -1;
+
+/// pack a guest region into the i64 the host expects.
+pub fn pack(ptr: usize, len: usize) -> i64 {
+    ((ptr as i64) << 32) | (len as i64 & 0xffff_ffff)
+}
+
+/// inverse of [`pack`]: `(ptr, len)`.
+pub fn unpack(packed: i64) -> (usize, usize) {
+    let p = packed as u64;
+    ((p >> 32) as usize, (p & 0xffff_ffff) as usize)
+}
+
+/// tiled begin return: the output dimensions, `(w << 32) | h`.
+pub fn pack_size(w: u32, h: u32) -> i64 {
+    ((w as i64) << 32) | h as i64
+}
+
+/// inverse of [`pack_size`]: `(w, h)`.
+pub fn unpack_size(packed: i64) -> (u32, u32) {
+    let p = packed as u64;
+    ((p >> 32) as u32, p as u32)
+}
+
+/// little-endian u32 at `at`; callers have already checked the length.
+pub fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
@@ -0,0 +1,109 @@
+//! Row-local filters: the shape that lets a plugin opt into the tiled hook.
+//!
+//! A [`BandFilter`] sees the input as consecutive bands of whole rows, top to
+//! bottom, and appends finished output rows as it goes. The same impl serves
+//! both `capscr_on_capture` (one band holding the whole image) and the tiled
+//! variant (the host streams bands and collects output rows on its side), so a
+//! 16384×16384 capture never has to fit in guest memory at once.
+
+use crate::config::Config;
+use crate::image::{begin_reply, Capture, Header};
+
+pub trait BandFilter: Sized {
+    /// set up for a capture, or None to leave it untouched.
+    fn begin(header: &Header, config: &dyn Config) -> Option<Self>;
+
+    /// `(width, height)` of the image this filter will emit.
+    fn output_size(&self) -> (u32, u32);
+
+    /// consume the next whole input rows (`rows.len()` is a multiple of the
+    /// input row size) and append any output rows they complete to `out`.
+    fn band(&mut self, rows: &[u8], out: &mut Vec<u8>);
+
+    /// append any output rows still buffered once the last band is in.
+    fn finish(&mut self, out: &mut Vec<u8>) {
+        let _ = out;
+    }
+}
+
+/// run `F` over a whole capture, writing a replacement blob (header included)
+/// into `out`. false if the filter passed, or emitted the wrong amount of data.
+pub fn apply<F: BandFilter>(cap: &Capture, config: &dyn Config, out: &mut Vec<u8>) -> bool {
+    let Some(mut filter) = F::begin(&cap.header, config) else {
+        return false;
+    };
+    let (ow, oh) = filter.output_size();
+    if ow == 0 || oh == 0 {
+        return false;
+    }
+    begin_reply(out, ow, oh);
+    let header_len = out.len();
+    filter.band(cap.rgba, out);
+    filter.finish(out);
+    out.len() - header_len == ow as usize * oh as usize * 4
+}
+
+/// a tiled stream in progress; the wasm glue keeps one per plugin between
+/// `_begin`, `_band` and `_end` calls.
+pub struct Tiled<F> {
+    filter: F,
+    header: Header,
+    next_row: u32,
+    out_rows: u64,
+}
+
+impl<F: BandFilter> Tiled<F> {
+    pub fn begin(header: Header, config: &dyn Config) -> Option<Tiled<F>> {
+        header.rgba_len()?;
+        let filter = F::begin(&header, config)?;
+        let (ow, oh) = filter.output_size();
+        if ow == 0 || oh == 0 {
+            return None;
+        }
+        Some(Tiled {
+            filter,
+            header,
+            next_row: 0,
+            out_rows: 0,
+        })
+    }
+
+    pub fn output_size(&self) -> (u32, u32) {
+        self.filter.output_size()
+    }
+
+    /// feed rows starting at input row `y`. false on a protocol error (bands
+    /// out of order, partial rows, or past the bottom of the image).
+    pub fn band(&mut self, y: u32, rows: &[u8], out: &mut Vec<u8>) -> bool {
+        let row_bytes = self.header.row_bytes();
+        if y != self.next_row || rows.is_empty() || !rows.len().is_multiple_of(row_bytes) {
+            return false;
+        }
+        let n = (rows.len() / row_bytes) as u64;
+        if y as u64 + n > self.header.height as u64 {
+            return false;
+        }
+        self.next_row += n as u32;
+        self.emit(out, |f, out| f.band(rows, out))
+    }
+
+    /// flush after the last band. false if rows are missing on either side.
+    pub fn finish(&mut self, out: &mut Vec<u8>) -> bool {
+        if self.next_row != self.header.height {
+            return false;
+        }
+        self.emit(out, |f, out| f.finish(out))
+            && self.out_rows == self.filter.output_size().1 as u64
+    }
+
+    fn emit(&mut self, out: &mut Vec<u8>, step: impl FnOnce(&mut F, &mut Vec<u8>)) -> bool {
+        out.clear();
+        step(&mut self.filter, out);
+        let out_row = self.filter.output_size().0 as usize * 4;
+        if !out.len().is_multiple_of(out_row) {
+            return false;
+        }
+        self.out_rows += (out.len() / out_row) as u64;
+        self.out_rows <= self.filter.output_size().1 as u64
+    }
+}
@@ -0,0 +1,106 @@
+//! Separable Gaussian blur over `f32` planes (one channel, row-major).
+//!
+//! A 2-D Gaussian is the product of two 1-D ones, so a blur is a horizontal
+//! pass then a vertical pass, each `2 * ceil(3σ) + 1` taps — linear in σ
+//! rather than quadratic. The vertical pass walks strips of columns so every
+//! read is a contiguous run of a row.
+
+/// what the blur sees past the edge of the plane.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    /// zeros — for masks, where outside means "nothing there".
+    Zero,
+    /// the nearest edge value repeated — for image content.
+    Clamp,
+}
+
+// columns per vertical-pass strip
+const STRIP: usize = 64;
+
+/// normalised taps for standard deviation `sigma`, centre in the middle;
+/// `[1.0]` (no blur) for `sigma <= 0`.
+pub fn kernel(sigma: f32) -> Vec<f32> {
+    if sigma <= 0.0 {
+        return vec![1.0];
+    }
+    let radius = (sigma * 3.0).ceil() as i32;
+    let taps: Vec<f32> = (-radius..=radius)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum: f32 = taps.iter().sum();
+    taps.into_iter().map(|t| t / sum).collect()
+}
+
+/// the σ CSS uses for a `blur` length (`box-shadow`, `filter: blur()`).
+pub fn sigma_for_radius(radius: f32) -> f32 {
+    radius / 2.0
+}
+
+/// convolve `src` with kernel `k` into `dst` (same length).
+pub fn blur_line(src: &[f32], dst: &mut [f32], k: &[f32], edge: Edge) {
+    let n = src.len();
+    let r = k.len() / 2;
+    // taps that can fall off either end; everything between is a plain dot
+    // product the compiler can keep tight
+    let (lo, hi) = (r.min(n), n.saturating_sub(r).max(r.min(n)));
+    for i in (0..lo).chain(hi..n) {
+        dst[i] = k
+            .iter()
+            .enumerate()
+            .map(|(j, &t)| {
+                let at = (i + j) as isize - r as isize;
+                t * match edge {
+                    _ if (0..n as isize).contains(&at) => src[at as usize],
+                    Edge::Zero => 0.0,
+                    Edge::Clamp => src[at.clamp(0, n as isize - 1) as usize],
+                }
+            })
+            .sum();
+    }
+    for (d, window) in dst[lo..hi].iter_mut().zip(src.windows(k.len())) {
+        *d = window.iter().zip(k).map(|(s, t)| s * t).sum();
+    }
+}
+
+/// blur a `w`×`h` plane in place with kernel `k` along both axes.
+pub fn blur_plane(plane: &mut [f32], w: usize, h: usize, k: &[f32], edge: Edge) {
+    if k.len() <= 1 || w == 0 || h == 0 {
+        return;
+    }
+    let mut line = vec![0.0; w];
+    for row in plane.chunks_exact_mut(w) {
+        line.copy_from_slice(row);
+        blur_line(&line, row, k, edge);
+    }
+
+    let r = k.len() / 2;
+    let mut strip = vec![0.0; STRIP * h];
+    for x0 in (0..w).step_by(STRIP) {
+        let sw = STRIP.min(w - x0);
+        for y in 0..h {
+            strip[y * sw..][..sw].copy_from_slice(&plane[y * w + x0..][..sw]);
+        }
+        for y in 0..h {
+            let out = &mut plane[y * w + x0..][..sw];
+            out.fill(0.0);
+            for (j, &t) in k.iter().enumerate() {
+                let Some(sy) = source_row(y + j, r, h, edge) else {
+                    continue;
+                };
+                for (o, s) in out.iter_mut().zip(&strip[sy * sw..][..sw]) {
+                    *o += t * s;
+                }
+            }
+        }
+    }
+}
+
+/// the row tap `y + j - r` reads, or None where it's a zero past the edge.
+fn source_row(yj: usize, r: usize, h: usize, edge: Edge) -> Option<usize> {
+    match (yj.checked_sub(r), edge) {
+        (Some(at), _) if at < h => Some(at),
+        (_, Edge::Zero) => None,
+        (None, Edge::Clamp) => Some(0),
+        (Some(_), Edge::Clamp) => Some(h - 1),
+    }
+}
@@ -0,0 +1,189 @@
+//! Typed reads over the plugin's `config.toml`.
+//!
+//! On wasm the values come from the host's `config_get` import (see
+//! [`crate::host::HostConfig`]); natively any key/value source can stand in.
+//! Every reader falls back to its default on a missing, unparsable or
+//! out-of-range value, so a typo in config.toml never breaks a capture.
+
+use core::ops::RangeInclusive;
+
+use crate::image::Mode;
+
+// widest aspect ratio either way, e.g. "10:1"
+const MAX_ASPECT: f64 = 10.0;
+
+/// a source of raw config values — the value's TOML text as the host renders
+/// it (strings unquoted, arrays as `[a, b, c]`).
+pub trait Config {
+    fn get(&self, key: &str) -> Option<String>;
+
+    /// called when `key` is set but rejected; the host impl logs a warning.
+    fn invalid(&self, key: &str, raw: &str) {
+        let _ = (key, raw);
+    }
+}
+
+/// no config at all — every reader returns its default.
+pub struct NoConfig;
+
+impl Config for NoConfig {
+    fn get(&self, _key: &str) -> Option<String> {
+        None
+    }
+}
+
+/// fixed key/value pairs, e.g. `&[("max_dimension", "1280")][..]`.
+impl<K: AsRef<str>, V: AsRef<str>> Config for [(K, V)] {
+    fn get(&self, key: &str) -> Option<String> {
+        self.iter()
+            .find(|(k, _)| k.as_ref() == key)
+            .map(|(_, v)| v.as_ref().to_owned())
+    }
+}
+
+/// the same as an array, so `&[("max_dimension", "1280")]` coerces to
+/// `&dyn Config` directly in tests and benchmarks.
+impl<K: AsRef<str>, V: AsRef<str>, const N: usize> Config for [(K, V); N] {
+    fn get(&self, key: &str) -> Option<String> {
+        Config::get(&self[..], key)
+    }
+}
+
+impl dyn Config + '_ {
+    /// read `key` through a custom `parse`; `default` if unset or rejected.
+    pub fn read<T>(&self, key: &str, default: T, parse: impl FnOnce(&str) -> Option<T>) -> T {
+        match self.get(key) {
+            None => default,
+            Some(raw) => match parse(raw.trim()) {
+                Some(v) => v,
+                None => {
+                    self.invalid(key, &raw);
+                    default
+                }
+            },
+        }
+    }
+
+    pub fn u32(&self, key: &str, default: u32, range: RangeInclusive<u32>) -> u32 {
+        self.read(key, default, |s| {
+            s.parse::<u32>().ok().filter(|v| range.contains(v))
+        })
+    }
+
+    pub fn i32(&self, key: &str, default: i32, range: RangeInclusive<i32>) -> i32 {
+        self.read(key, default, |s| {
+            s.parse::<i32>().ok().filter(|v| range.contains(v))
+        })
+    }
+
+    pub fn f32(&self, key: &str, default: f32, range: RangeInclusive<f32>) -> f32 {
+        self.read(key, default, |s| {
+            s.parse::<f32>().ok().filter(|v| range.contains(v))
+        })
+    }
+
+    pub fn bool(&self, key: &str, default: bool) -> bool {
+        self.read(key, default, |s| s.parse::<bool>().ok())
+    }
+
+    /// `[r, g, b]`, `[r, g, b, a]`, `#rrggbb` or `#rrggbbaa`.
+    pub fn color(&self, key: &str, default: [u8; 4]) -> [u8; 4] {
+        self.read(key, default, parse_color)
+    }
+
+    /// free text, surrounding quotes stripped.
+    pub fn string(&self, key: &str, default: &str) -> String {
+        self.read(key, default.to_owned(), |s| Some(unquote(s).to_owned()))
+    }
+
+    /// one of a fixed set of names (case-insensitive).
+    pub fn choice<T: Copy>(&self, key: &str, default: T, options: &[(&str, T)]) -> T {
+        self.read(key, default, |s| {
+            let s = unquote(s);
+            options
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(s))
+                .map(|&(_, v)| v)
+        })
+    }
+
+    /// a flat array of strings or numbers; empty if unset.
+    pub fn list(&self, key: &str) -> Vec<String> {
+        self.read(key, Vec::new(), parse_list)
+    }
+
+    /// an aspect ratio `"w:h"` such as `"16:9"`, or `"auto"` for None. Ratios
+    /// past 10:1 either way are rejected: padding a capture out to one would
+    /// only run into the host's size cap.
+    pub fn aspect(&self, key: &str, default: Option<(u32, u32)>) -> Option<(u32, u32)> {
+        self.read(key, default, |s| {
+            let s = unquote(s);
+            if s.eq_ignore_ascii_case("auto") {
+                return Some(None);
+            }
+            let (w, h) = s.split_once(':')?;
+            let (w, h) = (w.trim().parse::<u32>().ok()?, h.trim().parse::<u32>().ok()?);
+            let ratio = w as f64 / h as f64;
+            (w > 0 && h > 0 && (1.0 / MAX_ASPECT..=MAX_ASPECT).contains(&ratio))
+                .then_some(Some((w, h)))
+        })
+    }
+
+    /// an `only_modes`-style list of capture mode names; None (every mode) if
+    /// unset or if any name is unknown.
+    pub fn modes(&self, key: &str) -> Option<Vec<Mode>> {
+        self.read(key, None, |s| {
+            let names = parse_list(s)?;
+            names
+                .iter()
+                .map(|n| Mode::parse(n))
+                .collect::<Option<_>>()
+                .map(Some)
+        })
+    }
+}
+
+/// strip one level of surrounding quotes, if any.
+pub fn unquote(s: &str) -> &str {
+    let s = s.trim();
+    for q in ['"', '\''] {
+        if let Some(inner) = s.strip_prefix(q).and_then(|t| t.strip_suffix(q)) {
+            return inner;
+        }
+    }
+    s
+}
+
+/// `[a, "b", 3]` → `["a", "b", "3"]`. No nesting, no commas inside items.
+pub fn parse_list(s: &str) -> Option<Vec<String>> {
+    let inner = s.trim().strip_prefix('[')?.strip_suffix(']')?;
+    Some(
+        inner
+            .split(',')
+            .map(unquote)
+            .filter(|item| !item.is_empty())
+            .map(str::to_owned)
+            .collect(),
+    )
+}
+
+pub fn parse_color(s: &str) -> Option<[u8; 4]> {
+    let s = unquote(s);
+    if let Some(hex) = s.strip_prefix('#') {
+        let byte = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return match hex.len() {
+            6 => Some([byte(0)?, byte(2)?, byte(4)?, 255]),
+            8 => Some([byte(0)?, byte(2)?, byte(4)?, byte(6)?]),
+            _ => None,
+        };
+    }
+    let items = parse_list(s)?;
+    let mut c = [0, 0, 0, 255];
+    if !(3..=4).contains(&items.len()) {
+        return None;
+    }
+    for (slot, item) in c.iter_mut().zip(&items) {
+        *slot = item.parse().ok()?;
+    }
+    Some(c)
+}
@@ -0,0 +1,44 @@
+//! Whole-image filters, for effects that need more than one row of context —
+//! blurs, histograms, anything that reads the capture twice. No tiled variant:
+//! the whole capture is in guest memory for the duration of the call.
+
+use crate::config::Config;
+use crate::image::{begin_reply, Capture, Header};
+
+pub trait Filter: Sized {
+    /// set up for a capture, or None to leave it untouched.
+    fn begin(header: &Header, config: &dyn Config) -> Option<Self>;
+
+    /// look at the capture before `output_size` is asked for, for filters
+    /// whose output depends on the content. false leaves the capture
+    /// untouched.
+    fn scan(&mut self, rgba: &[u8]) -> bool {
+        let _ = rgba;
+        true
+    }
+
+    /// `(width, height)` of the image this filter will emit.
+    fn output_size(&self) -> (u32, u32);
+
+    /// append exactly `output_size()` worth of rgba to `out`.
+    fn render(&mut self, rgba: &[u8], out: &mut Vec<u8>);
+}
+
+/// run `F` over a capture, writing a replacement blob (header included) into
+/// `out`. false if the filter passed, or emitted the wrong amount of data.
+pub fn apply<F: Filter>(cap: &Capture, config: &dyn Config, out: &mut Vec<u8>) -> bool {
+    let Some(mut filter) = F::begin(&cap.header, config) else {
+        return false;
+    };
+    if !filter.scan(cap.rgba) {
+        return false;
+    }
+    let (ow, oh) = filter.output_size();
+    if ow == 0 || oh == 0 {
+        return false;
+    }
+    begin_reply(out, ow, oh);
+    let header_len = out.len();
+    filter.render(cap.rgba, out);
+    out.len() - header_len == ow as usize * oh as usize * 4
+}
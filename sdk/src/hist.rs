@@ -0,0 +1,41 @@
+//! 256-bin histograms and the percentile search behind automatic levels.
+//!
+//! Both `color-adjust`'s `auto_levels` and `auto-levels` stretch a capture
+//! from the darkest and brightest levels that remain once a small fraction
+//! of pixels is let go at each end, and both leave a histogram narrower than
+//! [`MIN_RANGE`] alone.
+
+use crate::px::luma;
+
+/// a clipped range narrower than this isn't worth stretching: on a near-flat
+/// image that only amplifies the noise.
+pub const MIN_RANGE: u32 = 32;
+
+/// the histogram of `rgba`'s BT.601 luma.
+pub fn luma_histogram(rgba: &[u8]) -> [u32; 256] {
+    let mut hist = [0; 256];
+    for px in rgba.chunks_exact(4) {
+        hist[luma(px) as usize] += 1;
+    }
+    hist
+}
+
+/// the darkest and brightest levels of `hist` (over `n` pixels) once the
+/// `clip` fractions at each end are let go.
+pub fn range(hist: &[u32; 256], n: u32, (low, high): (f32, f32)) -> (u32, u32) {
+    let black = past(hist, 0..256, (n as f32 * low) as u32);
+    let white = past(hist, (0..256).rev(), (n as f32 * high) as u32);
+    (black, white.max(black))
+}
+
+/// the first of `levels` by which more than `skip` pixels have been seen.
+fn past(hist: &[u32; 256], levels: impl Iterator<Item = usize>, skip: u32) -> u32 {
+    let mut seen = 0;
+    for v in levels {
+        seen += hist[v];
+        if seen > skip {
+            return v as u32;
+        }
+    }
+    0
+}
@@ -0,0 +1,38 @@
+//! The `capscr` host imports, wrapped. wasm32 only.
+
+use crate::abi::unpack;
+use crate::config::Config;
+
+#[link(wasm_import_module = "capscr")]
+extern "C" {
+    // level: 0 error, 1 warn, 2 info, 3 debug
+    #[link_name = "log"]
+    fn log_import(level: i32, ptr: i32, len: i32);
+    // key* -> packed (ptr<<32)|len of the value, 0 if absent
+    fn config_get(key_ptr: i32, key_len: i32) -> i64;
+}
+
+pub(crate) fn log(level: i32, msg: &str) {
+    // SAFETY: the host only reads (ptr,len) from our memory for the call
+    unsafe { log_import(level, msg.as_ptr() as i32, msg.len() as i32) }
+}
+
+/// `config.toml` via the host. Values are copied out immediately, since the
+/// host writes them into the scratch buffer the next call will reuse.
+pub struct HostConfig;
+
+impl Config for HostConfig {
+    fn get(&self, key: &str) -> Option<String> {
+        let packed = unsafe { config_get(key.as_ptr() as i32, key.len() as i32) };
+        if packed == 0 {
+            return None;
+        }
+        let (ptr, len) = unpack(packed);
+        let bytes = unsafe { core::slice::from_raw_parts(ptr as *const u8, len) };
+        core::str::from_utf8(bytes).ok().map(str::to_owned)
+    }
+
+    fn invalid(&self, key: &str, raw: &str) {
+        crate::log::warn(&format!("ignoring invalid config value {key} = {raw}"));
+    }
+}
@@ -0,0 +1,117 @@
+//! The capture blob the host hands to image hooks, parsed into plain values.
+
+use crate::abi::{read_u32, CAPTURE_HEADER_LEN, REPLY_HEADER_LEN};
+
+/// capture mode from `input[8..12]`. Names match the native plugins'
+/// `only_modes` strings so config files stay interchangeable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    FullScreen,
+    Window,
+    Region,
+    Gif,
+    /// a mode this SDK predates; passed through untouched
+    Other(u32),
+}
+
+impl Mode {
+    pub fn from_u32(v: u32) -> Mode {
+        match v {
+            0 => Mode::FullScreen,
+            1 => Mode::Window,
+            2 => Mode::Region,
+            3 => Mode::Gif,
+            v => Mode::Other(v),
+        }
+    }
+
+    pub fn to_u32(self) -> u32 {
+        match self {
+            Mode::FullScreen => 0,
+            Mode::Window => 1,
+            Mode::Region => 2,
+            Mode::Gif => 3,
+            Mode::Other(v) => v,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::FullScreen => "fullscreen",
+            Mode::Window => "window",
+            Mode::Region => "region",
+            Mode::Gif => "gif",
+            Mode::Other(_) => "other",
+        }
+    }
+
+    /// case-insensitive inverse of [`Mode::name`].
+    pub fn parse(name: &str) -> Option<Mode> {
+        let name = name.trim();
+        [Mode::FullScreen, Mode::Window, Mode::Region, Mode::Gif]
+            .into_iter()
+            .find(|m| m.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// the fixed-size part of a capture: dimensions and mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub width: u32,
+    pub height: u32,
+    pub mode: Mode,
+}
+
+impl Header {
+    /// parse `[w][h][mode]` from the front of `bytes`.
+    pub fn parse(bytes: &[u8]) -> Option<Header> {
+        if bytes.len() < CAPTURE_HEADER_LEN {
+            return None;
+        }
+        Some(Header {
+            width: read_u32(bytes, 0),
+            height: read_u32(bytes, 4),
+            mode: Mode::from_u32(read_u32(bytes, 8)),
+        })
+    }
+
+    pub fn row_bytes(&self) -> usize {
+        self.width as usize * 4
+    }
+
+    /// `w * h * 4`, or None if that's zero or overflows usize.
+    pub fn rgba_len(&self) -> Option<usize> {
+        (self.width as usize)
+            .checked_mul(self.height as usize)?
+            .checked_mul(4)
+            .filter(|&n| n > 0)
+    }
+}
+
+/// a borrowed capture: header plus exactly `w * h * 4` bytes of rgba.
+#[derive(Clone, Copy, Debug)]
+pub struct Capture<'a> {
+    pub header: Header,
+    pub rgba: &'a [u8],
+}
+
+impl<'a> Capture<'a> {
+    /// parse a whole `[w][h][mode][rgba]` blob, rejecting any size mismatch.
+    pub fn parse(blob: &'a [u8]) -> Option<Capture<'a>> {
+        let header = Header::parse(blob)?;
+        let rgba = &blob[CAPTURE_HEADER_LEN..];
+        if header.rgba_len()? != rgba.len() {
+            return None;
+        }
+        Some(Capture { header, rgba })
+    }
+}
+
+/// reset `out` to an empty replacement blob for a `w`×`h` image: the 8-byte
+/// header is written and room reserved for the rgba that follows.
+pub fn begin_reply(out: &mut Vec<u8>, w: u32, h: u32) {
+    out.clear();
+    out.reserve(REPLY_HEADER_LEN + w as usize * h as usize * 4);
+    out.extend_from_slice(&w.to_le_bytes());
+    out.extend_from_slice(&h.to_le_bytes());
+}
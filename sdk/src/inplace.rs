@@ -0,0 +1,64 @@
+//! Same-size filters that rewrite the capture where the host wrote it.
+//!
+//! Instead of building a replacement blob next to the input (2× the image in
+//! guest memory), an [`InPlaceFilter`] edits the rgba inside the scratch buffer
+//! and `capscr_on_capture` returns [`crate::abi::IN_PLACE`]; the host reads
+//! the pixels back from the input region. Row-local filters also get the
+//! tiled variant, where each band is rewritten in place and handed straight
+//! back.
+
+use crate::config::Config;
+use crate::image::Header;
+
+pub trait InPlaceFilter: Sized {
+    /// set up for a capture, or None to leave it untouched.
+    fn begin(header: &Header, config: &dyn Config) -> Option<Self>;
+
+    /// rewrite whole rows starting at row `y`. Without tiling this is called
+    /// once with the entire image.
+    fn rows(&mut self, y: u32, rows: &mut [u8]);
+}
+
+/// a tiled in-place stream in progress.
+pub struct TiledInPlace<F> {
+    filter: F,
+    header: Header,
+    next_row: u32,
+}
+
+impl<F: InPlaceFilter> TiledInPlace<F> {
+    pub fn begin(header: Header, config: &dyn Config) -> Option<TiledInPlace<F>> {
+        header.rgba_len()?;
+        let filter = F::begin(&header, config)?;
+        Some(TiledInPlace {
+            filter,
+            header,
+            next_row: 0,
+        })
+    }
+
+    pub fn output_size(&self) -> (u32, u32) {
+        (self.header.width, self.header.height)
+    }
+
+    /// rewrite the band starting at row `y`. false on a protocol error (bands
+    /// out of order, partial rows, or past the bottom of the image).
+    pub fn band(&mut self, y: u32, rows: &mut [u8]) -> bool {
+        let row_bytes = self.header.row_bytes();
+        if y != self.next_row || rows.is_empty() || !rows.len().is_multiple_of(row_bytes) {
+            return false;
+        }
+        let n = (rows.len() / row_bytes) as u64;
+        if y as u64 + n > self.header.height as u64 {
+            return false;
+        }
+        self.next_row += n as u32;
+        self.filter.rows(y, rows);
+        true
+    }
+
+    /// true once every row has been through [`TiledInPlace::band`].
+    pub fn finish(&self) -> bool {
+        self.next_row == self.header.height
+    }
+}
@@ -0,0 +1,15 @@
+//! Read-only analysis of the final capture (`on_capture_inspect`).
+//!
+//! The host calls inspectors after every modifying `on_capture` plugin has
+//! run, with the pixels that will actually be saved, and ignores the result —
+//! so an inspector's manifest only needs `image = ["read"]`. The SDK takes the
+//! blob out of the scratch buffer before the inspector runs, so config reads
+//! can't overwrite it: a move when the host wrote it where `capscr_alloc`
+//! pointed, as it always does in practice, and a copy otherwise.
+
+use crate::config::Config;
+use crate::image::Capture;
+
+pub trait Inspector {
+    fn inspect(cap: &Capture, config: &dyn Config);
+}
@@ -0,0 +1,193 @@
+//! capscr plugin SDK: the ABI glue every image plugin here used to copy-paste
+//! (scratch buffer, blob parsing, ptr/len packing, `config_get`), plus adapters
+//! that let a filter opt into the host's extended hooks.
+//!
+//! The pure parts — blob parsing, config parsing, the filter traits — build on
+//! every target, so kernels can be driven natively. The wasm exports come from
+//! the `export_*!` macros and only exist on wasm32. See README.md for the ABI.
+
+pub mod abi;
+pub mod band;
+pub mod blur;
+pub mod config;
+pub mod filter;
+pub mod hist;
+#[cfg(target_arch = "wasm32")]
+pub mod host;
+pub mod image;
+pub mod inplace;
+pub mod inspect;
+pub mod log;
+pub mod px;
+#[doc(hidden)]
+#[cfg(target_arch = "wasm32")]
+pub mod rt;
+pub mod shadow;
+pub mod srgb;
+
+/// export `capscr_alloc`, `capscr_on_capture` and the tiled
+/// `capscr_on_capture_tiled_{begin,band,end}` trio for a
+/// [`band::BandFilter`]. Declare both hooks in plugin.toml:
+///
+/// ```toml
+/// [hooks]
+/// on_capture = "capscr_on_capture"
+/// on_capture_tiled = "capscr_on_capture_tiled"
+/// ```
+#[macro_export]
+macro_rules! export_band_filter {
+    ($filter:ty) => {
+        #[cfg(target_arch = "wasm32")]
+        mod __capscr_exports {
+            #[allow(unused_imports)]
+            use super::*;
+
+            static mut TILED: Option<$crate::band::Tiled<$filter>> = None;
+
+            fn tiled() -> &'static mut Option<$crate::band::Tiled<$filter>> {
+                // SAFETY: single-threaded wasm; calls are serialised by the host
+                unsafe { &mut *core::ptr::addr_of_mut!(TILED) }
+            }
+
+            #[no_mangle]
+            pub extern "C" fn capscr_alloc(size: i32) -> i32 {
+                $crate::rt::alloc(size)
+            }
+
+            #[no_mangle]
+            pub extern "C" fn capscr_on_capture(ptr: i32, len: i32) -> i64 {
+                unsafe { $crate::rt::on_capture::<$filter>(ptr, len) }
+            }
+
+            #[no_mangle]
+            pub extern "C" fn capscr_on_capture_tiled_begin(ptr: i32, len: i32) -> i64 {
+                unsafe { $crate::rt::tiled_begin(tiled(), ptr, len) }
+            }
+
+            #[no_mangle]
+            pub extern "C" fn capscr_on_capture_tiled_band(ptr: i32, len: i32) -> i64 {
+                unsafe { $crate::rt::tiled_band(tiled(), ptr, len) }
+            }
+
+            #[no_mangle]
+            pub extern "C" fn capscr_on_capture_tiled_end() -> i64 {
+                $crate::rt::tiled_end(tiled())
+            }
+        }
+    };
+}
+
+/// export `capscr_alloc` and `capscr_on_capture` for a whole-image
+/// [`filter::Filter`]. No tiled variant; declare just the one hook:
+///
+/// ```toml
+/// [hooks]
+/// on_capture = "capscr_on_capture"
+/// ```
+#[macro_export]
+macro_rules! export_filter {
+    ($filter:ty) => {
+        #[cfg(target_arch = "wasm32")]
+        mod __capscr_exports {
+            #[allow(unused_imports)]
+            use super::*;
+
+            #[no_mangle]
+            pub extern "C" fn capscr_alloc(size: i32) -> i32 {
+                $crate::rt::alloc(size)
+            }
+
+            #[no_mangle]
+            pub extern "C" fn capscr_on_capture(ptr: i32, len: i32) -> i64 {
+                unsafe { $crate::rt::on_capture_filter::<$filter>(ptr, len) }
+            }
+        }
+    };
+}
+
+/// export `capscr_alloc` and `capscr_on_capture_inspect` for an
+/// [`inspect::Inspector`]. Declare the hook with the read-only capability:
+///
+/// ```toml
+/// [hooks]
+/// on_capture_inspect = "capscr_on_capture_inspect"
+///
+/// [capabilities]
+/// image = ["read"]
+/// ```
+#[macro_export]
+macro_rules! export_inspector {
+    ($inspector:ty) => {
+        #[cfg(target_arch = "wasm32")]
+        mod __capscr_exports {
+            #[allow(unused_imports)]
+            use super::*;
+
+            #[no_mangle]
+            pub extern "C" fn capscr_alloc(size: i32) -> i32 {
+                $crate::rt::alloc(size)
+            }
+
+            #[no_mangle]
+            pub extern "C" fn capscr_on_capture_inspect(ptr: i32, len: i32) {
+                unsafe { $crate::rt::on_capture_inspect::<$inspector>(ptr, len) }
+            }
+        }
+    };
+}
+
+/// export `capscr_alloc` and an in-place `capscr_on_capture` for an
+/// [`inplace::InPlaceFilter`]. With `, tiled` the filter is row-local and also
+/// gets the `capscr_on_capture_tiled_{begin,band,end}` trio, each band
+/// rewritten in place. Declare the hooks as for [`export_band_filter!`].
+#[macro_export]
+macro_rules! export_in_place_filter {
+    ($filter:ty) => {
+        #[cfg(target_arch = "wasm32")]
+        mod __capscr_exports {
+            #[allow(unused_imports)]
+            use super::*;
+
+            #[no_mangle]
+            pub extern "C" fn capscr_alloc(size: i32) -> i32 {
+                $crate::rt::alloc(size)
+            }
+
+            #[no_mangle]
+            pub extern "C" fn capscr_on_capture(ptr: i32, len: i32) -> i64 {
+                unsafe { $crate::rt::on_capture_in_place::<$filter>(ptr, len) }
+            }
+        }
+    };
+    ($filter:ty, tiled) => {
+        $crate::export_in_place_filter!($filter);
+
+        #[cfg(target_arch = "wasm32")]
+        mod __capscr_tiled_exports {
+            #[allow(unused_imports)]
+            use super::*;
+
+            static mut TILED: Option<$crate::inplace::TiledInPlace<$filter>> = None;
+
+            fn tiled() -> &'static mut Option<$crate::inplace::TiledInPlace<$filter>> {
+                // SAFETY: single-threaded wasm; calls are serialised by the host
+                unsafe { &mut *core::ptr::addr_of_mut!(TILED) }
+            }
+
+            #[no_mangle]
+            pub extern "C" fn capscr_on_capture_tiled_begin(ptr: i32, len: i32) -> i64 {
+                unsafe { $crate::rt::tiled_in_place_begin(tiled(), ptr, len) }
+            }
+
+            #[no_mangle]
+            pub extern "C" fn capscr_on_capture_tiled_band(ptr: i32, len: i32) -> i64 {
+                unsafe { $crate::rt::tiled_in_place_band(tiled(), ptr, len) }
+            }
+
+            #[no_mangle]
+            pub extern "C" fn capscr_on_capture_tiled_end() -> i64 {
+                $crate::rt::tiled_in_place_end(tiled())
+            }
+        }
+    };
+}
@@ -0,0 +1,22 @@
+//! capscr's log, via the host `log` import on wasm and stderr natively.
+
+pub const ERROR: i32 = 0;
+pub const WARN: i32 = 1;
+pub const INFO: i32 = 2;
+pub const DEBUG: i32 = 3;
+
+/// write `msg` to capscr's log at `level`.
+pub fn log(level: i32, msg: &str) {
+    #[cfg(target_arch = "wasm32")]
+    crate::host::log(level, msg);
+    #[cfg(not(target_arch = "wasm32"))]
+    eprintln!("[{level}] {msg}");
+}
+
+pub fn info(msg: &str) {
+    log(INFO, msg)
+}
+
+pub fn warn(msg: &str) {
+    log(WARN, msg)
+}
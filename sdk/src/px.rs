@@ -0,0 +1,181 @@
+//! Pixel helpers shared by the image plugins.
+//!
+//! With the `simd` feature on wasm32 the fills use simd128; everywhere else
+//! (and for the ragged tail) the scalar loop runs. Both paths write identical
+//! bytes.
+
+/// append `count` copies of `color` to `out`.
+pub fn fill_extend(out: &mut Vec<u8>, color: [u8; 4], count: usize) {
+    let start = out.len();
+    out.resize(start + count * 4, 0);
+    fill(&mut out[start..], color);
+}
+
+/// overwrite every pixel of `dst` (whole rgba pixels) with `color`.
+pub fn fill(dst: &mut [u8], color: [u8; 4]) {
+    #[cfg(all(feature = "simd", target_arch = "wasm32"))]
+    // SAFETY: simd128 is a module-level feature on wasm; an engine without it
+    // rejects the module at load time rather than trapping here
+    let dst = unsafe { simd::fill(dst, color) };
+    fill_scalar(dst, color);
+}
+
+pub fn fill_scalar(dst: &mut [u8], color: [u8; 4]) {
+    for px in dst.chunks_exact_mut(4) {
+        px.copy_from_slice(&color);
+    }
+}
+
+/// BT.601 luma of an sRGB pixel, in integer arithmetic: the same value in
+/// every plugin that thresholds or maps it.
+pub fn luma(px: &[u8]) -> u8 {
+    ((px[0] as u32 * 299 + px[1] as u32 * 587 + px[2] as u32 * 114) / 1000) as u8
+}
+
+/// straight-alpha "source over": `src` composited onto `dst`.
+pub fn over(src: [u8; 4], dst: [u8; 4]) -> [u8; 4] {
+    match (src[3], dst[3]) {
+        (255, _) | (_, 0) => src,
+        (0, _) => dst,
+        (sa, da) => {
+            let (sa, da) = (sa as u32, da as u32);
+            // out_a = sa + da(1 - sa), all ×255
+            let oa = sa * 255 + da * (255 - sa);
+            let mix = |s: u8, d: u8| {
+                ((s as u32 * sa * 255 + d as u32 * da * (255 - sa) + oa / 2) / oa) as u8
+            };
+            [
+                mix(src[0], dst[0]),
+                mix(src[1], dst[1]),
+                mix(src[2], dst[2]),
+                ((oa + 127) / 255) as u8,
+            ]
+        }
+    }
+}
+
+// samples per pixel side when measuring arc coverage
+const SUPERSAMPLE: u32 = 8;
+
+/// anti-aliased coverage (0..=255) of a rounded corner of `radius`: an
+/// `radius`×`radius` table, row-major, with `[0]` the outermost pixel. The
+/// same table serves all four corners, mirrored.
+pub fn corner_mask(radius: u32) -> Vec<u8> {
+    let r = radius as f32;
+    let n = SUPERSAMPLE;
+    let mut mask = Vec::with_capacity(radius as usize * radius as usize);
+    for y in 0..radius {
+        for x in 0..radius {
+            let mut inside = 0;
+            for sy in 0..n {
+                for sx in 0..n {
+                    let dx = r - (x as f32 + (sx as f32 + 0.5) / n as f32);
+                    let dy = r - (y as f32 + (sy as f32 + 0.5) / n as f32);
+                    inside += (dx * dx + dy * dy <= r * r) as u32;
+                }
+            }
+            mask.push(((inside * 255 + n * n / 2) / (n * n)) as u8);
+        }
+    }
+    mask
+}
+
+/// the rounded-corner alpha mask of a `width`×`height` image.
+pub struct Corners {
+    width: u32,
+    height: u32,
+    radius: u32,
+    mask: Vec<u8>,
+}
+
+impl Corners {
+    /// `radius` is capped at half the shorter side.
+    pub fn new(width: u32, height: u32, radius: u32) -> Corners {
+        let radius = radius.min(width.min(height) / 2);
+        Corners {
+            width,
+            height,
+            radius,
+            mask: corner_mask(radius),
+        }
+    }
+
+    pub fn radius(&self) -> u32 {
+        self.radius
+    }
+
+    /// distance into the corner from the nearer of top and bottom, if row `y`
+    /// crosses one.
+    fn corner_row(&self, y: u32) -> Option<u32> {
+        let (h, r) = (self.height, self.radius);
+        if y < r {
+            Some(y)
+        } else if y >= h - r {
+            Some(h - 1 - y)
+        } else {
+            None
+        }
+    }
+
+    /// true if row `y` has any pixel the mask cuts into.
+    pub fn rounds_row(&self, y: u32) -> bool {
+        self.corner_row(y).is_some()
+    }
+
+    /// the coverage of pixel (x, y); 255 away from the corners.
+    pub fn coverage(&self, x: u32, y: u32) -> u8 {
+        let (w, r) = (self.width, self.radius);
+        let dx = if x < r {
+            x
+        } else if x >= w - r {
+            w - 1 - x
+        } else {
+            return 255;
+        };
+        match self.corner_row(y) {
+            Some(dy) => self.mask[(dy * r + dx) as usize],
+            None => 255,
+        }
+    }
+
+    /// scale the alpha of whole rows starting at row `y`.
+    pub fn apply(&self, y: u32, rows: &mut [u8]) {
+        let (w, r) = (self.width, self.radius);
+        for (row, y) in rows.chunks_exact_mut(w as usize * 4).zip(y..) {
+            let Some(dy) = self.corner_row(y) else {
+                continue;
+            };
+            let mask = &self.mask[(dy * r) as usize..][..r as usize];
+            for (dx, &coverage) in (0..r).zip(mask) {
+                if coverage == 255 {
+                    continue;
+                }
+                for x in [dx, w - 1 - dx] {
+                    let a = &mut row[x as usize * 4 + 3];
+                    *a = scale_alpha(*a, coverage);
+                }
+            }
+        }
+    }
+}
+
+/// scale an alpha byte by a 0..=255 coverage.
+pub fn scale_alpha(a: u8, coverage: u8) -> u8 {
+    ((a as u32 * coverage as u32 + 127) / 255) as u8
+}
+
+#[cfg(all(feature = "simd", target_arch = "wasm32"))]
+mod simd {
+    use core::arch::wasm32::*;
+
+    /// fill 16-byte chunks; returns the tail still to do.
+    #[target_feature(enable = "simd128")]
+    pub unsafe fn fill(dst: &mut [u8], color: [u8; 4]) -> &mut [u8] {
+        let v = u32x4_splat(u32::from_le_bytes(color));
+        let mut chunks = dst.chunks_exact_mut(16);
+        for chunk in &mut chunks {
+            v128_store(chunk.as_mut_ptr() as *mut v128, v);
+        }
+        chunks.into_remainder()
+    }
+}
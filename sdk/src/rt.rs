@@ -0,0 +1,289 @@
+//! Hook glue behind the `export_*!` macros. wasm32 only.
+//!
+//! capscr serialises hook calls per plugin and the guest is single-threaded,
+//! so the shared buffers below are only ever touched by one call at a time.
+
+use crate::abi::{
+    pack, pack_size, read_u32, ABORT, BAND_HEADER_LEN, CAPTURE_HEADER_LEN, CONTINUE, IN_PLACE,
+};
+use crate::band::{self, BandFilter, Tiled};
+use crate::filter::{self, Filter};
+use crate::host::HostConfig;
+use crate::image::{Capture, Header};
+use crate::inplace::{InPlaceFilter, TiledInPlace};
+use crate::inspect::Inspector;
+
+// the host writes every payload here via capscr_alloc: hook inputs, config_get
+// values, bands. OUTPUT holds whatever we hand back; the host copies it out
+// before the next call reuses it.
+static mut SCRATCH: Vec<u8> = Vec::new();
+static mut OUTPUT: Vec<u8> = Vec::new();
+
+pub fn alloc(size: i32) -> i32 {
+    let size = size.max(0) as usize;
+    // SAFETY: see module docs
+    unsafe {
+        let buf = &mut *core::ptr::addr_of_mut!(SCRATCH);
+        buf.clear();
+        buf.reserve(size);
+        buf.as_mut_ptr() as i32
+    }
+}
+
+/// take ownership of the payload the host just wrote, leaving SCRATCH empty so
+/// host calls made while we work on it (config_get) can't overwrite it. No copy
+/// when (ptr,len) is the SCRATCH allocation, which it always is in practice.
+///
+/// # Safety
+/// (ptr,len) must be the region the host wrote for the current hook call.
+pub unsafe fn take_input(ptr: i32, len: i32) -> Option<Vec<u8>> {
+    if ptr < 0 || len < 0 {
+        return None;
+    }
+    let len = len as usize;
+    let buf = &mut *core::ptr::addr_of_mut!(SCRATCH);
+    if buf.as_ptr() as i32 == ptr && len <= buf.capacity() {
+        let mut owned = core::mem::take(buf);
+        // the host initialised these bytes before calling the hook
+        owned.set_len(len);
+        Some(owned)
+    } else {
+        Some(core::slice::from_raw_parts(ptr as *const u8, len).to_vec())
+    }
+}
+
+/// hand a taken input back so its capacity is reused by the next capture.
+pub fn restore_input(mut input: Vec<u8>) {
+    input.clear();
+    // SAFETY: see module docs
+    unsafe { *core::ptr::addr_of_mut!(SCRATCH) = input }
+}
+
+/// borrow the (ptr,len) payload in place, for payloads that need no host
+/// call while they're alive.
+///
+/// # Safety
+/// as for [`take_input`].
+pub unsafe fn borrow_input<'a>(ptr: i32, len: i32) -> Option<&'a [u8]> {
+    if ptr < 0 || len < 0 {
+        return None;
+    }
+    Some(core::slice::from_raw_parts(ptr as *const u8, len as usize))
+}
+
+/// as [`borrow_input`], mutably.
+///
+/// # Safety
+/// as for [`take_input`].
+pub unsafe fn borrow_input_mut<'a>(ptr: i32, len: i32) -> Option<&'a mut [u8]> {
+    if ptr < 0 || len < 0 {
+        return None;
+    }
+    Some(core::slice::from_raw_parts_mut(
+        ptr as *mut u8,
+        len as usize,
+    ))
+}
+
+fn output() -> &'static mut Vec<u8> {
+    // SAFETY: see module docs
+    unsafe { &mut *core::ptr::addr_of_mut!(OUTPUT) }
+}
+
+fn reply(out: &[u8]) -> i64 {
+    if out.is_empty() {
+        CONTINUE
+    } else {
+        pack(out.as_ptr() as usize, out.len())
+    }
+}
+
+/// `capscr_on_capture` for a band filter: the whole image is one band.
+///
+/// # Safety
+/// as for [`take_input`].
+pub unsafe fn on_capture<F: BandFilter>(ptr: i32, len: i32) -> i64 {
+    let Some(input) = take_input(ptr, len) else {
+        return CONTINUE;
+    };
+    let out = output();
+    let ret = match Capture::parse(&input) {
+        Some(cap) if band::apply::<F>(&cap, &HostConfig, out) => reply(out),
+        _ => CONTINUE,
+    };
+    restore_input(input);
+    ret
+}
+
+/// `capscr_on_capture` for a whole-image filter.
+///
+/// # Safety
+/// as for [`take_input`].
+pub unsafe fn on_capture_filter<F: Filter>(ptr: i32, len: i32) -> i64 {
+    let Some(input) = take_input(ptr, len) else {
+        return CONTINUE;
+    };
+    let out = output();
+    let ret = match Capture::parse(&input) {
+        Some(cap) if filter::apply::<F>(&cap, &HostConfig, out) => reply(out),
+        _ => CONTINUE,
+    };
+    restore_input(input);
+    ret
+}
+
+/// `capscr_on_capture_tiled_begin`: payload `[w][h][mode]`; returns the output
+/// size `(w << 32) | h`, or 0 to leave the capture untouched.
+///
+/// # Safety
+/// as for [`take_input`].
+pub unsafe fn tiled_begin<F: BandFilter>(state: &mut Option<Tiled<F>>, ptr: i32, len: i32) -> i64 {
+    *state = None;
+    // copy the header out before config reads reuse SCRATCH
+    let Some(header) = borrow_input(ptr, len).and_then(Header::parse) else {
+        return CONTINUE;
+    };
+    match Tiled::<F>::begin(header, &HostConfig) {
+        Some(t) => {
+            let (w, h) = t.output_size();
+            *state = Some(t);
+            pack_size(w, h)
+        }
+        None => CONTINUE,
+    }
+}
+
+/// `capscr_on_capture_tiled_band`: payload `[y][rows]`; returns the output rows
+/// this band completed (packed), 0 for none yet, or -1 to abort the stream.
+///
+/// # Safety
+/// as for [`take_input`].
+pub unsafe fn tiled_band<F: BandFilter>(state: &mut Option<Tiled<F>>, ptr: i32, len: i32) -> i64 {
+    let (Some(t), Some(input)) = (state.as_mut(), borrow_input(ptr, len)) else {
+        return ABORT;
+    };
+    if input.len() < BAND_HEADER_LEN {
+        *state = None;
+        return ABORT;
+    }
+    let y = read_u32(input, 0);
+    let out = output();
+    if !t.band(y, &input[BAND_HEADER_LEN..], out) {
+        *state = None;
+        return ABORT;
+    }
+    reply(out)
+}
+
+/// `capscr_on_capture_tiled_end`: returns any remaining output rows (packed),
+/// 0 for none, or -1 if the stream was incomplete.
+pub fn tiled_end<F: BandFilter>(state: &mut Option<Tiled<F>>) -> i64 {
+    let Some(mut t) = state.take() else {
+        return ABORT;
+    };
+    let out = output();
+    if !t.finish(out) {
+        return ABORT;
+    }
+    reply(out)
+}
+
+/// `capscr_on_capture_inspect`: payload as for `capscr_on_capture`, no reply.
+///
+/// # Safety
+/// as for [`take_input`].
+pub unsafe fn on_capture_inspect<I: Inspector>(ptr: i32, len: i32) {
+    // taken rather than borrowed: the inspector may read config mid-way
+    let Some(input) = take_input(ptr, len) else {
+        return;
+    };
+    if let Some(cap) = Capture::parse(&input) {
+        I::inspect(&cap, &HostConfig);
+    }
+    restore_input(input);
+}
+
+/// `capscr_on_capture` for an in-place filter: rewrite the rgba inside the
+/// input blob and return [`IN_PLACE`].
+///
+/// # Safety
+/// as for [`take_input`].
+pub unsafe fn on_capture_in_place<F: InPlaceFilter>(ptr: i32, len: i32) -> i64 {
+    let Some(mut input) = take_input(ptr, len) else {
+        return CONTINUE;
+    };
+    let header = Capture::parse(&input).map(|cap| cap.header);
+    // config reads during begin land in the (now empty) SCRATCH, not the input
+    let ret = match header.and_then(|h| F::begin(&h, &HostConfig)) {
+        Some(mut filter) => {
+            filter.rows(0, &mut input[CAPTURE_HEADER_LEN..]);
+            if input.as_ptr() as i32 != ptr {
+                // take_input had to copy; put the result where the host looks
+                core::ptr::copy_nonoverlapping(input.as_ptr(), ptr as *mut u8, input.len());
+            }
+            IN_PLACE
+        }
+        None => CONTINUE,
+    };
+    // clearing doesn't touch the bytes; they stay put until the next alloc
+    restore_input(input);
+    ret
+}
+
+/// `capscr_on_capture_tiled_begin` for a row-local in-place filter.
+///
+/// # Safety
+/// as for [`take_input`].
+pub unsafe fn tiled_in_place_begin<F: InPlaceFilter>(
+    state: &mut Option<TiledInPlace<F>>,
+    ptr: i32,
+    len: i32,
+) -> i64 {
+    *state = None;
+    let Some(header) = borrow_input(ptr, len).and_then(Header::parse) else {
+        return CONTINUE;
+    };
+    match TiledInPlace::<F>::begin(header, &HostConfig) {
+        Some(t) => {
+            let (w, h) = t.output_size();
+            *state = Some(t);
+            pack_size(w, h)
+        }
+        None => CONTINUE,
+    }
+}
+
+/// `capscr_on_capture_tiled_band` for a row-local in-place filter: the band is
+/// rewritten inside the payload and its rows returned from there, uncopied.
+///
+/// # Safety
+/// as for [`take_input`].
+pub unsafe fn tiled_in_place_band<F: InPlaceFilter>(
+    state: &mut Option<TiledInPlace<F>>,
+    ptr: i32,
+    len: i32,
+) -> i64 {
+    let (Some(t), Some(input)) = (state.as_mut(), borrow_input_mut(ptr, len)) else {
+        return ABORT;
+    };
+    if input.len() < BAND_HEADER_LEN {
+        *state = None;
+        return ABORT;
+    }
+    let y = read_u32(input, 0);
+    let rows = &mut input[BAND_HEADER_LEN..];
+    if !t.band(y, rows) {
+        *state = None;
+        return ABORT;
+    }
+    reply(rows)
+}
+
+/// `capscr_on_capture_tiled_end` for a row-local in-place filter: every row
+/// has already been returned, so this only checks none are missing.
+pub fn tiled_in_place_end<F: InPlaceFilter>(state: &mut Option<TiledInPlace<F>>) -> i64 {
+    match state.take() {
+        Some(t) if t.finish() => CONTINUE,
+        _ => ABORT,
+    }
+}
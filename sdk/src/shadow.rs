@@ -0,0 +1,143 @@
+//! Soft shadows: an image's alpha, Gaussian blurred and placed on a canvas.
+//!
+//! The blur is separable. For an opaque image the shadow is a blurred
+//! rectangle, which is exactly the outer product of two blurred 1-D steps — so
+//! only `width + height` samples are convolved, however big the image.
+//! Translucent pixels (rounded corners) are subtracted back out tile by tile,
+//! so only the tiles that have any pay for a 2-D blur.
+
+use crate::blur::{self, Edge};
+
+// image tiles checked for translucency; each translucent one becomes a patch
+const TILE: usize = 256;
+
+/// the blurred shadow of an image, one canvas row at a time.
+pub struct Shadow {
+    // the opaque rectangle's shadow, per canvas column and row
+    xs: Vec<f32>,
+    ys: Vec<f32>,
+    patches: Vec<Patch>,
+}
+
+/// the blurred deficit (1 - alpha) of one image tile, on a canvas region
+/// `reach` wider than the tile on every side so nothing is clipped. `x`/`y`
+/// may lie off the canvas.
+struct Patch {
+    x: i64,
+    y: i64,
+    w: usize,
+    h: usize,
+    data: Vec<f32>,
+}
+
+/// `opaque[y]` for [`Shadow::new`]: whether every pixel of row `y` of `rgba`
+/// (`width` wide) has alpha 255.
+pub fn opaque_rows(rgba: &[u8], width: u32) -> Vec<bool> {
+    rgba.chunks_exact(width as usize * 4)
+        .map(|row| row.chunks_exact(4).all(|px| px[3] == 255))
+        .collect()
+}
+
+impl Shadow {
+    /// the shadow of a `width`×`height` image whose pixel (x, y) has alpha
+    /// `alpha(x, y)`, blurred with `kernel` and with its top-left at `at` on a
+    /// `canvas`-sized canvas. Any part may fall off the canvas. `opaque[y]`
+    /// must be true only for rows with no alpha below 255.
+    pub fn new(
+        (width, height): (u32, u32),
+        alpha: impl Fn(usize, usize) -> u8,
+        opaque: &[bool],
+        at: (i64, i64),
+        canvas: (u32, u32),
+        kernel: &[f32],
+    ) -> Shadow {
+        Shadow {
+            xs: profile(at.0, width, canvas.0, kernel),
+            ys: profile(at.1, height, canvas.1, kernel),
+            patches: patches((width, height), alpha, opaque, at, kernel),
+        }
+    }
+
+    /// shadow coverage, 0..=1, of every pixel of canvas row `y`.
+    pub fn row(&self, y: usize, out: &mut [f32]) {
+        let sy = self.ys[y];
+        for (o, x) in out.iter_mut().zip(&self.xs) {
+            *o = x * sy;
+        }
+        let y = y as i64;
+        for p in self
+            .patches
+            .iter()
+            .filter(|p| (p.y..p.y + p.h as i64).contains(&y))
+        {
+            let deficit = &p.data[(y - p.y) as usize * p.w..][..p.w];
+            // clip the patch to the canvas
+            let skip = (-p.x).max(0) as usize;
+            let from = p.x.max(0) as usize;
+            for (o, d) in out.iter_mut().skip(from).zip(deficit.iter().skip(skip)) {
+                *o = (*o - d).max(0.0);
+            }
+        }
+    }
+}
+
+/// one axis of the opaque shadow: a blurred step, 1 over `[from, from + len)`,
+/// sampled on `[0, canvas)`. Blurred on a line `reach` longer at both ends so
+/// a step running off the canvas still blurs as if it didn't.
+fn profile(from: i64, len: u32, canvas: u32, kernel: &[f32]) -> Vec<f32> {
+    let reach = (kernel.len() / 2) as i64;
+    let n = canvas as i64 + 2 * reach;
+    let (lo, hi) = (
+        (from + reach).clamp(0, n),
+        (from + reach + len as i64).clamp(0, n),
+    );
+    let mut step = vec![0.0; n as usize];
+    step[lo as usize..hi as usize].fill(1.0);
+    let mut blurred = vec![0.0; n as usize];
+    blur::blur_line(&step, &mut blurred, kernel, Edge::Zero);
+    blurred.drain(..reach as usize);
+    blurred.truncate(canvas as usize);
+    blurred
+}
+
+/// blur is linear, so the shadow of a translucent image is the opaque
+/// rectangle's minus the blurred deficit — which only needs computing around
+/// the tiles that actually have translucent pixels (for a window with rounded
+/// corners, just the four corners).
+fn patches(
+    (w, h): (u32, u32),
+    alpha: impl Fn(usize, usize) -> u8,
+    opaque: &[bool],
+    at: (i64, i64),
+    kernel: &[f32],
+) -> Vec<Patch> {
+    let (w, h) = (w as usize, h as usize);
+    let reach = kernel.len() / 2;
+    let mut patches = Vec::new();
+    for ty in (0..h).step_by(TILE) {
+        for tx in (0..w).step_by(TILE) {
+            let (tw, th) = (TILE.min(w - tx), TILE.min(h - ty));
+            let translucent =
+                (ty..ty + th).any(|y| !opaque[y] && (tx..tx + tw).any(|x| alpha(x, y) != 255));
+            if !translucent {
+                continue;
+            }
+            let (pw, ph) = (tw + 2 * reach, th + 2 * reach);
+            let mut data = vec![0.0; pw * ph];
+            for y in 0..th {
+                for x in 0..tw {
+                    data[(y + reach) * pw + x + reach] = 1.0 - alpha(tx + x, ty + y) as f32 / 255.0;
+                }
+            }
+            blur::blur_plane(&mut data, pw, ph, kernel, Edge::Zero);
+            patches.push(Patch {
+                x: at.0 + (tx as i64) - reach as i64,
+                y: at.1 + (ty as i64) - reach as i64,
+                w: pw,
+                h: ph,
+                data,
+            });
+        }
+    }
+    patches
+}
@@ -0,0 +1,81 @@
+//! sRGB ↔ linear light, for filters that average or blend colours.
+//!
+//! sRGB bytes are perceptually spaced, so averaging them directly darkens
+//! anything that mixes light and dark (thin text, edges); averaging in linear
+//! light doesn't. [`Lut`] does both directions by table: 256 entries one way,
+//! and the other a binary search over the 255 points halfway between codes,
+//! so a round trip is exact and no `powf` runs per pixel.
+
+/// sRGB-encoded value (0..=1) to linear light (0..=1).
+pub fn to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// linear light (0..=1) to sRGB-encoded (0..=1).
+pub fn from_linear(l: f32) -> f32 {
+    if l <= 0.003_130_8 {
+        l * 12.92
+    } else {
+        1.055 * l.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// lookup tables between sRGB bytes and linear light.
+pub struct Lut {
+    linear: [f32; 256],
+    // linear value halfway (in sRGB) between codes i and i + 1
+    mids: [f32; 255],
+}
+
+impl Default for Lut {
+    fn default() -> Lut {
+        Lut::new()
+    }
+}
+
+impl Lut {
+    pub fn new() -> Lut {
+        Lut {
+            linear: core::array::from_fn(|i| to_linear(i as f32 / 255.0)),
+            mids: core::array::from_fn(|i| to_linear((i as f32 + 0.5) / 255.0)),
+        }
+    }
+
+    /// the linear light of sRGB byte `c`.
+    pub fn linear(&self, c: u8) -> f32 {
+        self.linear[c as usize]
+    }
+
+    /// the nearest sRGB byte to linear light `l` (clamped to 0..=1).
+    pub fn encode(&self, l: f32) -> u8 {
+        self.mids.partition_point(|&m| m < l) as u8
+    }
+
+    /// a straight-alpha sRGB pixel as premultiplied linear `[r, g, b, a]`,
+    /// every channel 0..=1 — the form that averages and blurs correctly.
+    pub fn premultiplied(&self, px: [u8; 4]) -> [f32; 4] {
+        let a = px[3] as f32 / 255.0;
+        [
+            self.linear(px[0]) * a,
+            self.linear(px[1]) * a,
+            self.linear(px[2]) * a,
+            a,
+        ]
+    }
+
+    /// back from [`premultiplied`](Self::premultiplied); out-of-range input
+    /// (a sharpening kernel's overshoot) is clamped — after unpremultiplying,
+    /// so an overshoot in alpha doesn't shift the colour.
+    pub fn unpremultiplied(&self, v: [f32; 4]) -> [u8; 4] {
+        let a = v[3];
+        if a * 255.0 < 0.5 {
+            return [0; 4];
+        }
+        let c = |l: f32| self.encode(l / a);
+        [c(v[0]), c(v[1]), c(v[2]), (a.min(1.0) * 255.0 + 0.5) as u8]
+    }
+}
@@ -0,0 +1,88 @@
+//! capscr plugin: unsharp-mask sharpening on luma, for downscaled text.
+//!
+//! Each pixel's BT.601 luma is compared with a Gaussian blur of it (see
+//! [`capscr_plugin_sdk::blur`]); where they differ by at least `threshold`
+//! levels, `amount` times the difference is added to r, g and b alike. Only
+//! lightness is sharpened, so edges between colours get no coloured fringes,
+//! and the threshold keeps flat areas and gentle gradients from turning into
+//! noise. Alpha is never touched.
+//!
+//! `min_dimension` skips captures too small to have been downscaled. The blur
+//! needs rows above and below, so there's no tiled variant; the rewrite
+//! happens in place (capscr 0.6+) and the only extra memory is the blurred
+//! luma plane.
+
+use capscr_plugin_sdk::blur::{self, Edge};
+use capscr_plugin_sdk::config::Config;
+use capscr_plugin_sdk::image::Header;
+use capscr_plugin_sdk::inplace::InPlaceFilter;
+
+const DEFAULT_RADIUS: f32 = 1.0;
+const MAX_RADIUS: f32 = 10.0;
+const DEFAULT_AMOUNT: f32 = 1.0;
+const MAX_AMOUNT: f32 = 5.0;
+const DEFAULT_THRESHOLD: u32 = 2;
+const MAX_DIM: u32 = 16384;
+
+capscr_plugin_sdk::export_in_place_filter!(Sharpen);
+
+pub struct Sharpen {
+    width: usize,
+    height: usize,
+    kernel: Vec<f32>,
+    amount: f32,
+    threshold: f32,
+}
+
+impl InPlaceFilter for Sharpen {
+    fn begin(header: &Header, config: &dyn Config) -> Option<Self> {
+        if let Some(modes) = config.modes("only_modes") {
+            if !modes.contains(&header.mode) {
+                return None;
+            }
+        }
+        let min = config.u32("min_dimension", 0, 0..=MAX_DIM);
+        if header.width.max(header.height) < min {
+            return None; // too small to have been downscaled
+        }
+        let amount = config.f32("amount", DEFAULT_AMOUNT, 0.0..=MAX_AMOUNT);
+        if amount == 0.0 {
+            return None;
+        }
+        Some(Sharpen {
+            width: header.width as usize,
+            height: header.height as usize,
+            kernel: blur::kernel(config.f32("radius", DEFAULT_RADIUS, 0.1..=MAX_RADIUS)),
+            amount,
+            threshold: config.u32("threshold", DEFAULT_THRESHOLD, 0..=255) as f32,
+        })
+    }
+
+    fn rows(&mut self, _y: u32, rgba: &mut [u8]) {
+        let mut blurred: Vec<f32> = rgba.chunks_exact(4).map(luma).collect();
+        blur::blur_plane(
+            &mut blurred,
+            self.width,
+            self.height,
+            &self.kernel,
+            Edge::Clamp,
+        );
+        for (px, &soft) in rgba.chunks_exact_mut(4).zip(&blurred) {
+            let detail = luma(px) - soft;
+            if detail.abs() < self.threshold {
+                continue;
+            }
+            let delta = (detail * self.amount).round() as i32;
+            for c in &mut px[..3] {
+                *c = (*c as i32 + delta).clamp(0, 255) as u8;
+            }
+        }
+    }
+}
+
+/// BT.601 luma, 0..=255, unrounded. Not `px::luma`: the detail is the
+/// difference between this and its blur, scaled by up to 5×, and the integer
+/// luma's truncation would come through that as up to 5 levels of banding.
+fn luma(px: &[u8]) -> f32 {
+    0.299 * px[0] as f32 + 0.587 * px[1] as f32 + 0.114 * px[2] as f32
+}
@@ -0,0 +1,146 @@
+//! capscr plugin: turn dark-themed captures light without ruining colours.
+//!
+//! Plain inversion maps every channel to 255 − v, which also flips hues — a
+//! blue link turns orange, a photo turns into its negative. This inverts
+//! lightness only, in OKLab (see [`oklab`]): L becomes 1 − L while a and b,
+//! hue and chroma, stay; a colour that falls outside sRGB at its new
+//! lightness gives up chroma rather than hue. Alpha is never touched.
+//!
+//! Two optional exemptions leave content as it was: pixels above a chroma
+//! (strongly coloured icons, charts, brand colours), and photographic areas,
+//! found block by block from their luma variance (see [`photo_blocks`]). The
+//! latter needs the whole image, so there's no tiled variant; the rewrite
+//! happens in place (capscr 0.6+).
+
+pub mod oklab;
+
+use capscr_plugin_sdk::config::Config;
+use capscr_plugin_sdk::image::Header;
+use capscr_plugin_sdk::inplace::InPlaceFilter;
+use capscr_plugin_sdk::px::luma;
+use capscr_plugin_sdk::srgb::Lut;
+
+const MAX_CHROMA: f32 = 0.4; // past anything sRGB can show
+const DEFAULT_PHOTO_VARIANCE: u32 = 100;
+const MAX_PHOTO_VARIANCE: u32 = 10_000;
+const CACHE_BITS: u32 = 14;
+/// the side of the square blocks photo detection looks at.
+pub const BLOCK: usize = 16;
+
+capscr_plugin_sdk::export_in_place_filter!(SmartInvert);
+
+pub struct SmartInvert {
+    width: usize,
+    /// OKLab chroma above which a pixel is left as it is; 0 for none
+    keep_chroma: f32,
+    /// the luma variance above which a block may be a photo, or None to
+    /// invert photos too
+    photo_variance: Option<u32>,
+    lut: Box<Lut>,
+    // recent conversions, direct-mapped by colour: r,g,b + 1 (0 = empty) and
+    // the result. UI has few distinct colours, and most of them in runs
+    cache: Vec<(u32, [u8; 3])>,
+}
+
+impl InPlaceFilter for SmartInvert {
+    fn begin(header: &Header, config: &dyn Config) -> Option<Self> {
+        if let Some(modes) = config.modes("only_modes") {
+            if !modes.contains(&header.mode) {
+                return None;
+            }
+        }
+        let keep_photos = config.bool("keep_photos", false);
+        let photo_variance = config.u32(
+            "photo_variance",
+            DEFAULT_PHOTO_VARIANCE,
+            1..=MAX_PHOTO_VARIANCE,
+        );
+        Some(SmartInvert {
+            width: header.width as usize,
+            keep_chroma: config.f32("keep_saturated", 0.0, 0.0..=MAX_CHROMA),
+            photo_variance: keep_photos.then_some(photo_variance),
+            lut: Box::new(Lut::new()),
+            cache: vec![(0, [0; 3]); 1 << CACHE_BITS],
+        })
+    }
+
+    fn rows(&mut self, _y: u32, rgba: &mut [u8]) {
+        let w = self.width;
+        let blocks_across = w.div_ceil(BLOCK);
+        let photos = match self.photo_variance {
+            Some(variance) => photo_blocks(rgba, w, variance),
+            None => Vec::new(),
+        };
+        for (y, row) in rgba.chunks_exact_mut(w * 4).enumerate() {
+            for (x, px) in row.chunks_exact_mut(4).enumerate() {
+                if !photos.is_empty() && photos[y / BLOCK * blocks_across + x / BLOCK] {
+                    continue;
+                }
+                let rgb = [px[0], px[1], px[2]];
+                let key = u32::from_le_bytes([px[0], px[1], px[2], 0]) + 1;
+                let slot = (key.wrapping_mul(0x9e37_79b1) >> (32 - CACHE_BITS)) as usize;
+                if self.cache[slot].0 != key {
+                    self.cache[slot] = (key, self.invert(rgb));
+                }
+                px[..3].copy_from_slice(&self.cache[slot].1);
+            }
+        }
+    }
+}
+
+impl SmartInvert {
+    /// `rgb` with its OKLab lightness inverted, or as it is if it's more
+    /// colourful than `keep_chroma`.
+    fn invert(&self, rgb: [u8; 3]) -> [u8; 3] {
+        let [l, a, b] = oklab::from_srgb(&self.lut, rgb);
+        if self.keep_chroma > 0.0 && (a * a + b * b).sqrt() > self.keep_chroma {
+            return rgb;
+        }
+        oklab::to_srgb(&self.lut, [1.0 - l, a, b])
+    }
+}
+
+/// which `BLOCK`-square blocks of `rgba` (`w` pixels a row; row-major, edge
+/// blocks partial) look photographic: BT.601 luma variance of at least
+/// `variance`, and fewer than half of the horizontally neighbouring pixels
+/// identical — dense text is just as varied, but sits on runs of flat
+/// background. A busy block with no busy neighbour (an icon, a word) doesn't
+/// count either.
+pub fn photo_blocks(rgba: &[u8], w: usize, variance: u32) -> Vec<bool> {
+    let h = rgba.len() / 4 / w;
+    let (bw, bh) = (w.div_ceil(BLOCK), h.div_ceil(BLOCK));
+    // per block: luma sum, sum of squares, pixels, identical pairs, pairs
+    let mut stats = vec![[0u64; 5]; bw * bh];
+    for (y, row) in rgba.chunks_exact(w * 4).enumerate() {
+        let blocks = &mut stats[y / BLOCK * bw..][..bw];
+        for (x, px) in row.chunks_exact(4).enumerate() {
+            let s = &mut blocks[x / BLOCK];
+            let luma = luma(px) as u64;
+            s[0] += luma;
+            s[1] += luma * luma;
+            s[2] += 1;
+            if x % BLOCK > 0 {
+                s[3] += (px == &row[(x - 1) * 4..x * 4]) as u64;
+                s[4] += 1;
+            }
+        }
+    }
+    let busy: Vec<bool> = stats
+        .iter()
+        .map(|&[sum, squares, n, same, pairs]| {
+            // n²·variance = n·Σy² − (Σy)²
+            let spread = n * squares - sum * sum;
+            spread >= variance as u64 * n * n && same * 2 < pairs
+        })
+        .collect();
+    (0..bw * bh)
+        .map(|i| {
+            let (bx, by) = (i % bw, i / bw);
+            busy[i]
+                && ((bx > 0 && busy[i - 1])
+                    || (bx + 1 < bw && busy[i + 1])
+                    || (by > 0 && busy[i - bw])
+                    || (by + 1 < bh && busy[i + bw]))
+        })
+        .collect()
+}
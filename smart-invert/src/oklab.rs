@@ -0,0 +1,52 @@
+//! Björn Ottosson's OKLab: a perceptual colour space whose L tracks
+//! lightness and whose a, b carry hue and chroma, so lightness can change
+//! without hues drifting the way they do in HSL.
+
+use capscr_plugin_sdk::srgb::Lut;
+
+/// sRGB bytes to `[L, a, b]`.
+pub fn from_srgb(lut: &Lut, rgb: [u8; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb.map(|c| lut.linear(c));
+    let l = (0.412_221_46 * r + 0.536_332_55 * g + 0.051_445_995 * b).cbrt();
+    let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+    let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+    [
+        0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+        1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+        0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+    ]
+}
+
+/// `[L, a, b]` to linear sRGB, possibly outside 0..=1.
+pub fn to_linear([lightness, a, b]: [f32; 3]) -> [f32; 3] {
+    let l = (lightness + 0.396_337_78 * a + 0.215_803_76 * b).powi(3);
+    let m = (lightness - 0.105_561_346 * a - 0.063_854_17 * b).powi(3);
+    let s = (lightness - 0.089_484_18 * a - 1.291_485_5 * b).powi(3);
+    [
+        4.076_741_7 * l - 3.307_711_6 * m + 0.230_969_94 * s,
+        -1.268_438 * l + 2.609_757_4 * m - 0.341_319_38 * s,
+        -0.004_196_086_3 * l - 0.703_418_6 * m + 1.707_614_7 * s,
+    ]
+}
+
+/// `[L, a, b]` to sRGB bytes. A colour outside the sRGB gamut keeps its L
+/// and hue and gives up chroma until it fits, rather than being clipped per
+/// channel (which shifts hue).
+pub fn to_srgb(lut: &Lut, lab: [f32; 3]) -> [u8; 3] {
+    let fits = |rgb: &[f32; 3]| rgb.iter().all(|v| (-1e-4..=1.0 + 1e-4).contains(v));
+    let mut rgb = to_linear(lab);
+    if !fits(&rgb) {
+        // bisect the chroma scale: `lo` fits, `hi` doesn't
+        let (mut lo, mut hi) = (0.0, 1.0);
+        for _ in 0..8 {
+            let mid = (lo + hi) / 2.0;
+            if fits(&to_linear([lab[0], lab[1] * mid, lab[2] * mid])) {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        rgb = to_linear([lab[0], lab[1] * lo, lab[2] * lo]);
+    }
+    rgb.map(|v| lut.encode(v))
+}
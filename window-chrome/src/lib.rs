@@ -0,0 +1,303 @@
+//! capscr plugin: frame each capture as an app window.
+//!
+//! Draws a title bar above the capture — macOS traffic lights, Windows
+//! caption buttons, or a bare strip — with an optional caption, a one-pixel
+//! frame around the lot, and anti-aliased rounded outer corners. Everything is
+//! drawn procedurally (the caption uses a built-in 5×7 bitmap font), so there
+//! are no asset files. Pure byte math, no dependencies.
+//!
+//! Every output row depends only on its own input row, so it's a
+//! [`BandFilter`] and also exports the tiled hook.
+
+mod font;
+
+use capscr_plugin_sdk::band::BandFilter;
+use capscr_plugin_sdk::config::Config;
+use capscr_plugin_sdk::image::Header;
+use capscr_plugin_sdk::px::{self, Corners};
+
+const MAX_SCALE: u32 = 4;
+const MAX_RADIUS: u32 = 256;
+const MAX_TITLE: usize = 256; // chars; the bar's width truncates well before this
+const MAX_DIM: u32 = 16384; // host rejects larger replacements; bail to match
+
+/// which window system's title bar to draw.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// traffic lights on the left, caption centred
+    MacOs,
+    /// minimise/maximise/close on the right, caption on the left
+    Windows,
+    /// a plain strip, caption centred
+    Minimal,
+}
+
+const STYLES: &[(&str, Style)] = &[
+    ("macos", Style::MacOs),
+    ("windows", Style::Windows),
+    ("minimal", Style::Minimal),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    Light,
+    Dark,
+}
+
+const THEMES: &[(&str, Theme)] = &[("light", Theme::Light), ("dark", Theme::Dark)];
+
+struct Palette {
+    bar: [u8; 4],
+    frame: [u8; 4],
+    text: [u8; 4],
+}
+
+const LIGHT: Palette = Palette {
+    bar: [246, 246, 246, 255],
+    frame: [208, 208, 208, 255],
+    text: [77, 77, 77, 255],
+};
+
+const DARK: Palette = Palette {
+    bar: [42, 42, 42, 255],
+    frame: [72, 72, 72, 255],
+    text: [222, 222, 222, 255],
+};
+
+// macOS close / minimise / zoom
+const TRAFFIC_LIGHTS: [[u8; 4]; 3] = [[255, 95, 87, 255], [254, 188, 46, 255], [40, 200, 64, 255]];
+
+capscr_plugin_sdk::export_band_filter!(WindowChrome);
+
+pub struct WindowChrome {
+    width: u32,
+    style: Style,
+    palette: &'static Palette,
+    // every size below is in px at this scale (2 for a HiDPI look)
+    scale: u32,
+    frame: u32,
+    bar: u32,
+    caption: Caption,
+    corners: Corners,
+    out_width: u32,
+    out_height: u32,
+    // next output row to emit
+    y: u32,
+}
+
+/// the caption, already truncated to fit, and where it's drawn in the bar.
+struct Caption {
+    text: Vec<char>,
+    // font pixel size
+    size: u32,
+    x: u32,
+    y: u32,
+}
+
+impl BandFilter for WindowChrome {
+    fn begin(header: &Header, config: &dyn Config) -> Option<Self> {
+        let (w, h) = (header.width, header.height);
+        if let Some(modes) = config.modes("only_modes") {
+            if !modes.contains(&header.mode) {
+                return None;
+            }
+        }
+        let style = config.choice("style", Style::MacOs, STYLES);
+        let theme = config.choice("theme", Theme::Light, THEMES);
+        let s = config.u32("scale", 1, 1..=MAX_SCALE);
+        let radius = config.u32("corner_radius", 10 * s, 0..=MAX_RADIUS);
+        let title = config.string("title", "");
+
+        let frame = s;
+        let bar = s * match style {
+            Style::MacOs => 28,
+            Style::Windows => 32,
+            Style::Minimal if title.is_empty() => 12,
+            Style::Minimal => 20,
+        };
+        let nw = w.checked_add(2 * frame)?;
+        let nh = h.checked_add(bar + 2 * frame)?;
+        if nw > MAX_DIM || nh > MAX_DIM {
+            return None; // the host would reject an oversized replacement anyway
+        }
+        Some(WindowChrome {
+            width: w,
+            style,
+            palette: match theme {
+                Theme::Light => &LIGHT,
+                Theme::Dark => &DARK,
+            },
+            scale: s,
+            frame,
+            bar,
+            caption: Caption::layout(&title, style, s, bar, nw),
+            corners: Corners::new(nw, nh, radius),
+            out_width: nw,
+            out_height: nh,
+            y: 0,
+        })
+    }
+
+    fn output_size(&self) -> (u32, u32) {
+        (self.out_width, self.out_height)
+    }
+
+    fn band(&mut self, rows: &[u8], out: &mut Vec<u8>) {
+        while self.y < self.frame + self.bar {
+            self.chrome_row(out);
+        }
+        let frame = self.frame as usize;
+        for row in rows.chunks_exact(self.width as usize * 4) {
+            let start = out.len();
+            px::fill_extend(out, self.palette.frame, frame);
+            out.extend_from_slice(row);
+            px::fill_extend(out, self.palette.frame, frame);
+            self.corners.apply(self.y, &mut out[start..]);
+            self.y += 1;
+        }
+    }
+
+    fn finish(&mut self, out: &mut Vec<u8>) {
+        // the frame below the capture
+        while self.y < self.out_height {
+            let start = out.len();
+            px::fill_extend(out, self.palette.frame, self.out_width as usize);
+            self.corners.apply(self.y, &mut out[start..]);
+            self.y += 1;
+        }
+    }
+}
+
+impl WindowChrome {
+    /// emit output row `self.y`, which lies in the frame or title bar above
+    /// the capture.
+    fn chrome_row(&mut self, out: &mut Vec<u8>) {
+        let start = out.len();
+        let (nw, frame) = (self.out_width, self.frame);
+        if self.y < frame {
+            px::fill_extend(out, self.palette.frame, nw as usize);
+        } else {
+            let y = self.y - frame;
+            for x in 0..nw {
+                let c = if x < frame || x >= nw - frame {
+                    self.palette.frame
+                } else {
+                    self.bar_px(x, y)
+                };
+                out.extend_from_slice(&c);
+            }
+        }
+        self.corners.apply(self.y, &mut out[start..]);
+        self.y += 1;
+    }
+
+    /// the colour of title-bar pixel (x, y): x across the whole output, y
+    /// down from the top of the bar.
+    fn bar_px(&self, x: u32, y: u32) -> [u8; 4] {
+        let (s, p) = (self.scale, self.palette);
+        if self.style != Style::Minimal && y >= self.bar - s {
+            return p.frame; // the line between title bar and content
+        }
+        if self.caption.lit(x, y) {
+            return p.text;
+        }
+        let (fx, fy) = (x as f32 + 0.5, y as f32 + 0.5);
+        let cy = self.bar as f32 / 2.0;
+        match self.style {
+            Style::MacOs => {
+                // 12 px lights, 20 px apart, the first 14 px in
+                for (i, &light) in TRAFFIC_LIGHTS.iter().enumerate() {
+                    let cx = (self.frame + 14 * s + 20 * s * i as u32) as f32;
+                    let d = ((fx - cx).powi(2) + (fy - cy).powi(2)).sqrt();
+                    let coverage = (6.0 * s as f32 - d + 0.5).clamp(0.0, 1.0);
+                    if coverage > 0.0 {
+                        return mix(p.bar, light, coverage);
+                    }
+                }
+                p.bar
+            }
+            Style::Windows => {
+                // 46 px buttons from the right: close, maximise, minimise
+                let right = self.out_width - self.frame;
+                let cell = 46 * s;
+                let from_right = right - 1 - x;
+                if from_right >= 3 * cell {
+                    return p.bar;
+                }
+                let i = from_right / cell;
+                let cx = (right - i * cell) as f32 - cell as f32 / 2.0;
+                let (dx, dy) = (fx - cx, fy - cy);
+                // 10 px glyphs, 1 px strokes
+                let (half, stroke) = (5.0 * s as f32, s as f32);
+                let inside = dx.abs() < half && dy.abs() < half;
+                let on = match i {
+                    0 => {
+                        inside
+                            && ((dx - dy).abs() < stroke * 0.75 || (dx + dy).abs() < stroke * 0.75)
+                    }
+                    1 => inside && (dx.abs() > half - stroke || dy.abs() > half - stroke),
+                    _ => dx.abs() < half && (dy + stroke / 2.0).abs() < stroke / 2.0,
+                };
+                if on {
+                    p.text
+                } else {
+                    p.bar
+                }
+            }
+            Style::Minimal => p.bar,
+        }
+    }
+}
+
+impl Caption {
+    /// fit `title` into the space the style leaves free, at a font size
+    /// about half the bar's height.
+    fn layout(title: &str, style: Style, s: u32, bar: u32, nw: u32) -> Caption {
+        let size = (bar / 14).max(1);
+        let advance = font::ADVANCE * size;
+        // keep clear of the traffic lights / caption buttons
+        let (margin, reserved) = match style {
+            Style::MacOs => (74 * s, 2 * 74 * s),
+            Style::Windows => (12 * s, 12 * s + 3 * 46 * s),
+            Style::Minimal => (8 * s, 16 * s),
+        };
+        let room = (nw.saturating_sub(reserved) / advance) as usize;
+        let mut text: Vec<char> = title.chars().take(MAX_TITLE).collect();
+        if text.len() > room {
+            text.truncate(room.saturating_sub(3));
+            if room >= 3 {
+                text.extend("...".chars());
+            }
+        }
+        let width = (text.len() as u32 * advance).saturating_sub(size);
+        let x = match style {
+            Style::Windows => margin,
+            _ => nw.saturating_sub(width) / 2,
+        };
+        Caption {
+            text,
+            size,
+            x,
+            y: bar.saturating_sub(font::HEIGHT * size) / 2,
+        }
+    }
+
+    /// whether bar pixel (x, y) is part of the caption.
+    fn lit(&self, x: u32, y: u32) -> bool {
+        if x < self.x || y < self.y {
+            return false;
+        }
+        let (dx, dy) = (x - self.x, (y - self.y) / self.size);
+        let advance = font::ADVANCE * self.size;
+        let Some(&c) = self.text.get((dx / advance) as usize) else {
+            return false;
+        };
+        font::lit(c, dx % advance / self.size, dy)
+    }
+}
+
+/// `a` blended towards `b` by `t` (0..=1).
+fn mix(a: [u8; 4], b: [u8; 4], t: f32) -> [u8; 4] {
+    let m = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t + 0.5) as u8;
+    [m(a[0], b[0]), m(a[1], b[1]), m(a[2], b[2]), m(a[3], b[3])]
+}